@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE user_token (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    purpose VARCHAR(32) NOT NULL,
+    value CHAR(64) NOT NULL UNIQUE,
+    datetime_ttl DATETIME NOT NULL,
+    datetime_used DATETIME DEFAULT NULL,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES `user`(id),
+    INDEX idx_user_token_user_purpose (user_id, purpose)
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE user_token DROP FOREIGN KEY fk_user_token_email_id;
+ALTER TABLE user_token DROP COLUMN email_id;
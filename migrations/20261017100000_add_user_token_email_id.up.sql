@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE user_token
+    ADD COLUMN email_id BIGINT DEFAULT NULL,
+    ADD CONSTRAINT fk_user_token_email_id FOREIGN KEY (email_id) REFERENCES user_email(id);
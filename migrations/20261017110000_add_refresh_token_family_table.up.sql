@@ -0,0 +1,10 @@
+-- Add up migration script here
+CREATE TABLE refresh_token_family (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    value VARCHAR(255) NOT NULL UNIQUE,
+    user_id BIGINT NOT NULL,
+    current_jti VARCHAR(255) NOT NULL,
+    datetime_revoked DATETIME DEFAULT NULL,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES `user`(id)
+);
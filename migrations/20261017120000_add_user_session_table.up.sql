@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE user_session (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    value VARCHAR(255) NOT NULL UNIQUE,
+    user_id BIGINT NOT NULL,
+    family_id BIGINT NOT NULL UNIQUE,
+    user_agent VARCHAR(512) DEFAULT NULL,
+    ip_address VARCHAR(45) DEFAULT NULL,
+    datetime_last_used DATETIME NOT NULL,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES `user`(id),
+    FOREIGN KEY (family_id) REFERENCES refresh_token_family(id)
+);
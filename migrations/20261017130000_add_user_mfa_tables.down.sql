@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE user_mfa_recovery_code;
+DROP TABLE user_mfa;
@@ -0,0 +1,20 @@
+-- Add up migration script here
+CREATE TABLE user_mfa (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id BIGINT NOT NULL UNIQUE,
+    secret VARCHAR(255) NOT NULL,
+    last_used_step BIGINT DEFAULT NULL,
+    datetime_enabled DATETIME DEFAULT NULL,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES `user`(id)
+);
+
+CREATE TABLE user_mfa_recovery_code (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    value CHAR(64) NOT NULL,
+    datetime_used DATETIME DEFAULT NULL,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES `user`(id),
+    INDEX idx_user_mfa_recovery_code_user_value (user_id, value)
+);
@@ -0,0 +1,9 @@
+-- Add up migration script here
+CREATE TABLE login_attempt (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    value CHAR(64) NOT NULL UNIQUE,
+    failures INT NOT NULL DEFAULT 0,
+    datetime_last_failure DATETIME NOT NULL,
+    datetime_blocked_until DATETIME DEFAULT NULL,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
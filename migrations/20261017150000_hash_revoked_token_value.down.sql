@@ -0,0 +1,8 @@
+-- Add down migration script here
+-- digests can't be turned back into tokens, the rows are useless to the old code
+DELETE FROM revoked_token;
+
+ALTER TABLE revoked_token
+    DROP INDEX idx_revoked_token_datetime_ttl,
+    DROP INDEX idx_revoked_token_value,
+    MODIFY value TEXT NOT NULL;
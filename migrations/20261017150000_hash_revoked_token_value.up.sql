@@ -0,0 +1,8 @@
+-- Add up migration script here
+-- revoked tokens are looked up by their sha256 hex digest instead of the raw jwt
+UPDATE revoked_token SET value = SHA2(value, 256);
+
+ALTER TABLE revoked_token
+    MODIFY value CHAR(64) NOT NULL,
+    ADD INDEX idx_revoked_token_value (value),
+    ADD INDEX idx_revoked_token_datetime_ttl (datetime_ttl);
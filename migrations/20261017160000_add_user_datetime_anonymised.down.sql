@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE `user`
+    DROP INDEX idx_user_datetime_deleted,
+    DROP COLUMN datetime_anonymised;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- datetime_deleted is when deletion was asked for, datetime_anonymised is when the personal data was removed
+ALTER TABLE `user`
+    ADD COLUMN datetime_anonymised DATETIME DEFAULT NULL,
+    ADD INDEX idx_user_datetime_deleted (datetime_deleted);
@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE user_export (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    value VARCHAR(255) NOT NULL UNIQUE,
+    user_id BIGINT NOT NULL,
+    datetime_ready DATETIME DEFAULT NULL,
+    datetime_failed DATETIME DEFAULT NULL,
+    datetime_ttl DATETIME NOT NULL,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES `user`(id),
+    INDEX idx_user_export_datetime_ttl (datetime_ttl)
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE user_handle_history;
+DROP TABLE user_handle;
@@ -0,0 +1,22 @@
+-- Add up migration script here
+CREATE TABLE user_handle (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id BIGINT NOT NULL UNIQUE,
+    value VARCHAR(32) NOT NULL,
+    normalized VARCHAR(32) NOT NULL UNIQUE,
+    datetime_taken DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES `user`(id)
+);
+
+CREATE TABLE user_handle_history (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id BIGINT NOT NULL,
+    value VARCHAR(32) NOT NULL,
+    normalized VARCHAR(32) NOT NULL,
+    datetime_released DATETIME NOT NULL,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES `user`(id),
+    INDEX idx_user_handle_history_normalized (normalized, datetime_released),
+    INDEX idx_user_handle_history_user_id (user_id, datetime_released)
+);
@@ -0,0 +1,8 @@
+-- Add up migration script here
+CREATE TABLE user_avatar (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id BIGINT NOT NULL UNIQUE,
+    value VARCHAR(255) NOT NULL UNIQUE,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES `user`(id)
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE user_invite_use;
+DROP TABLE user_invite;
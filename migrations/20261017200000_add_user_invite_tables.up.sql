@@ -0,0 +1,25 @@
+-- Add up migration script here
+CREATE TABLE user_invite (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    value VARCHAR(255) NOT NULL UNIQUE,
+    code VARCHAR(64) NOT NULL UNIQUE,
+    created_by_user_id BIGINT NOT NULL,
+    email VARCHAR(255) DEFAULT NULL,
+    domain VARCHAR(255) DEFAULT NULL,
+    max_uses INT UNSIGNED NOT NULL,
+    uses INT UNSIGNED NOT NULL DEFAULT 0,
+    datetime_ttl DATETIME NOT NULL,
+    datetime_revoked DATETIME DEFAULT NULL,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (created_by_user_id) REFERENCES `user`(id),
+    INDEX idx_user_invite_created_by_user_id (created_by_user_id)
+);
+
+CREATE TABLE user_invite_use (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    invite_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL UNIQUE,
+    datetime_created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (invite_id) REFERENCES user_invite(id),
+    FOREIGN KEY (user_id) REFERENCES `user`(id)
+);
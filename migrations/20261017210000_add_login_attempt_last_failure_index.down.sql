@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE login_attempt
+    DROP INDEX idx_login_attempt_datetime_last_failure;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- old rows are purged by their last failure
+ALTER TABLE login_attempt
+    ADD INDEX idx_login_attempt_datetime_last_failure (datetime_last_failure);
@@ -0,0 +1,1011 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use actix_web::cookie::SameSite;
+use actix_web::http::Method;
+use actix_web::http::header::HeaderName;
+
+// used when APP_CONFIG_FILE is not set, only if it exists
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+// Everything the server needs to start, loaded and validated once at startup
+// shared as web::Data<AppConfig>
+//
+// Every setting is looked up in this order:
+//  - environment variable (.env is loaded into the environment first)
+//  - TOML file from APP_CONFIG_FILE, or ./config.toml if it exists
+//  - default, for settings that have one
+// e.g. SERVER_PORT or
+//      [server]
+//      port = 5000
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub jwt: JwtConfig,
+    pub cookie: CookieConfig,
+    pub cors: CorsConfig,
+    pub mail: MailConfig,
+    pub account: AccountConfig,
+    pub tokens: TokenConfig,
+    pub unconfirmed: UnconfirmedConfig,
+    pub mfa: MfaConfig,
+    pub login_throttle: LoginThrottleConfig,
+    pub rate_limit: RateLimitConfig,
+    pub registration: RegistrationConfig,
+    pub magic_link: MagicLinkConfig,
+    pub export: ExportConfig,
+    pub blob_store: BlobStoreConfig,
+    pub avatar: AvatarConfig,
+    pub tasks: TasksConfig,
+}
+
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub metrics_enabled: bool, // serves /internal/metrics/* - keep it off or unreachable from outside
+    pub public_url: String,    // base of links to this api, e.g. export downloads
+    pub trusted_proxy: bool, // client ip comes from Forwarded / X-Forwarded-For - only behind a proxy that overwrites them
+}
+
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_seconds: u64,
+    pub idle_timeout_seconds: u64,
+}
+
+pub struct JwtConfig {
+    pub access_algorithm: String,      // HS512, EdDSA or RS256
+    pub access_secret: Option<String>, // HS512 only
+    pub access_keys: Option<String>,   // EdDSA and RS256 only - kid=path,kid=path
+    pub refresh_secret: String,
+    pub access_token_expiration_minutes: i64,
+    pub refresh_token_expiration_days: i64,
+    pub refresh_reuse_rotate_authid: bool, // a reused refresh token also logs its user out everywhere
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+}
+
+// refresh token cookie and its csrf token cookie
+// with host_prefix the cookies are named __Host-... - browsers then only accept them
+// from a Secure origin, with Path=/ and no Domain, so no sibling subdomain can set or overwrite them
+pub struct CookieConfig {
+    pub secure: bool, // on unless set to false, e.g. for local development over http
+    pub same_site: SameSite,
+    pub domain: Option<String>,
+    pub host_prefix: bool,
+    pub max_age_days: i64, // same as REFRESH_TOKEN_EXPIRATION_DAYS
+}
+
+impl CookieConfig {
+    pub fn refresh_cookie_name(&self) -> &'static str {
+        if self.host_prefix {
+            "__Host-refresh_token"
+        } else {
+            "refresh_token"
+        }
+    }
+
+    pub fn csrf_cookie_name(&self) -> &'static str {
+        if self.host_prefix {
+            "__Host-csrf_token"
+        } else {
+            "csrf_token"
+        }
+    }
+
+    pub fn path(&self) -> &'static str {
+        if self.host_prefix { "/" } else { "/api/auth" }
+    }
+}
+
+// browsers send the refresh cookie cross-origin only to the allowed origins
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allow_any_origin_dev: bool, // instead of allowed_origins, without credentials - local development only
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub max_age_seconds: usize, // how long browsers may cache a preflight
+}
+
+pub struct MailConfig {
+    pub mailer: String,             // only "log" for now
+    pub outbox_dir: Option<String>, // log mailer also writes every mail here
+    pub frontend_url: String,       // base of the links in emails
+}
+
+pub struct AccountConfig {
+    pub deletion_grace_days: i64, // time to change one's mind before personal data is removed
+    pub reactivation_token_expiration_minutes: i64,
+    pub handle_change_interval_days: i64, // least time between two handle changes
+    pub handle_redirect_days: i64, // an old handle keeps pointing to its user, and can't be taken, for this long
+}
+
+// single use tokens sent by email
+pub struct TokenConfig {
+    pub confirmation_expiration_hours: i64,
+    pub password_reset_expiration_minutes: i64,
+    pub email_change_expiration_hours: i64,
+}
+
+// what users that have not confirmed their email may do
+pub struct UnconfirmedConfig {
+    pub allow_refresh: bool,
+}
+
+// authenticator app 2FA
+pub struct MfaConfig {
+    pub issuer: String, // name authenticator apps show next to the code
+    pub pending_token_expiration_minutes: i64, // time to enter the code after the password
+}
+
+// How failed logins slow down and lock out further attempts
+pub struct LoginThrottleConfig {
+    pub store: String, // "mysql" is shared by every instance, "memory" only suits a single instance
+    pub max_failures_per_email: u32, // lockout after this many failures for one email
+    pub max_failures_per_ip: u32, // lockout after this many failures from one ip
+    pub backoff_base_seconds: i64, // wait after the first failure, doubles with every failure
+    pub backoff_max_seconds: i64,
+    pub lockout_minutes: i64,
+    pub failure_window_minutes: i64, // failures older than this are forgotten
+}
+
+// Requests allowed per client (or per user, or for everyone) before 429
+// e.g. RATE_LIMIT_AUTH_LOGIN=10/60 or
+//      [rate_limit]
+//      auth_login = "10/60"
+pub struct RateLimitConfig {
+    pub api: RateLimitRule, // overall limit of every /api route
+    pub auth_login: RateLimitRule,
+    pub auth_login_mfa: RateLimitRule,
+    pub auth_magic_link: RateLimitRule,
+    pub auth_magic_link_login: RateLimitRule,
+    pub auth_register: RateLimitRule,
+    pub auth_register_all: RateLimitRule,
+    pub auth_register_handle: RateLimitRule,
+    pub auth_refresh: RateLimitRule,
+    pub auth_confirm_resend: RateLimitRule,
+    pub auth_password_forgot: RateLimitRule,
+    pub auth_account_reactivate_request: RateLimitRule,
+    pub invites_create: RateLimitRule,
+    pub users_handle: RateLimitRule,
+    pub users_avatar: RateLimitRule,
+    pub users_export: RateLimitRule,
+}
+
+// capacity requests at once, tokens come back at capacity per period_seconds
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitRule {
+    pub capacity: u32,
+    pub period_seconds: u64,
+}
+
+pub struct RegistrationConfig {
+    pub mode: String, // "open" or "invite" - invite needs an invite code to register
+    pub invite_creator_pids: Vec<String>, // users that may create invites, there are no admin roles yet
+    pub invite_expiration_days: i64,      // when the creator doesn't pick one
+}
+
+impl RegistrationConfig {
+    pub fn invite_only(&self) -> bool {
+        self.mode.to_lowercase() == "invite"
+    }
+}
+
+// passwordless login with a link sent by email
+pub struct MagicLinkConfig {
+    pub enabled: bool,
+    pub expiration_minutes: i64,
+}
+
+#[derive(Clone)]
+pub struct ExportConfig {
+    pub dir: String,            // where archives are written
+    pub signing_secret: String, // signs download links
+    pub link_expiration_minutes: i64,
+    pub retention_hours: i64, // archive is deleted after this
+}
+
+pub struct BlobStoreConfig {
+    pub kind: String, // only "local" for now
+    pub dir: String,  // local store keeps its files here
+}
+
+#[derive(Clone)]
+pub struct AvatarConfig {
+    pub max_bytes: usize, // of the uploaded file
+    pub min_dimension: u32,
+    pub max_dimension: u32, // width and height, checked before decoding
+}
+
+// background tasks started with the server
+pub struct TasksConfig {
+    pub revoked_token_purge_interval_minutes: u64,
+    pub account_anonymise_interval_minutes: u64,
+    pub export_purge_interval_minutes: u64,
+    pub login_attempt_purge_interval_minutes: u64,
+}
+
+impl AppConfig {
+    // every problem is collected so a bad deploy shows all of them at once
+    pub fn load() -> Result<AppConfig, String> {
+        let mut source = ConfigSource::new()?;
+
+        // refresh token and its cookie live just as long
+        let refresh_token_expiration_days = source.number(
+            "REFRESH_TOKEN_EXPIRATION_DAYS",
+            "jwt.refresh_token_expiration_days",
+            7,
+        );
+
+        let config = AppConfig {
+            server: ServerConfig {
+                host: source.string("SERVER_HOST", "server.host", "0.0.0.0"),
+                port: source.number("SERVER_PORT", "server.port", 5000),
+                metrics_enabled: source.boolean(
+                    "SERVER_METRICS_ENABLED",
+                    "server.metrics_enabled",
+                    false,
+                ),
+                public_url: source
+                    .string(
+                        "SERVER_PUBLIC_URL",
+                        "server.public_url",
+                        "http://localhost:5000",
+                    )
+                    .trim_end_matches('/')
+                    .to_string(),
+                trusted_proxy: source.boolean(
+                    "SERVER_TRUSTED_PROXY",
+                    "server.trusted_proxy",
+                    false,
+                ),
+            },
+            database: DatabaseConfig {
+                url: source.required("DATABASE_URL", "database.url"),
+                max_connections: source.number(
+                    "DATABASE_MAX_CONNECTIONS",
+                    "database.max_connections",
+                    5,
+                ),
+                min_connections: source.number(
+                    "DATABASE_MIN_CONNECTIONS",
+                    "database.min_connections",
+                    1,
+                ),
+                acquire_timeout_seconds: source.number(
+                    "DATABASE_ACQUIRE_TIMEOUT_SECONDS",
+                    "database.acquire_timeout_seconds",
+                    5,
+                ),
+                idle_timeout_seconds: source.number(
+                    "DATABASE_IDLE_TIMEOUT_SECONDS",
+                    "database.idle_timeout_seconds",
+                    300,
+                ),
+            },
+            jwt: JwtConfig {
+                access_algorithm: source.string(
+                    "JWT_ACCESS_ALGORITHM",
+                    "jwt.access_algorithm",
+                    "HS512",
+                ),
+                access_secret: source.optional("JWT_ACCESS_SECRET", "jwt.access_secret"),
+                access_keys: source.optional("JWT_ACCESS_KEYS", "jwt.access_keys"),
+                refresh_secret: source.required("JWT_REFRESH_SECRET", "jwt.refresh_secret"),
+                access_token_expiration_minutes: source.number(
+                    "ACCESS_TOKEN_EXPIRATION_MINUTES",
+                    "jwt.access_token_expiration_minutes",
+                    15,
+                ),
+                refresh_token_expiration_days,
+                refresh_reuse_rotate_authid: source.boolean(
+                    "REFRESH_REUSE_ROTATE_AUTHID",
+                    "jwt.refresh_reuse_rotate_authid",
+                    false,
+                ),
+                issuer: source.optional("JWT_ISSUER", "jwt.issuer"),
+                audience: source.optional("JWT_AUDIENCE", "jwt.audience"),
+            },
+            cookie: CookieConfig {
+                secure: source.boolean("COOKIE_SECURE", "cookie.secure", true),
+                same_site: source.same_site("COOKIE_SAME_SITE", "cookie.same_site", SameSite::Lax),
+                domain: source.optional("COOKIE_DOMAIN", "cookie.domain"),
+                host_prefix: source.boolean("COOKIE_HOST_PREFIX", "cookie.host_prefix", false),
+                max_age_days: refresh_token_expiration_days,
+            },
+            cors: CorsConfig {
+                allowed_origins: source.list("CORS_ALLOWED_ORIGINS", "cors.allowed_origins", &[]),
+                allow_any_origin_dev: source.boolean(
+                    "CORS_ALLOW_ANY_ORIGIN_DEV",
+                    "cors.allow_any_origin_dev",
+                    false,
+                ),
+                allowed_methods: source.list(
+                    "CORS_ALLOWED_METHODS",
+                    "cors.allowed_methods",
+                    &["GET", "POST", "PUT", "PATCH", "DELETE"],
+                ),
+                allowed_headers: source.list(
+                    "CORS_ALLOWED_HEADERS",
+                    "cors.allowed_headers",
+                    &["Authorization", "Content-Type", "Accept", "X-CSRF-Token"],
+                ),
+                max_age_seconds: source.number(
+                    "CORS_MAX_AGE_SECONDS",
+                    "cors.max_age_seconds",
+                    3600,
+                ),
+            },
+            mail: MailConfig {
+                mailer: source.string("MAILER", "mail.mailer", "log"),
+                outbox_dir: source.optional("MAIL_OUTBOX_DIR", "mail.outbox_dir"),
+                frontend_url: source
+                    .string("FRONTEND_URL", "mail.frontend_url", "http://localhost:3000")
+                    .trim_end_matches('/')
+                    .to_string(),
+            },
+            account: AccountConfig {
+                deletion_grace_days: source.number(
+                    "ACCOUNT_DELETION_GRACE_DAYS",
+                    "account.deletion_grace_days",
+                    30,
+                ),
+                reactivation_token_expiration_minutes: source.number(
+                    "ACCOUNT_REACTIVATION_TOKEN_EXPIRATION_MINUTES",
+                    "account.reactivation_token_expiration_minutes",
+                    60,
+                ),
+                handle_change_interval_days: source.number(
+                    "ACCOUNT_HANDLE_CHANGE_INTERVAL_DAYS",
+                    "account.handle_change_interval_days",
+                    30,
+                ),
+                handle_redirect_days: source.number(
+                    "ACCOUNT_HANDLE_REDIRECT_DAYS",
+                    "account.handle_redirect_days",
+                    90,
+                ),
+            },
+            tokens: TokenConfig {
+                confirmation_expiration_hours: source.number(
+                    "CONFIRMATION_TOKEN_EXPIRATION_HOURS",
+                    "tokens.confirmation_expiration_hours",
+                    24,
+                ),
+                password_reset_expiration_minutes: source.number(
+                    "PASSWORD_RESET_TOKEN_EXPIRATION_MINUTES",
+                    "tokens.password_reset_expiration_minutes",
+                    30,
+                ),
+                email_change_expiration_hours: source.number(
+                    "EMAIL_CHANGE_TOKEN_EXPIRATION_HOURS",
+                    "tokens.email_change_expiration_hours",
+                    24,
+                ),
+            },
+            unconfirmed: UnconfirmedConfig {
+                allow_refresh: source.boolean(
+                    "UNCONFIRMED_ALLOW_REFRESH",
+                    "unconfirmed.allow_refresh",
+                    true,
+                ),
+            },
+            mfa: MfaConfig {
+                issuer: source.string("MFA_ISSUER", "mfa.issuer", "Makisama"),
+                pending_token_expiration_minutes: source.number(
+                    "MFA_PENDING_TOKEN_EXPIRATION_MINUTES",
+                    "mfa.pending_token_expiration_minutes",
+                    5,
+                ),
+            },
+            login_throttle: LoginThrottleConfig {
+                store: source.string("LOGIN_ATTEMPT_STORE", "login_throttle.store", "mysql"),
+                max_failures_per_email: source.number(
+                    "LOGIN_MAX_FAILURES_PER_EMAIL",
+                    "login_throttle.max_failures_per_email",
+                    5,
+                ),
+                max_failures_per_ip: source.number(
+                    "LOGIN_MAX_FAILURES_PER_IP",
+                    "login_throttle.max_failures_per_ip",
+                    20,
+                ),
+                backoff_base_seconds: source.number(
+                    "LOGIN_BACKOFF_BASE_SECONDS",
+                    "login_throttle.backoff_base_seconds",
+                    1,
+                ),
+                backoff_max_seconds: source.number(
+                    "LOGIN_BACKOFF_MAX_SECONDS",
+                    "login_throttle.backoff_max_seconds",
+                    300,
+                ),
+                lockout_minutes: source.number(
+                    "LOGIN_LOCKOUT_MINUTES",
+                    "login_throttle.lockout_minutes",
+                    15,
+                ),
+                failure_window_minutes: source.number(
+                    "LOGIN_FAILURE_WINDOW_MINUTES",
+                    "login_throttle.failure_window_minutes",
+                    15,
+                ),
+            },
+            rate_limit: RateLimitConfig {
+                api: source.rate_limit("RATE_LIMIT_API", "rate_limit.api", 300, 60),
+                auth_login: source.rate_limit(
+                    "RATE_LIMIT_AUTH_LOGIN",
+                    "rate_limit.auth_login",
+                    10,
+                    60,
+                ),
+                auth_login_mfa: source.rate_limit(
+                    "RATE_LIMIT_AUTH_LOGIN_MFA",
+                    "rate_limit.auth_login_mfa",
+                    10,
+                    60,
+                ),
+                auth_magic_link: source.rate_limit(
+                    "RATE_LIMIT_AUTH_MAGIC_LINK",
+                    "rate_limit.auth_magic_link",
+                    5,
+                    3600,
+                ),
+                auth_magic_link_login: source.rate_limit(
+                    "RATE_LIMIT_AUTH_MAGIC_LINK_LOGIN",
+                    "rate_limit.auth_magic_link_login",
+                    10,
+                    60,
+                ),
+                auth_register: source.rate_limit(
+                    "RATE_LIMIT_AUTH_REGISTER",
+                    "rate_limit.auth_register",
+                    5,
+                    3600,
+                ),
+                auth_register_all: source.rate_limit(
+                    "RATE_LIMIT_AUTH_REGISTER_ALL",
+                    "rate_limit.auth_register_all",
+                    200,
+                    3600,
+                ),
+                auth_register_handle: source.rate_limit(
+                    "RATE_LIMIT_AUTH_REGISTER_HANDLE",
+                    "rate_limit.auth_register_handle",
+                    30,
+                    60,
+                ),
+                auth_refresh: source.rate_limit(
+                    "RATE_LIMIT_AUTH_REFRESH",
+                    "rate_limit.auth_refresh",
+                    30,
+                    60,
+                ),
+                auth_confirm_resend: source.rate_limit(
+                    "RATE_LIMIT_AUTH_CONFIRM_RESEND",
+                    "rate_limit.auth_confirm_resend",
+                    5,
+                    3600,
+                ),
+                auth_password_forgot: source.rate_limit(
+                    "RATE_LIMIT_AUTH_PASSWORD_FORGOT",
+                    "rate_limit.auth_password_forgot",
+                    5,
+                    3600,
+                ),
+                auth_account_reactivate_request: source.rate_limit(
+                    "RATE_LIMIT_AUTH_ACCOUNT_REACTIVATE_REQUEST",
+                    "rate_limit.auth_account_reactivate_request",
+                    5,
+                    3600,
+                ),
+                invites_create: source.rate_limit(
+                    "RATE_LIMIT_INVITES_CREATE",
+                    "rate_limit.invites_create",
+                    30,
+                    3600,
+                ),
+                users_handle: source.rate_limit(
+                    "RATE_LIMIT_USERS_HANDLE",
+                    "rate_limit.users_handle",
+                    5,
+                    3600,
+                ),
+                users_avatar: source.rate_limit(
+                    "RATE_LIMIT_USERS_AVATAR",
+                    "rate_limit.users_avatar",
+                    10,
+                    3600,
+                ),
+                users_export: source.rate_limit(
+                    "RATE_LIMIT_USERS_EXPORT",
+                    "rate_limit.users_export",
+                    3,
+                    3600,
+                ),
+            },
+            registration: RegistrationConfig {
+                mode: source.string("REGISTRATION_MODE", "registration.mode", "open"),
+                invite_creator_pids: source.list(
+                    "REGISTRATION_INVITE_CREATOR_PIDS",
+                    "registration.invite_creator_pids",
+                    &[],
+                ),
+                invite_expiration_days: source.number(
+                    "REGISTRATION_INVITE_EXPIRATION_DAYS",
+                    "registration.invite_expiration_days",
+                    7,
+                ),
+            },
+            magic_link: MagicLinkConfig {
+                enabled: source.boolean("MAGIC_LINK_ENABLED", "magic_link.enabled", true),
+                expiration_minutes: source.number(
+                    "MAGIC_LINK_EXPIRATION_MINUTES",
+                    "magic_link.expiration_minutes",
+                    15,
+                ),
+            },
+            export: ExportConfig {
+                dir: source.string("EXPORT_DIR", "export.dir", "./exports"),
+                signing_secret: source.required("EXPORT_SIGNING_SECRET", "export.signing_secret"),
+                link_expiration_minutes: source.number(
+                    "EXPORT_LINK_EXPIRATION_MINUTES",
+                    "export.link_expiration_minutes",
+                    60,
+                ),
+                retention_hours: source.number(
+                    "EXPORT_RETENTION_HOURS",
+                    "export.retention_hours",
+                    24,
+                ),
+            },
+            blob_store: BlobStoreConfig {
+                kind: source.string("BLOB_STORE", "blob_store.kind", "local"),
+                dir: source.string("BLOB_STORE_DIR", "blob_store.dir", "./media"),
+            },
+            avatar: AvatarConfig {
+                max_bytes: source.number("AVATAR_MAX_BYTES", "avatar.max_bytes", 5 * 1024 * 1024),
+                min_dimension: source.number("AVATAR_MIN_DIMENSION", "avatar.min_dimension", 64),
+                max_dimension: source.number("AVATAR_MAX_DIMENSION", "avatar.max_dimension", 4096),
+            },
+            tasks: TasksConfig {
+                revoked_token_purge_interval_minutes: source.number(
+                    "REVOKED_TOKEN_PURGE_INTERVAL_MINUTES",
+                    "tasks.revoked_token_purge_interval_minutes",
+                    60,
+                ),
+                account_anonymise_interval_minutes: source.number(
+                    "ACCOUNT_ANONYMISE_INTERVAL_MINUTES",
+                    "tasks.account_anonymise_interval_minutes",
+                    60,
+                ),
+                export_purge_interval_minutes: source.number(
+                    "EXPORT_PURGE_INTERVAL_MINUTES",
+                    "tasks.export_purge_interval_minutes",
+                    60,
+                ),
+                login_attempt_purge_interval_minutes: source.number(
+                    "LOGIN_ATTEMPT_PURGE_INTERVAL_MINUTES",
+                    "tasks.login_attempt_purge_interval_minutes",
+                    60,
+                ),
+            },
+        };
+
+        config.validate(&mut source.errors);
+
+        if !source.errors.is_empty() {
+            return Err(format!(
+                "Invalid configuration:\n  - {}",
+                source.errors.join("\n  - ")
+            ));
+        }
+
+        Ok(config)
+    }
+
+    // rules between settings, single values are checked while loading
+    fn validate(&self, errors: &mut Vec<String>) {
+        if self.database.max_connections == 0 {
+            errors.push("DATABASE_MAX_CONNECTIONS must be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            errors.push(
+                "DATABASE_MIN_CONNECTIONS can't be more than DATABASE_MAX_CONNECTIONS".to_string(),
+            );
+        }
+
+        match self.jwt.access_algorithm.as_str() {
+            "HS512" => match &self.jwt.access_secret {
+                None => errors.push("JWT_ACCESS_SECRET must be set for HS512".to_string()),
+                Some(s) if *s == self.jwt.refresh_secret => errors
+                    .push("JWT_ACCESS_SECRET and JWT_REFRESH_SECRET must be different".to_string()),
+                Some(_) => {}
+            },
+            "EdDSA" | "RS256" => {
+                if self.jwt.access_keys.is_none() {
+                    errors.push(format!(
+                        "JWT_ACCESS_KEYS must be set for {}",
+                        self.jwt.access_algorithm
+                    ));
+                }
+            }
+            other => errors.push(format!(
+                "JWT_ACCESS_ALGORITHM must be HS512, EdDSA or RS256, got {}",
+                other
+            )),
+        }
+        if self.jwt.access_token_expiration_minutes <= 0 {
+            errors.push("ACCESS_TOKEN_EXPIRATION_MINUTES must be more than 0".to_string());
+        }
+        if self.jwt.refresh_token_expiration_days <= 0 {
+            errors.push("REFRESH_TOKEN_EXPIRATION_DAYS must be more than 0".to_string());
+        }
+
+        // browsers drop SameSite=None cookies that are not Secure
+        if self.cookie.same_site == SameSite::None && !self.cookie.secure {
+            errors.push("COOKIE_SAME_SITE=None requires COOKIE_SECURE=true".to_string());
+        }
+        if self.cookie.host_prefix {
+            if !self.cookie.secure {
+                errors.push("COOKIE_HOST_PREFIX requires COOKIE_SECURE=true".to_string());
+            }
+            if self.cookie.domain.is_some() {
+                errors.push("COOKIE_HOST_PREFIX can't be used with COOKIE_DOMAIN".to_string());
+            }
+        }
+
+        // an empty allow-list is a mistake unless any origin is asked for explicitly
+        match (
+            self.cors.allowed_origins.is_empty(),
+            self.cors.allow_any_origin_dev,
+        ) {
+            (true, false) => errors.push(
+                "CORS_ALLOWED_ORIGINS must be set, or CORS_ALLOW_ANY_ORIGIN_DEV=true for local development"
+                    .to_string(),
+            ),
+            (false, true) => errors.push(
+                "CORS_ALLOWED_ORIGINS can't be used with CORS_ALLOW_ANY_ORIGIN_DEV".to_string(),
+            ),
+            _ => {}
+        }
+        // the refresh cookie makes every cors request a credentialed one, browsers refuse those with *
+        if self.cors.allowed_origins.iter().any(|o| o == "*") {
+            errors.push(
+                "CORS_ALLOWED_ORIGINS can't contain *, list the origins of the frontend"
+                    .to_string(),
+            );
+        }
+        for method in &self.cors.allowed_methods {
+            if Method::from_bytes(method.as_bytes()).is_err() {
+                errors.push(format!(
+                    "CORS_ALLOWED_METHODS has an invalid method: {}",
+                    method
+                ));
+            }
+        }
+        for header in &self.cors.allowed_headers {
+            if HeaderName::from_bytes(header.as_bytes()).is_err() {
+                errors.push(format!(
+                    "CORS_ALLOWED_HEADERS has an invalid header: {}",
+                    header
+                ));
+            }
+        }
+
+        if self.mail.mailer.to_lowercase() != "log" {
+            errors.push(format!("MAILER must be log, got {}", self.mail.mailer));
+        }
+
+        if self.account.deletion_grace_days < 0 {
+            errors.push("ACCOUNT_DELETION_GRACE_DAYS can't be negative".to_string());
+        }
+        if self.account.reactivation_token_expiration_minutes <= 0 {
+            errors.push(
+                "ACCOUNT_REACTIVATION_TOKEN_EXPIRATION_MINUTES must be more than 0".to_string(),
+            );
+        }
+        if self.account.handle_change_interval_days < 0 {
+            errors.push("ACCOUNT_HANDLE_CHANGE_INTERVAL_DAYS can't be negative".to_string());
+        }
+        if self.account.handle_redirect_days < 0 {
+            errors.push("ACCOUNT_HANDLE_REDIRECT_DAYS can't be negative".to_string());
+        }
+
+        if self.tokens.confirmation_expiration_hours <= 0 {
+            errors.push("CONFIRMATION_TOKEN_EXPIRATION_HOURS must be more than 0".to_string());
+        }
+        if self.tokens.password_reset_expiration_minutes <= 0 {
+            errors.push("PASSWORD_RESET_TOKEN_EXPIRATION_MINUTES must be more than 0".to_string());
+        }
+        if self.tokens.email_change_expiration_hours <= 0 {
+            errors.push("EMAIL_CHANGE_TOKEN_EXPIRATION_HOURS must be more than 0".to_string());
+        }
+
+        if self.mfa.issuer.contains(':') {
+            errors.push(
+                "MFA_ISSUER can't contain :, authenticator apps split the label on it".to_string(),
+            );
+        }
+        if self.mfa.pending_token_expiration_minutes <= 0 {
+            errors.push("MFA_PENDING_TOKEN_EXPIRATION_MINUTES must be more than 0".to_string());
+        }
+
+        match self.login_throttle.store.to_lowercase().as_str() {
+            "mysql" | "memory" => {}
+            other => errors.push(format!(
+                "LOGIN_ATTEMPT_STORE must be mysql or memory, got {}",
+                other
+            )),
+        }
+        if self.login_throttle.max_failures_per_email == 0 {
+            errors.push("LOGIN_MAX_FAILURES_PER_EMAIL must be at least 1".to_string());
+        }
+        if self.login_throttle.max_failures_per_ip == 0 {
+            errors.push("LOGIN_MAX_FAILURES_PER_IP must be at least 1".to_string());
+        }
+        if self.login_throttle.backoff_base_seconds < 0 {
+            errors.push("LOGIN_BACKOFF_BASE_SECONDS can't be negative".to_string());
+        }
+        if self.login_throttle.backoff_max_seconds < self.login_throttle.backoff_base_seconds {
+            errors.push(
+                "LOGIN_BACKOFF_MAX_SECONDS can't be less than LOGIN_BACKOFF_BASE_SECONDS"
+                    .to_string(),
+            );
+        }
+        if self.login_throttle.lockout_minutes <= 0 {
+            errors.push("LOGIN_LOCKOUT_MINUTES must be more than 0".to_string());
+        }
+        if self.login_throttle.failure_window_minutes <= 0 {
+            errors.push("LOGIN_FAILURE_WINDOW_MINUTES must be more than 0".to_string());
+        }
+
+        match self.registration.mode.to_lowercase().as_str() {
+            "open" | "invite" => {}
+            other => errors.push(format!(
+                "REGISTRATION_MODE must be open or invite, got {}",
+                other
+            )),
+        }
+        if self.registration.invite_expiration_days <= 0 {
+            errors.push("REGISTRATION_INVITE_EXPIRATION_DAYS must be more than 0".to_string());
+        }
+
+        if self.magic_link.expiration_minutes <= 0 {
+            errors.push("MAGIC_LINK_EXPIRATION_MINUTES must be more than 0".to_string());
+        }
+
+        if self.export.link_expiration_minutes <= 0 {
+            errors.push("EXPORT_LINK_EXPIRATION_MINUTES must be more than 0".to_string());
+        }
+        if self.export.retention_hours <= 0 {
+            errors.push("EXPORT_RETENTION_HOURS must be more than 0".to_string());
+        }
+
+        if self.blob_store.kind.to_lowercase() != "local" {
+            errors.push(format!(
+                "BLOB_STORE must be local, got {}",
+                self.blob_store.kind
+            ));
+        }
+
+        if self.avatar.max_bytes == 0 {
+            errors.push("AVATAR_MAX_BYTES must be at least 1".to_string());
+        }
+        if self.avatar.min_dimension == 0 {
+            errors.push("AVATAR_MIN_DIMENSION must be at least 1".to_string());
+        }
+        if self.avatar.min_dimension > self.avatar.max_dimension {
+            errors.push("AVATAR_MIN_DIMENSION can't be more than AVATAR_MAX_DIMENSION".to_string());
+        }
+
+        if self.tasks.revoked_token_purge_interval_minutes == 0 {
+            errors.push("REVOKED_TOKEN_PURGE_INTERVAL_MINUTES must be at least 1".to_string());
+        }
+        if self.tasks.account_anonymise_interval_minutes == 0 {
+            errors.push("ACCOUNT_ANONYMISE_INTERVAL_MINUTES must be at least 1".to_string());
+        }
+        if self.tasks.export_purge_interval_minutes == 0 {
+            errors.push("EXPORT_PURGE_INTERVAL_MINUTES must be at least 1".to_string());
+        }
+        if self.tasks.login_attempt_purge_interval_minutes == 0 {
+            errors.push("LOGIN_ATTEMPT_PURGE_INTERVAL_MINUTES must be at least 1".to_string());
+        }
+    }
+}
+
+// Where the settings come from - environment first, then the TOML file
+// problems are collected in errors instead of stopping at the first one
+struct ConfigSource {
+    file_values: HashMap<String, String>, // TOML values by "section.key"
+    errors: Vec<String>,
+}
+
+impl ConfigSource {
+    fn new() -> Result<ConfigSource, String> {
+        let path = match env::var("APP_CONFIG_FILE") {
+            Ok(p) => Some(p),
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Some(DEFAULT_CONFIG_FILE.to_string())
+            }
+            Err(_) => None,
+        };
+
+        let mut file_values = HashMap::new();
+
+        if let Some(path) = path {
+            let content =
+                fs::read_to_string(&path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+            let table = content
+                .parse::<toml::Table>()
+                .map_err(|e| format!("Can't parse {}: {}", path, e))?;
+
+            _flatten("", &table, &mut file_values);
+        }
+
+        Ok(ConfigSource {
+            file_values,
+            errors: vec![],
+        })
+    }
+
+    fn optional(&self, env_key: &str, file_key: &str) -> Option<String> {
+        env::var(env_key)
+            .ok()
+            .or(self.file_values.get(file_key).cloned())
+            .filter(|v| !v.trim().is_empty())
+    }
+
+    fn required(&mut self, env_key: &str, file_key: &str) -> String {
+        match self.optional(env_key, file_key) {
+            Some(v) => v,
+            None => {
+                self.errors
+                    .push(format!("{} (or {}) must be set", env_key, file_key));
+                String::new()
+            }
+        }
+    }
+
+    fn string(&self, env_key: &str, file_key: &str, default: &str) -> String {
+        self.optional(env_key, file_key)
+            .unwrap_or(default.to_string())
+    }
+
+    fn number<T>(&mut self, env_key: &str, file_key: &str, default: T) -> T
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        match self.optional(env_key, file_key) {
+            None => default,
+            Some(v) => match v.trim().parse::<T>() {
+                Ok(n) => n,
+                Err(e) => {
+                    self.errors
+                        .push(format!("{} must be a number, got {}: {}", env_key, v, e));
+                    default
+                }
+            },
+        }
+    }
+
+    fn boolean(&mut self, env_key: &str, file_key: &str, default: bool) -> bool {
+        match self.optional(env_key, file_key) {
+            None => default,
+            Some(v) => match v.trim().parse::<bool>() {
+                Ok(b) => b,
+                Err(_) => {
+                    self.errors
+                        .push(format!("{} must be true or false, got {}", env_key, v));
+                    default
+                }
+            },
+        }
+    }
+
+    fn same_site(&mut self, env_key: &str, file_key: &str, default: SameSite) -> SameSite {
+        match self.optional(env_key, file_key) {
+            None => default,
+            Some(v) => match v.trim().to_lowercase().as_str() {
+                "strict" => SameSite::Strict,
+                "lax" => SameSite::Lax,
+                "none" => SameSite::None,
+                _ => {
+                    self.errors.push(format!(
+                        "{} must be Strict, Lax or None, got {}",
+                        env_key, v
+                    ));
+                    default
+                }
+            },
+        }
+    }
+
+    // requests/seconds, e.g. 10/60 is 10 requests a minute
+    fn rate_limit(
+        &mut self,
+        env_key: &str,
+        file_key: &str,
+        capacity: u32,
+        period_seconds: u64,
+    ) -> RateLimitRule {
+        let default = RateLimitRule {
+            capacity,
+            period_seconds,
+        };
+
+        match self.optional(env_key, file_key) {
+            None => default,
+            Some(v) => {
+                let parsed = v.split_once('/').and_then(|(c, p)| {
+                    Some((c.trim().parse::<u32>().ok()?, p.trim().parse::<u64>().ok()?))
+                });
+
+                match parsed {
+                    Some((capacity, period_seconds)) if capacity > 0 && period_seconds > 0 => {
+                        RateLimitRule {
+                            capacity,
+                            period_seconds,
+                        }
+                    }
+                    _ => {
+                        self.errors.push(format!(
+                            "{} must be requests/seconds above 0, e.g. 10/60, got {}",
+                            env_key, v
+                        ));
+                        default
+                    }
+                }
+            }
+        }
+    }
+
+    // comma separated in the environment, a comma separated string or an array in the file
+    fn list(&self, env_key: &str, file_key: &str, default: &[&str]) -> Vec<String> {
+        match self.optional(env_key, file_key) {
+            None => default.iter().map(|s| s.to_string()).collect(),
+            Some(v) => v
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+        }
+    }
+}
+
+// [server] port = 5000 becomes "server.port" => "5000", arrays are joined with commas
+fn _flatten(prefix: &str, table: &toml::Table, values: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let full_key = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+
+        match value {
+            toml::Value::Table(t) => _flatten(&full_key, t, values),
+            toml::Value::String(s) => {
+                values.insert(full_key, s.to_string());
+            }
+            toml::Value::Array(items) => {
+                let joined = items
+                    .iter()
+                    .map(|i| match i {
+                        toml::Value::String(s) => s.to_string(),
+                        other => other.to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(",");
+                values.insert(full_key, joined);
+            }
+            other => {
+                values.insert(full_key, other.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+
+use validator::Validate;
+
+use crate::utils::custom_validation_utils::validate_email;
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct DeactivateAccountRequestData {
+    #[validate(length(min = 1, max = 255))]
+    pub current_password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct DeleteAccountRequestData {
+    #[validate(length(min = 1, max = 255))]
+    pub current_password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeleteAccountResponseData {
+    pub datetime_anonymise: NaiveDateTime, // personal data is removed after this, unless the account is reactivated
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct ReactivationRequestData {
+    #[validate(custom(function = "validate_email"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct ReactivateAccountRequestData {
+    #[validate(length(min = 1, max = 255))]
+    pub token: String,
+}
@@ -0,0 +1,11 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+// urls of each variant of an avatar, square and in webp
+// an avatar never changes under its urls so they can be cached for good
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AvatarResponseData {
+    pub small: String,  // 64px
+    pub medium: String, // 128px
+    pub large: String,  // 256px
+}
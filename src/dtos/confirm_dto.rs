@@ -0,0 +1,10 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct ConfirmRequestData {
+    #[validate(length(min = 1, max = 255))]
+    pub token: String,
+}
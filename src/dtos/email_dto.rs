@@ -0,0 +1,15 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use validator::Validate;
+
+use crate::utils::custom_validation_utils::validate_email;
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct ChangeEmailRequestData {
+    #[validate(length(min = 1, max = 255))]
+    pub current_password: String,
+
+    #[validate(custom(function = "validate_email"))]
+    pub email: String,
+}
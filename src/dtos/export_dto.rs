@@ -0,0 +1,72 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportResponseData {
+    pub id: String,
+    pub status: String, // pending, ready, failed or expired
+    pub datetime_created: NaiveDateTime,
+    pub datetime_ttl: NaiveDateTime,  // archive is deleted after this
+    pub download_url: Option<String>, // only once ready
+    pub datetime_download_url_expires: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportDownloadQuery {
+    pub expires: i64, // unix timestamp
+    pub signature: String,
+}
+
+// manifest.json of the archive
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportManifestData {
+    pub user: String, // pid
+    pub datetime_generated: NaiveDateTime,
+    pub files: Vec<String>,
+}
+
+// profile.json of the archive
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportProfileData {
+    pub pid: String,
+    pub firstname: String,
+    pub lastname: String,
+    pub handle: Option<String>,
+    pub email: String,
+    pub datetime_created: NaiveDateTime,
+    pub datetime_confirmed: Option<NaiveDateTime>,
+    pub datetime_deactivated: Option<NaiveDateTime>,
+    pub datetime_deleted: Option<NaiveDateTime>,
+    pub mfa_enabled: bool,
+}
+
+// one entry of sessions.json of the archive
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportSessionData {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub datetime_created: NaiveDateTime,
+    pub datetime_last_used: NaiveDateTime,
+}
+
+// one entry of handle_history.json of the archive
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportHandleData {
+    pub handle: String,
+    pub datetime_released: NaiveDateTime,
+}
+
+// one entry of invites.json of the archive - invites the user created
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportInviteData {
+    pub id: String,
+    pub email: Option<String>,
+    pub domain: Option<String>,
+    pub max_uses: u32,
+    pub uses: u32,
+    pub datetime_ttl: NaiveDateTime,
+    pub datetime_revoked: Option<NaiveDateTime>,
+    pub datetime_created: NaiveDateTime,
+}
@@ -0,0 +1,37 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+
+use validator::Validate;
+
+use crate::utils::custom_validation_utils::validate_handle;
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct ChangeHandleRequestData {
+    #[validate(custom(function = "validate_handle"))]
+    pub handle: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChangeHandleTooSoonResponseData {
+    pub datetime_next_change: NaiveDateTime, // earliest the handle can be changed again
+}
+
+// ?handle=
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct HandleAvailabilityQuery {
+    #[validate(custom(function = "validate_handle"))]
+    pub handle: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandleAvailabilityResponseData {
+    pub handle: String,
+    pub available: bool,
+}
+
+// the user's handle after a change, or the current one when an old handle was asked for
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HandleResponseData {
+    pub handle: String,
+}
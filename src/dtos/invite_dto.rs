@@ -0,0 +1,50 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+
+use validator::Validate;
+
+use crate::utils::custom_validation_utils::validate_email;
+use crate::utils::custom_validation_utils::validate_email_domain;
+
+// email and domain lock the invite to who may use it - at most one of them
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct CreateInviteRequestData {
+    #[validate(custom(function = "validate_email"))]
+    pub email: Option<String>,
+
+    #[validate(custom(function = "validate_email_domain"))]
+    pub domain: Option<String>,
+
+    #[validate(range(min = 1, max = 1000))]
+    pub max_uses: Option<u32>, // single use if not given
+
+    #[validate(range(min = 1, max = 365))]
+    pub expiration_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InviteResponseData {
+    pub id: String,
+    pub code: Option<String>, // only in the response to creating it, it can't be seen again
+    pub email: Option<String>,
+    pub domain: Option<String>,
+    pub max_uses: u32,
+    pub uses: u32,
+    pub datetime_ttl: NaiveDateTime,
+    pub datetime_revoked: Option<NaiveDateTime>,
+    pub datetime_created: NaiveDateTime,
+}
+
+// someone that registered with an invite
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InviteUseResponseData {
+    pub pid: String,
+    pub datetime_created: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InviteDetailResponseData {
+    pub invite: InviteResponseData,
+    pub uses: Vec<InviteUseResponseData>,
+}
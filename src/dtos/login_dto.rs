@@ -5,4 +5,6 @@ use serde::Deserialize;
 pub struct LoginRequestData {
     pub email: String,
     pub password: String,
+    #[serde(default)]
+    pub reactivate: bool, // bring back a deactivated account, or one waiting to be deleted
 }
\ No newline at end of file
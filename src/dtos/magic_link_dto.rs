@@ -0,0 +1,18 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use validator::Validate;
+
+use crate::utils::custom_validation_utils::validate_email;
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MagicLinkRequestData {
+    #[validate(custom(function = "validate_email"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MagicLinkLoginRequestData {
+    #[validate(length(min = 1, max = 255))]
+    pub token: String,
+}
@@ -0,0 +1,45 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MfaLoginRequestData {
+    #[validate(length(min = 1, max = 255))]
+    pub mfa_token: String,
+
+    #[validate(length(min = 1, max = 32))]
+    pub code: String, // authenticator code or recovery code
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MfaVerifyRequestData {
+    #[validate(length(min = 1, max = 32))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MfaDisableRequestData {
+    #[validate(length(min = 1, max = 255))]
+    pub current_password: String,
+
+    #[validate(length(min = 1, max = 32))]
+    pub code: String, // authenticator code or recovery code
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MfaPendingResponseData {
+    pub mfa_required: bool,
+    pub mfa_token: String, // send back to /api/auth/login/mfa with the code
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MfaEnrollResponseData {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MfaRecoveryCodesResponseData {
+    pub recovery_codes: Vec<String>, // shown once, only their hashes are stored
+}
@@ -1,2 +1,14 @@
+pub mod account_dto;
+pub mod avatar_dto;
+pub mod confirm_dto;
+pub mod email_dto;
+pub mod export_dto;
+pub mod handle_dto;
+pub mod invite_dto;
 pub mod login_dto;
+pub mod magic_link_dto;
+pub mod mfa_dto;
+pub mod password_dto;
+pub mod profile_dto;
 pub mod register_dto;
+pub mod session_dto;
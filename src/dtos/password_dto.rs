@@ -0,0 +1,36 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use validator::Validate;
+
+use crate::utils::custom_validation_utils::validate_email;
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct ForgotPasswordRequestData {
+    #[validate(custom(function = "validate_email"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct ResetPasswordRequestData {
+    #[validate(length(min = 1, max = 255))]
+    pub token: String,
+
+    #[validate(length(min = 8, max = 255))]
+    pub password: String,
+
+    #[validate(length(min = 8, max = 255))]
+    pub repeat: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct ChangePasswordRequestData {
+    #[validate(length(min = 1, max = 255))]
+    pub current_password: String,
+
+    #[validate(length(min = 8, max = 255))]
+    pub password: String,
+
+    #[validate(length(min = 8, max = 255))]
+    pub repeat: String,
+}
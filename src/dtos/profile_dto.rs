@@ -0,0 +1,45 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+
+use validator::Validate;
+
+use crate::dtos::avatar_dto::AvatarResponseData;
+use crate::utils::custom_validation_utils::validate_name;
+
+// What the user sees of itself
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MeResponseData {
+    pub pid: String,
+    pub firstname: String,
+    pub lastname: String,
+    pub handle: Option<String>,
+    pub avatar: Option<AvatarResponseData>,
+    pub email: String,
+    pub datetime_created: NaiveDateTime,
+    pub datetime_confirmed: Option<NaiveDateTime>,
+    pub mfa_enabled: bool,
+}
+
+// What anyone sees of a user
+// email and account state are never part of it
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PublicProfileResponseData {
+    pub pid: String,
+    pub firstname: String,
+    pub lastname: String,
+    pub handle: Option<String>,
+    pub avatar: Option<AvatarResponseData>,
+    pub datetime_created: NaiveDateTime,
+}
+
+// only the given fields are changed
+// email and password have their own endpoints since they need confirming
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct UpdateProfileRequestData {
+    #[validate(custom(function = "validate_name"))]
+    pub firstname: Option<String>,
+
+    #[validate(custom(function = "validate_name"))]
+    pub lastname: Option<String>,
+}
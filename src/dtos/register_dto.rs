@@ -4,6 +4,7 @@ use serde::Serialize;
 use validator::Validate;
 
 use crate::utils::custom_validation_utils::validate_email;
+use crate::utils::custom_validation_utils::validate_handle;
 use crate::utils::custom_validation_utils::validate_name;
 
 #[derive(Debug, Deserialize, Serialize, Validate)]
@@ -14,6 +15,9 @@ pub struct RegisterRequestData {
     #[validate(custom(function = "validate_name"))]
     pub lastname: String,
 
+    #[validate(custom(function = "validate_handle"))]
+    pub handle: String,
+
     #[validate(custom(function = "validate_email"))]
     pub email: String,
 
@@ -22,4 +26,8 @@ pub struct RegisterRequestData {
 
     #[validate(length(min = 8, max = 255))]
     pub repeat: String,
+
+    // needed while registration is invite only
+    #[validate(length(min = 1, max = 255))]
+    pub invite: Option<String>,
 }
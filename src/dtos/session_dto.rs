@@ -0,0 +1,13 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionResponseData {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub datetime_created: NaiveDateTime,
+    pub datetime_last_used: NaiveDateTime,
+    pub current: bool, // session of the refresh token sent with the request
+}
@@ -0,0 +1,114 @@
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::http::StatusCode;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, HttpResponse, web};
+
+use futures_util::future::LocalBoxFuture;
+use sqlx::MySqlPool;
+
+use crate::middlewares::jwt_auth_middleware::{AuthSub, AuthUserId};
+use crate::models::user_models::user_model::UserModel;
+use crate::utils::response_utils::{ErrorCode, ResponseMaker};
+
+// User of the access token, for handlers behind AuthRequired
+// e.g. pub async fn list(req: HttpRequest, auth: AuthenticatedUser, ...)
+// users that are deactivated or deleted are refused before the handler runs
+pub struct AuthenticatedUser {
+    pub sub: String, // authid the access token was issued for
+    pub user: UserModel,
+}
+
+// Same as AuthenticatedUser, but the user's email must also be confirmed
+pub struct ConfirmedUser(pub AuthenticatedUser);
+
+impl FromRequest for AuthenticatedUser {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move {
+            // put there by AuthRequired
+            let (sub, user_id) = match (
+                req.extensions().get::<AuthSub>(),
+                req.extensions().get::<AuthUserId>(),
+            ) {
+                (Some(s), Some(id)) => (s.0.to_string(), id.0),
+                _ => {
+                    log::error!(
+                        "AuthenticatedUser used on {} without AuthRequired",
+                        req.path()
+                    );
+                    return Err(_reject(ResponseMaker::respond_with_server_error(&req)));
+                }
+            };
+
+            let pool = match req.app_data::<web::Data<MySqlPool>>() {
+                Some(p) => p.clone(),
+                None => {
+                    log::error!("Database pool is missing from app data");
+                    return Err(_reject(ResponseMaker::respond_with_server_error(&req)));
+                }
+            };
+
+            // AuthRequired already checked that sub is the user's current authid
+            let user = match UserModel::get_by_id(&pool, user_id).await {
+                Err(e) => {
+                    log::error!("{}", e);
+                    return Err(_reject(ResponseMaker::respond_with_server_error(&req)));
+                }
+                Ok(None) => {
+                    return Err(_reject(ResponseMaker::error_response(
+                        &req,
+                        &StatusCode::UNAUTHORIZED,
+                        ErrorCode::TokenRevoked,
+                        "Must be authenticated",
+                    )));
+                }
+                Ok(Some(u)) => u,
+            };
+
+            if user.datetime_deactivated.is_some() || user.datetime_deleted.is_some() {
+                return Err(_reject(ResponseMaker::error_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    ErrorCode::AccountInactive,
+                    "Account is deactivated",
+                )));
+            }
+
+            Ok(AuthenticatedUser { sub, user })
+        })
+    }
+}
+
+impl FromRequest for ConfirmedUser {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let authenticated = AuthenticatedUser::from_request(&req, payload);
+
+        Box::pin(async move {
+            let authenticated = authenticated.await?;
+
+            if authenticated.user.datetime_confirmed.is_none() {
+                return Err(_reject(ResponseMaker::error_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    ErrorCode::EmailNotConfirmed,
+                    "Email address must be confirmed",
+                )));
+            }
+
+            Ok(ConfirmedUser(authenticated))
+        })
+    }
+}
+
+// the response goes back to the client as is
+fn _reject(resp: HttpResponse) -> Error {
+    InternalError::from_response("", resp).into()
+}
@@ -0,0 +1,234 @@
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+use actix_web::rt;
+use actix_web::web;
+
+use sqlx::MySqlPool;
+
+use validator::Validate;
+
+use crate::config::app_config::AppConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::mailers::mailer::Mailer;
+use crate::services::account_service::AccountService;
+use crate::utils::bcrypt_utils::is_matched;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::dtos::account_dto::DeactivateAccountRequestData;
+use crate::dtos::account_dto::DeleteAccountRequestData;
+use crate::dtos::account_dto::DeleteAccountResponseData;
+use crate::dtos::account_dto::ReactivateAccountRequestData;
+use crate::dtos::account_dto::ReactivationRequestData;
+
+pub struct Account {}
+
+impl Account {
+    pub async fn deactivate(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        data: web::Json<DeactivateAccountRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Check the current password
+            - Stamp datetime_deactivated and rotate the user's authid
+                - this logs the user out on all devices
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        let mut user = auth_user.user;
+
+        match is_matched(&data.current_password, &user.password) {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(false) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    "Current password is incorrect",
+                );
+            }
+            Ok(true) => {}
+        }
+
+        match AccountService::deactivate(&pool, &mut user).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(_) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    "Account deactivated. Login with reactivate to bring it back",
+                );
+            }
+        }
+    }
+
+    pub async fn delete(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+        data: web::Json<DeleteAccountRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Check the current password
+            - Stamp datetime_deleted and rotate the user's authid
+                - this logs the user out on all devices
+            - Respond with when the account is anonymised - until then it can still be reactivated
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        let mut user = auth_user.user;
+
+        match is_matched(&data.current_password, &user.password) {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(false) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    "Current password is incorrect",
+                );
+            }
+            Ok(true) => {}
+        }
+
+        match AccountService::request_deletion(&pool, &config.account, &mut user).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(datetime_anonymise) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::ACCEPTED,
+                    DeleteAccountResponseData { datetime_anonymise },
+                );
+            }
+        }
+    }
+
+    pub async fn request_reactivation(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        mailer: web::Data<dyn Mailer>,
+        config: web::Data<AppConfig>,
+        data: web::Json<ReactivationRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Look up the email and mail a reactivation link in the background
+            - Always respond the same way so this can't be used to find out which accounts are deactivated
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        // done in the background so response time doesn't depend on the account
+        let pool = pool.get_ref().clone();
+        let mailer = mailer.into_inner();
+        let config = config.into_inner();
+        let email = data.into_inner().email;
+        rt::spawn(async move {
+            if let Err(e) = AccountService::send_reactivation(
+                &pool,
+                mailer.as_ref(),
+                &config.mail,
+                &config.account,
+                &email,
+            )
+            .await
+            {
+                log::error!("Unable to send reactivation email. {}", e);
+            }
+        });
+
+        return ResponseMaker::general_response(
+            &req,
+            &StatusCode::OK,
+            "If the account is deactivated, a reactivation link has been sent",
+        );
+    }
+
+    pub async fn reactivate(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        data: web::Json<ReactivateAccountRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Use up the reactivation token
+            - Clear datetime_deactivated and datetime_deleted of the token's user
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        match AccountService::reactivate_with_token(&pool, &data.token).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(None) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    "Reactivation token is invalid or has expired",
+                );
+            }
+            Ok(Some(_)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    "Account reactivated. Please login",
+                );
+            }
+        }
+    }
+}
@@ -1,52 +1,218 @@
-use actix_web::HttpMessage;
 use actix_web::HttpRequest;
+use actix_web::HttpResponse;
 use actix_web::Responder;
 use actix_web::Scope;
 use actix_web::http::StatusCode;
 use actix_web::web;
 
-use chrono::DateTime;
-use chrono::Duration;
-
 use jsonwebtoken::TokenData;
 use sqlx::MySqlPool;
 
 use validator::Validate;
 
-use crate::constants;
-use crate::models::revoked_token_models::revoked_token_model::RevokedTokenModel;
-use crate::models::user_models::user_authid_model::UserAuthidModel;
+use crate::config::app_config::AppConfig;
+use crate::config::app_config::LoginThrottleConfig;
+use crate::config::app_config::RateLimitConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::handlers::account_handlers::Account;
+use crate::handlers::confirmation_handlers::Confirmation;
+use crate::handlers::email_handlers::Email;
+use crate::handlers::handle_handlers::Handle;
+use crate::handlers::magic_link_handlers::MagicLink;
+use crate::handlers::mfa_handlers::Mfa;
+use crate::handlers::password_handlers::Password;
+use crate::handlers::session_handlers::Session;
+use crate::mailers::mailer::Mailer;
 use crate::models::user_models::user_email_model::UserEmailModel;
 use crate::models::user_models::user_model::UserModel;
+use crate::services::account_service::AccountService;
 use crate::services::auth_service::AuthService;
+use crate::services::confirmation_service::ConfirmationService;
+use crate::services::confirmation_service::UnconfirmedAction;
+use crate::services::handle_service::HandleService;
+use crate::services::invite_service::InviteService;
+use crate::services::login_throttle_service::LoginThrottleService;
+use crate::services::mfa_service::MfaService;
+use crate::services::refresh_token_service::RefreshRotation;
+use crate::services::refresh_token_service::RefreshTokenService;
 use crate::services::user_service::UserService;
+use crate::stores::login_attempt_store::LoginAttemptStore;
 use crate::utils::bcrypt_utils::is_matched;
+use crate::utils::client_utils::ClientInfo;
 
 use crate::utils::jwt_utils::Claims;
+use crate::utils::jwt_utils::JwtKeys;
 use crate::utils::jwt_utils::decode_refresh_token;
 use crate::utils::jwt_utils::generate_access_token;
-use crate::utils::jwt_utils::generate_refresh_token;
+use crate::utils::jwt_utils::revoked_until;
+use crate::utils::response_utils::ErrorCode;
 use crate::utils::response_utils::ResponseMaker;
 
+use crate::middlewares::csrf_middleware::CsrfProtected;
 use crate::middlewares::jwt_auth_middleware::AuthRequired;
+use crate::middlewares::rate_limit_middleware::RateLimit;
+use crate::middlewares::rate_limit_middleware::RateLimitKey;
 
 use crate::dtos::login_dto::LoginRequestData;
+use crate::dtos::mfa_dto::MfaPendingResponseData;
 use crate::dtos::register_dto::RegisterRequestData;
 
-pub fn scopes() -> Scope {
+pub fn scopes(rate_limits: &RateLimitConfig) -> Scope {
     web::scope("/auth")
-        .route("/login", web::post().to(Authentication::login))
-        .route("/register", web::post().to(Authentication::register))
+        .route(
+            "/login",
+            web::post().to(Authentication::login).wrap(RateLimit::new(
+                "auth_login",
+                &rate_limits.auth_login,
+                RateLimitKey::Ip,
+            )),
+        )
+        .route(
+            "/login/mfa",
+            web::post().to(Mfa::login).wrap(RateLimit::new(
+                "auth_login_mfa",
+                &rate_limits.auth_login_mfa,
+                RateLimitKey::Ip,
+            )),
+        )
+        .route(
+            "/magic-link",
+            web::post().to(MagicLink::request).wrap(RateLimit::new(
+                "auth_magic_link",
+                &rate_limits.auth_magic_link,
+                RateLimitKey::Ip,
+            )),
+        )
+        .route(
+            "/magic-link/login",
+            web::post().to(MagicLink::login).wrap(RateLimit::new(
+                "auth_magic_link_login",
+                &rate_limits.auth_magic_link_login,
+                RateLimitKey::Ip,
+            )),
+        )
+        .route(
+            "/register",
+            web::post()
+                .to(Authentication::register)
+                .wrap(RateLimit::new(
+                    "auth_register",
+                    &rate_limits.auth_register,
+                    RateLimitKey::Ip,
+                ))
+                // cap on signups from everyone together - stops bulk registration from many ips
+                .wrap(RateLimit::new(
+                    "auth_register_all",
+                    &rate_limits.auth_register_all,
+                    RateLimitKey::Route,
+                )),
+        )
+        // for the registration form, register checks again
+        .route(
+            "/register/handle",
+            web::get().to(Handle::available).wrap(RateLimit::new(
+                "auth_register_handle",
+                &rate_limits.auth_register_handle,
+                RateLimitKey::Ip,
+            )),
+        )
         .route(
             "/logout",
-            web::post().to(Authentication::logout).wrap(AuthRequired {}),
+            web::post()
+                .to(Authentication::logout)
+                .wrap(AuthRequired {})
+                .wrap(CsrfProtected {}),
+        )
+        .route(
+            "/logout-all",
+            web::post()
+                .to(Authentication::logout_all)
+                .wrap(AuthRequired {}),
         )
         .route(
             "/refresh",
             web::post()
                 .to(Authentication::refresh)
+                .wrap(RateLimit::new(
+                    "auth_refresh",
+                    &rate_limits.auth_refresh,
+                    RateLimitKey::Sub,
+                ))
+                .wrap(AuthRequired {})
+                .wrap(CsrfProtected {}),
+        )
+        .route("/confirm", web::post().to(Confirmation::confirm))
+        .route(
+            "/confirm/resend",
+            web::post()
+                .to(Confirmation::resend)
+                .wrap(RateLimit::new(
+                    "auth_confirm_resend",
+                    &rate_limits.auth_confirm_resend,
+                    RateLimitKey::Sub,
+                ))
                 .wrap(AuthRequired {}),
         )
+        .route(
+            "/password/forgot",
+            web::post().to(Password::forgot).wrap(RateLimit::new(
+                "auth_password_forgot",
+                &rate_limits.auth_password_forgot,
+                RateLimitKey::Ip,
+            )),
+        )
+        .route("/password/reset", web::post().to(Password::reset))
+        .route(
+            "/password/change",
+            web::post().to(Password::change).wrap(AuthRequired {}),
+        )
+        .route(
+            "/email/change",
+            web::post().to(Email::change).wrap(AuthRequired {}),
+        )
+        .route(
+            "/email/confirm",
+            web::post().to(Email::confirm).wrap(AuthRequired {}),
+        )
+        .route(
+            "/mfa/enroll",
+            web::post().to(Mfa::enroll).wrap(AuthRequired {}),
+        )
+        .route(
+            "/mfa/verify",
+            web::post().to(Mfa::verify).wrap(AuthRequired {}),
+        )
+        .route(
+            "/mfa/disable",
+            web::post().to(Mfa::disable).wrap(AuthRequired {}),
+        )
+        .route(
+            "/sessions",
+            web::get().to(Session::list).wrap(AuthRequired {}),
+        )
+        .route(
+            "/sessions/{id}",
+            web::delete().to(Session::revoke).wrap(AuthRequired {}),
+        )
+        .route(
+            "/account/deactivate",
+            web::post().to(Account::deactivate).wrap(AuthRequired {}),
+        )
+        .route(
+            "/account/delete",
+            web::post().to(Account::delete).wrap(AuthRequired {}),
+        )
+        .route(
+            "/account/reactivate/request",
+            web::post()
+                .to(Account::request_reactivation)
+                .wrap(RateLimit::new(
+                    "auth_account_reactivate_request",
+                    &rate_limits.auth_account_reactivate_request,
+                    RateLimitKey::Ip,
+                )),
+        )
+        .route("/account/reactivate", web::post().to(Account::reactivate))
 }
 
 pub struct Authentication {}
@@ -55,17 +221,51 @@ impl Authentication {
     pub async fn login(
         req: HttpRequest,
         pool: web::Data<MySqlPool>,
+        jwt_keys: web::Data<JwtKeys>,
+        config: web::Data<AppConfig>,
+        login_attempts: web::Data<dyn LoginAttemptStore>,
         data: web::Form<LoginRequestData>,
     ) -> impl Responder {
         /*
+           - Refuse with 429 while the email or ip is backing off or locked out
            - Get email from form
                - check if its present in db
                - get user
            - Check user password with the password that came with the form
+               - every failure is counted against the email and the ip
+           - Deactivated or deleted accounts are refused unless the form asks to reactivate
+           - If the user has 2FA, respond with a pending token for /login/mfa instead
+               - the reactivate flag goes with it, the account is reactivated there after the code
+           - Otherwise reactivate the account if asked to
            - Create access token and refresh token
            - Access token goes to response body and refresh token goes into cookie
         */
 
+        let client_info = ClientInfo::from_request(&req);
+        let login_attempts: &dyn LoginAttemptStore = login_attempts.get_ref();
+
+        // check backoff and lockout
+        match LoginThrottleService::retry_after(
+            login_attempts,
+            &data.email,
+            client_info.ip_address.as_deref(),
+        )
+        .await
+        {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(Some(retry_after)) => {
+                return ResponseMaker::too_many_requests_response(
+                    &req,
+                    retry_after,
+                    "Too many failed login attempts. Please try again later",
+                );
+            }
+            Ok(None) => {}
+        }
+
         // check email
         let user_email_obj: UserEmailModel =
             match UserEmailModel::get_by_value(&pool, &data.email).await {
@@ -74,30 +274,37 @@ impl Authentication {
                     return ResponseMaker::respond_with_server_error(&req);
                 }
                 Ok(None) => {
-                    return ResponseMaker::general_response(
+                    return _login_failed(
                         &req,
-                        &StatusCode::UNAUTHORIZED,
-                        "Invalid email and/or password",
-                    );
+                        login_attempts,
+                        &config.login_throttle,
+                        &data.email,
+                        &client_info,
+                    )
+                    .await;
                 }
                 Ok(Some(e)) => e,
             };
 
         // get the user
-        let user_obj: UserModel = match UserModel::get_by_email_id(&pool, user_email_obj.id).await {
-            Err(e) => {
-                log::error!("{}", e);
-                return ResponseMaker::respond_with_server_error(&req);
-            }
-            Ok(None) => {
-                return ResponseMaker::general_response(
-                    &req,
-                    &StatusCode::UNAUTHORIZED,
-                    "Invalid email and/or password",
-                );
-            }
-            Ok(Some(u)) => u,
-        };
+        let mut user_obj: UserModel =
+            match UserModel::get_by_email_id(&pool, user_email_obj.id).await {
+                Err(e) => {
+                    log::error!("{}", e);
+                    return ResponseMaker::respond_with_server_error(&req);
+                }
+                Ok(None) => {
+                    return _login_failed(
+                        &req,
+                        login_attempts,
+                        &config.login_throttle,
+                        &data.email,
+                        &client_info,
+                    )
+                    .await;
+                }
+                Ok(Some(u)) => u,
+            };
 
         // check password
         match is_matched(&data.password, &user_obj.password) {
@@ -107,51 +314,109 @@ impl Authentication {
             }
             Ok(m) => {
                 if !m {
-                    return ResponseMaker::general_response(
+                    return _login_failed(
                         &req,
-                        &StatusCode::UNAUTHORIZED,
-                        "Invalid email and/or password",
-                    );
+                        login_attempts,
+                        &config.login_throttle,
+                        &data.email,
+                        &client_info,
+                    )
+                    .await;
                 } else {
                     // password matched
-                    // generate tokens
+                    // the email's failures are cleared further down, once there is no code left to check
+                    // logging in is how a deactivated account, or one waiting to be deleted, comes back
+                    let inactive = user_obj.datetime_deactivated.is_some()
+                        || user_obj.datetime_deleted.is_some();
+                    if inactive && !data.reactivate {
+                        return ResponseMaker::error_response(
+                            &req,
+                            &StatusCode::FORBIDDEN,
+                            ErrorCode::AccountInactive,
+                            "Account is deactivated. Login with reactivate to bring it back",
+                        );
+                    }
 
-                    // get user authid
-                    match UserAuthidModel::get_by_id(&pool, user_obj.authid_id).await {
+                    // 2FA users get a pending token instead and finish at /login/mfa
+                    // an inactive account is only reactivated there, once the code matched
+                    match MfaService::is_enabled(&pool, user_obj.id).await {
                         Err(e) => {
                             log::error!("{}", e);
                             return ResponseMaker::respond_with_server_error(&req);
                         }
-                        Ok(None) => {
-                            log::error!("Error! A user {} doesn't have authid", user_obj.id);
-                            return ResponseMaker::respond_with_server_error(&req);
-                        }
-                        Ok(Some(obj)) => {
-                            // create access and refresh tokens
-                            let access_token = match generate_access_token(&obj.value) {
-                                Err(e) => {
-                                    log::error!("{}", e);
-                                    return ResponseMaker::respond_with_server_error(&req);
-                                }
-                                Ok(at) => at,
-                            };
-
-                            let refresh_token = match generate_refresh_token(&obj.value) {
+                        Ok(true) => {
+                            return match MfaService::start_login(
+                                &pool,
+                                &config.mfa,
+                                user_obj.id,
+                                inactive,
+                            )
+                            .await
+                            {
                                 Err(e) => {
                                     log::error!("{}", e);
-                                    return ResponseMaker::respond_with_server_error(&req);
+                                    ResponseMaker::respond_with_server_error(&req)
                                 }
-                                Ok(rt) => rt,
+                                Ok(mfa_token) => ResponseMaker::general_response(
+                                    &req,
+                                    &StatusCode::OK,
+                                    MfaPendingResponseData {
+                                        mfa_required: true,
+                                        mfa_token,
+                                    },
+                                ),
                             };
+                        }
+                        Ok(false) => {}
+                    }
 
-                            return ResponseMaker::jwt_response(
-                                &req,
-                                &StatusCode::OK,
-                                &access_token,
-                                &refresh_token,
-                            );
+                    if inactive {
+                        match AccountService::reactivate(&pool, &mut user_obj).await {
+                            Err(e) => {
+                                log::error!("{}", e);
+                                return ResponseMaker::respond_with_server_error(&req);
+                            }
+                            Ok(false) => {
+                                return _login_failed(
+                                    &req,
+                                    login_attempts,
+                                    &config.login_throttle,
+                                    &data.email,
+                                    &client_info,
+                                )
+                                .await;
+                            }
+                            Ok(true) => {}
                         }
                     }
+
+                    if let Err(e) =
+                        LoginThrottleService::record_success(login_attempts, &data.email).await
+                    {
+                        log::error!("Unable to clear failed logins. {}", e);
+                    }
+
+                    // generate tokens
+                    return match RefreshTokenService::start_session(
+                        &pool,
+                        &jwt_keys,
+                        &user_obj,
+                        &client_info,
+                    )
+                    .await
+                    {
+                        Err(e) => {
+                            log::error!("{}", e);
+                            ResponseMaker::respond_with_server_error(&req)
+                        }
+                        Ok((access_token, refresh_token)) => ResponseMaker::jwt_response(
+                            &req,
+                            &StatusCode::OK,
+                            &config.cookie,
+                            &access_token,
+                            &refresh_token,
+                        ),
+                    };
                 }
             }
         }
@@ -160,13 +425,19 @@ impl Authentication {
     pub async fn register(
         req: HttpRequest,
         pool: web::Data<MySqlPool>,
+        jwt_keys: web::Data<JwtKeys>,
+        mailer: web::Data<dyn Mailer>,
+        config: web::Data<AppConfig>,
         data: web::Json<RegisterRequestData>,
     ) -> impl Responder {
         /*
             - Validate the data
             - Check that form password and form repeat are the same
             - Check email is not yet in use
+            - Check handle is available
+            - Check the invite code - needed while registration is invite only
             - Create the user and its associated data
+            - Send the email confirmation link
             - Create and respond with access token and refresh token - access token goes into body, refresh token goes to cookie
         */
 
@@ -217,87 +488,129 @@ impl Authentication {
             }
         }
 
-        // create user
-        let user_obj: UserModel = match UserService::create_user(&pool, &data).await {
+        // check handle if available
+        match HandleService::is_available(&pool, &config.account, &data.handle, None).await {
             Err(e) => {
                 log::error!("{}", e);
                 return ResponseMaker::respond_with_server_error(&req);
             }
-            Ok(u) => u,
-        };
+            Ok(true) => {}
+            Ok(false) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::CONFLICT,
+                    "Handle is already taken",
+                );
+            }
+        }
 
-        // get user authid and create tokens
-        let user_authid_obj: UserAuthidModel =
-            match UserAuthidModel::get_by_id(&pool, user_obj.authid_id).await {
+        // check invite code
+        let invite_id: Option<i64> = match &data.invite {
+            None if config.registration.invite_only() => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    "Registration requires an invite code",
+                );
+            }
+            None => None,
+            Some(code) => match InviteService::check(&pool, code, &data.email).await {
                 Err(e) => {
                     log::error!("{}", e);
                     return ResponseMaker::respond_with_server_error(&req);
                 }
                 Ok(None) => {
-                    log::error!(
-                        "Unable to get user auth identity id from newly created user. user id: {}",
-                        user_obj.id
+                    return ResponseMaker::general_response(
+                        &req,
+                        &StatusCode::FORBIDDEN,
+                        "Invite code is invalid, expired or used up",
                     );
-                    return ResponseMaker::respond_with_server_error(&req);
                 }
-                Ok(Some(uam)) => uam,
-            };
+                Ok(Some(invite)) => Some(invite.id),
+            },
+        };
 
-        let access_token: String = match generate_access_token(&user_authid_obj.value) {
+        // create user
+        let user_obj: UserModel = match UserService::create_user(&pool, &data, invite_id).await {
             Err(e) => {
                 log::error!("{}", e);
                 return ResponseMaker::respond_with_server_error(&req);
             }
-            Ok(at) => at,
+            Ok(u) => u,
         };
 
-        let refresh_token: String = match generate_refresh_token(&user_authid_obj.value) {
+        // send confirmation email
+        // registration still succeeds if this fails, user can ask for a new one through resend
+        if let Err(e) = ConfirmationService::send_confirmation(
+            &pool,
+            mailer.get_ref(),
+            &config.mail,
+            &config.tokens,
+            &user_obj,
+        )
+        .await
+        {
+            log::error!(
+                "Unable to send confirmation email to user {}. {}",
+                user_obj.id,
+                e
+            );
+        }
+
+        // create tokens
+        return match RefreshTokenService::start_session(
+            &pool,
+            &jwt_keys,
+            &user_obj,
+            &ClientInfo::from_request(&req),
+        )
+        .await
+        {
             Err(e) => {
                 log::error!("{}", e);
-                return ResponseMaker::respond_with_server_error(&req);
+                ResponseMaker::respond_with_server_error(&req)
             }
-            Ok(rt) => rt,
+            Ok((access_token, refresh_token)) => ResponseMaker::jwt_response(
+                &req,
+                &StatusCode::CREATED,
+                &config.cookie,
+                &access_token,
+                &refresh_token,
+            ),
         };
-
-        return ResponseMaker::jwt_response(
-            &req,
-            &StatusCode::CREATED,
-            &access_token,
-            &refresh_token,
-        );
     }
 
-    pub async fn logout(req: HttpRequest, pool: web::Data<MySqlPool>) -> impl Responder {
+    pub async fn logout(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        jwt_keys: web::Data<JwtKeys>,
+        config: web::Data<AppConfig>,
+    ) -> impl Responder {
         /*
             - Get the authid from access token - auth middleware already takes care of decoding the access token
             - Get the refresh token from cookie
                 - check that it is not in db yet
                 - decode the refresh token to get TokenData<Claim>
             - Compare and make sure that access token authid == refresh token authid
-                - revoke the refresh token
+                - revoke the refresh token and its family
 
             - For security purposes, if access token authid != refresh token authid
                 - change the user's authid
                     - will trigger a 'logout on all devices' since it will invalidate all the user's tokens from all devices
         */
 
-        // get access token
-        // At this point, req extension is sub (authid)
-        let access_token_authid_value: String = match req.extensions().get::<String>() {
-            Some(sub) => sub.clone(),
-            None => {
-                return ResponseMaker::respond_with_server_error(&req);
-            }
-        };
+        // user of the access token - AuthenticatedUser takes care of looking it up
+        let access_token_authid_value: &str = &auth_user.sub;
 
         // get cookie
-        if let Some(cookie) = req.cookie("refresh_token") {
-            match RevokedTokenModel::get_by_value(&pool, &cookie.value()).await {
+        if let Some(cookie) = req.cookie(config.cookie.refresh_cookie_name()) {
+            match AuthService::is_revoked(&pool, &cookie.value()).await {
                 Err(e) => {
                     log::error!("{}", e);
                     return ResponseMaker::respond_with_server_error(&req);
                 }
-                Ok(Some(_)) => {
+                Ok(true) => {
                     // refresh token is already in database
                     return ResponseMaker::general_response(
                         &req,
@@ -305,26 +618,25 @@ impl Authentication {
                         "Logout successful",
                     );
                 }
-                Ok(None) => {}
+                Ok(false) => {}
             }
 
-            match decode_refresh_token(&cookie.value()) {
+            match decode_refresh_token(&jwt_keys, &cookie.value()) {
                 Ok(token_data) => {
                     // make sure that the user actually owns the refresh token
                     // this should prevent authenticated users from using fake refresh token
 
                     if access_token_authid_value.eq_ignore_ascii_case(&token_data.claims.sub) {
-                        // grab the refresh token exp datetime and add 7 days as revoked token ttl
-                        if let Some(dt) = DateTime::from_timestamp(token_data.claims.exp, 0) {
-                            let ttl = dt + Duration::days(7);
+                        // end the family so no token of this login can be refreshed anymore
+                        if let Err(e) =
+                            RefreshTokenService::revoke_family(&pool, &token_data.claims).await
+                        {
+                            log::error!("{}", e);
+                        }
 
-                            match AuthService::create_revoked(
-                                &pool,
-                                cookie.value(),
-                                &ttl.naive_utc(),
-                            )
-                            .await
-                            {
+                        // keep it revoked until the token would have expired on its own
+                        if let Some(ttl) = revoked_until(&token_data.claims) {
+                            match AuthService::create_revoked(&pool, cookie.value(), &ttl).await {
                                 Err(e) => {
                                     log::error!("{}", e); // let this fall through to the end to change authid value
                                 }
@@ -351,36 +663,33 @@ impl Authentication {
         // Change auth id for security purposes since we can't revoked the refresh token
         // this will basically logout the user on all devices
 
-        let user_authid =
-            match UserAuthidModel::get_by_value(&pool, &access_token_authid_value).await {
-                Err(e) => {
-                    log::error!("{}", e);
-                    return ResponseMaker::respond_with_server_error(&req);
-                }
-                Ok(None) => {
-                    return ResponseMaker::general_response(
-                        &req,
-                        &StatusCode::UNAUTHORIZED,
-                        "Must be authenticated",
-                    );
-                }
-                Ok(Some(o)) => o,
-            };
+        let mut user = auth_user.user;
 
-        let mut user = match UserModel::get_by_authid_id(&pool, user_authid.id).await {
+        match UserService::update_user_authid(&pool, &mut user).await {
             Err(e) => {
                 log::error!("{}", e);
                 return ResponseMaker::respond_with_server_error(&req);
             }
-            Ok(None) => {
-                return ResponseMaker::general_response(
-                    &req,
-                    &StatusCode::UNAUTHORIZED,
-                    "Must be authenticated",
-                );
+            Ok(_) => {
+                return ResponseMaker::general_response(&req, &StatusCode::OK, "Logout successful");
             }
-            Ok(Some(o)) => o,
         };
+    }
+
+    pub async fn logout_all(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+    ) -> impl Responder {
+        /*
+            - Get the authid from access token - auth middleware already takes care of decoding the access token
+            - Get the user
+            - Change the user's authid
+                - every access and refresh token of the user carries the old authid so all of them stop working
+        */
+
+        // user of the access token - AuthenticatedUser takes care of looking it up
+        let mut user = auth_user.user;
 
         match UserService::update_user_authid(&pool, &mut user).await {
             Err(e) => {
@@ -388,133 +697,172 @@ impl Authentication {
                 return ResponseMaker::respond_with_server_error(&req);
             }
             Ok(_) => {
-                return ResponseMaker::general_response(&req, &StatusCode::OK, "Logout successful");
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    "Logged out on all devices",
+                );
             }
-        };
+        }
     }
 
-    pub async fn refresh(req: HttpRequest, pool: web::Data<MySqlPool>) -> impl Responder {
+    pub async fn refresh(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        jwt_keys: web::Data<JwtKeys>,
+        config: web::Data<AppConfig>,
+    ) -> impl Responder {
         /*
             - Get the authid from access token - auth middleware already takes care of decoding the access token
             - Get the refresh token from cookie
                 - check that it is not in db yet
                 - decode the refresh token to get TokenData<Claim>
             - Compare and make sure that access token authid == refresh token authid
+                - check that the user is allowed to refresh if email is not yet confirmed
+                - rotate the refresh token family - an already rotated token revokes the family
                 - create new access token and refresh token
             - Use jwt response to make response and send it back to client
         */
 
-        // get access token
-        // At this point, req extension is sub (authid)
-        let access_token_authid_value: String = match req.extensions().get::<String>() {
-            Some(sub) => sub.clone(),
-            None => {
-                return ResponseMaker::respond_with_server_error(&req);
-            }
-        };
+        // user of the access token - AuthenticatedUser takes care of looking it up
+        let access_token_authid_value: &str = &auth_user.sub;
 
         // get refresh token from cookie
-        if let Some(cookie) = req.cookie("refresh_token") {
+        if let Some(cookie) = req.cookie(config.cookie.refresh_cookie_name()) {
             // check if refresh token has already been revoked
-            match RevokedTokenModel::get_by_value(&pool, &cookie.value()).await {
+            match AuthService::is_revoked(&pool, &cookie.value()).await {
                 Err(e) => {
                     log::error!("{}", e);
                     return ResponseMaker::respond_with_server_error(&req);
                 }
-                Ok(Some(_)) => {
-                    return ResponseMaker::general_response(
+                Ok(true) => {
+                    // a revoked refresh token being used again means it could have been stolen
+                    // revoke the rest of its family as well
+                    if let Ok(td) = decode_refresh_token(&jwt_keys, &cookie.value()) {
+                        if let Err(e) = RefreshTokenService::handle_reuse(
+                            &pool,
+                            &td.claims,
+                            config.jwt.refresh_reuse_rotate_authid,
+                        )
+                        .await
+                        {
+                            log::error!("{}", e);
+                        }
+                    }
+
+                    return ResponseMaker::error_response(
                         &req,
                         &StatusCode::UNAUTHORIZED,
+                        ErrorCode::TokenRevoked,
                         "Refresh token is invalid",
                     );
                 }
-                Ok(None) => {
+                Ok(false) => {
                     // decode the refresh token
                     // cookie.value() is refresh token
                     let refresh_token_td: TokenData<Claims> =
-                        match decode_refresh_token(&cookie.value()) {
-                            Err(e) => {
-                                let (err_msg, status) =
-                                    if e.to_string().eq_ignore_ascii_case("expiredsignature") {
-                                        (
-                                            "Refresh token has an expired signature",
-                                            &StatusCode::UNAUTHORIZED,
-                                        )
-                                    } else if e.to_string().eq_ignore_ascii_case("invalidsignature")
-                                    {
-                                        (
-                                            "Refresh token has an invalid signature",
-                                            &StatusCode::UNAUTHORIZED,
-                                        )
-                                    } else if e.to_string().starts_with("Base64") {
-                                        (
-                                            "Refresh token has Base64 encoding issue",
-                                            &StatusCode::UNAUTHORIZED,
-                                        )
-                                    } else if e.to_string().eq_ignore_ascii_case("invalidtoken") {
-                                        ("Refresh token is invalid", &StatusCode::UNAUTHORIZED)
-                                    } else {
-                                        (
-                                            constants::INTERNAL_SERVER_ERROR_MSG,
-                                            &StatusCode::INTERNAL_SERVER_ERROR,
-                                        )
-                                    };
-
-                                return ResponseMaker::general_response(&req, &status, err_msg);
-                            }
+                        match decode_refresh_token(&jwt_keys, &cookie.value()) {
+                            Err(e) => match e.error_code() {
+                                Some(error_code) => {
+                                    return ResponseMaker::error_response(
+                                        &req,
+                                        &StatusCode::UNAUTHORIZED,
+                                        error_code,
+                                        format!("Refresh token {}", e),
+                                    );
+                                }
+                                None => {
+                                    log::error!("{}", e);
+                                    return ResponseMaker::respond_with_server_error(&req);
+                                }
+                            },
                             Ok(td) => td,
                         };
 
                     if !access_token_authid_value.eq_ignore_ascii_case(&refresh_token_td.claims.sub)
                     {
-                        return ResponseMaker::general_response(
+                        return ResponseMaker::error_response(
                             &req,
                             &StatusCode::UNAUTHORIZED,
+                            ErrorCode::TokenMismatch,
                             "Claim subs did not match. Please login",
                         );
                     } else {
-                        if let Some(dt) = DateTime::from_timestamp(refresh_token_td.claims.exp, 0) {
-                            let ttl = dt + Duration::days(7);
+                        // subs match, so the refresh token belongs to the user of the access token
+                        let user = &auth_user.user;
 
-                            match AuthService::create_revoked(
-                                &pool,
-                                &cookie.value(),
-                                &ttl.naive_utc(),
-                            )
-                            .await
-                            {
+                        // unconfirmed users may be blocked from refreshing depending on policy
+                        if !ConfirmationService::allows(
+                            &config.unconfirmed,
+                            user,
+                            &UnconfirmedAction::Refresh,
+                        ) {
+                            return ResponseMaker::general_response(
+                                &req,
+                                &StatusCode::FORBIDDEN,
+                                "Email address must be confirmed",
+                            );
+                        }
+
+                        // move the family forward, an old token of the family revokes it instead
+                        let new_refresh_token = match RefreshTokenService::rotate(
+                            &pool,
+                            &jwt_keys,
+                            user,
+                            &refresh_token_td.claims,
+                            &ClientInfo::from_request(&req),
+                            config.jwt.refresh_reuse_rotate_authid,
+                        )
+                        .await
+                        {
+                            Err(e) => {
+                                log::error!("{}", e);
+                                return ResponseMaker::respond_with_server_error(&req);
+                            }
+                            Ok(RefreshRotation::Invalid) => {
+                                return ResponseMaker::error_response(
+                                    &req,
+                                    &StatusCode::UNAUTHORIZED,
+                                    ErrorCode::TokenRevoked,
+                                    "Refresh token is invalid",
+                                );
+                            }
+                            Ok(RefreshRotation::Reused) => {
+                                return ResponseMaker::error_response(
+                                    &req,
+                                    &StatusCode::UNAUTHORIZED,
+                                    ErrorCode::TokenRevoked,
+                                    "Refresh token has already been used. Please login",
+                                );
+                            }
+                            Ok(RefreshRotation::Rotated(rt)) => rt,
+                        };
+
+                        if let Some(ttl) = revoked_until(&refresh_token_td.claims) {
+                            match AuthService::create_revoked(&pool, &cookie.value(), &ttl).await {
                                 Err(e) => {
                                     log::error!("{}", e);
                                     return ResponseMaker::respond_with_server_error(&req);
                                 }
                                 Ok(_) => {
                                     // generate access token
-                                    let new_access_token =
-                                        match generate_access_token(&refresh_token_td.claims.sub) {
-                                            Err(e) => {
-                                                log::error!("{}", e);
-                                                return ResponseMaker::respond_with_server_error(
-                                                    &req,
-                                                );
-                                            }
-                                            Ok(at) => at,
-                                        };
-
-                                    // generate refresh token
-                                    let new_refresh_token = match generate_refresh_token(
+                                    let new_access_token = match generate_access_token(
+                                        &jwt_keys,
                                         &refresh_token_td.claims.sub,
                                     ) {
                                         Err(e) => {
                                             log::error!("{}", e);
                                             return ResponseMaker::respond_with_server_error(&req);
                                         }
-                                        Ok(rt) => rt,
+                                        Ok(at) => at,
                                     };
 
                                     // respond with new access token and new cookie with refresh token
                                     return ResponseMaker::jwt_response(
                                         &req,
                                         &StatusCode::OK,
+                                        &config.cookie,
                                         &new_access_token,
                                         &new_refresh_token,
                                     );
@@ -527,11 +875,263 @@ impl Authentication {
                 }
             }
         } else {
-            return ResponseMaker::general_response(
+            return ResponseMaker::error_response(
                 &req,
                 &StatusCode::UNAUTHORIZED,
+                ErrorCode::TokenMissing,
                 "Refresh token cookie is missing",
             );
         }
     }
 }
+
+// count the failed login and respond the same way whatever was wrong
+// so the response doesn't tell which emails are registered
+async fn _login_failed(
+    req: &HttpRequest,
+    login_attempts: &dyn LoginAttemptStore,
+    throttle_config: &LoginThrottleConfig,
+    email: &str,
+    client_info: &ClientInfo,
+) -> HttpResponse {
+    if let Err(e) = LoginThrottleService::record_failure(
+        login_attempts,
+        throttle_config,
+        email,
+        client_info.ip_address.as_deref(),
+    )
+    .await
+    {
+        log::error!("Unable to record failed login. {}", e);
+    }
+
+    ResponseMaker::general_response(
+        req,
+        &StatusCode::UNAUTHORIZED,
+        "Invalid email and/or password",
+    )
+}
+
+// needs a migrated MySQL database - DATABASE_URL and the other settings come from .env like the server
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::body::MessageBody;
+    use actix_web::cookie::Cookie;
+    use actix_web::dev::ServiceResponse;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{App, web};
+    use sqlx::MySqlPool;
+
+    use super::scopes;
+    use crate::config::app_config::AppConfig;
+    use crate::dtos::register_dto::RegisterRequestData;
+    use crate::middlewares::csrf_middleware::CSRF_HEADER;
+    use crate::services::user_service::UserService;
+    use crate::stores::login_attempt_store::LoginAttemptStore;
+    use crate::stores::memory_login_attempt_store::MemoryLoginAttemptStore;
+    use crate::utils::db_utils::DatabasePool;
+    use crate::utils::jwt_utils::JwtKeys;
+    use crate::utils::string_utils::random_alphanumeric;
+
+    const PASSWORD: &str = "correct-horse-battery";
+
+    // app data of the auth routes, the way main.rs sets it up
+    struct TestApp {
+        config: web::Data<AppConfig>,
+        pool: MySqlPool,
+        jwt_keys: web::Data<JwtKeys>,
+        login_attempts: Arc<dyn LoginAttemptStore>,
+    }
+
+    impl TestApp {
+        async fn new() -> TestApp {
+            dotenvy::dotenv().ok();
+
+            let config = AppConfig::load().expect("Failed to load config");
+            let pool = DatabasePool::new(&config.database)
+                .await
+                .expect("Failed to connect to database")
+                .pool;
+            sqlx::migrate!()
+                .run(&pool)
+                .await
+                .expect("Failed to run migrations");
+
+            TestApp {
+                jwt_keys: web::Data::new(
+                    JwtKeys::from_config(&config.jwt).expect("Failed to load JWT keys"),
+                ),
+                config: web::Data::new(config),
+                pool,
+                login_attempts: Arc::new(MemoryLoginAttemptStore::default()),
+            }
+        }
+
+        fn configure(&self, cfg: &mut web::ServiceConfig) {
+            cfg.app_data(self.config.clone())
+                .app_data(web::Data::new(self.pool.clone()))
+                .app_data(self.jwt_keys.clone())
+                .app_data(web::Data::from(self.login_attempts.clone()))
+                .service(web::scope("/api").service(scopes(&self.config.rate_limit)));
+        }
+
+        // a new user with its own email and handle, returns the email
+        async fn register(&self) -> String {
+            let suffix = random_alphanumeric(12).to_lowercase();
+            let email = format!("logout_all_{}@example.com", suffix);
+
+            UserService::create_user(
+                &self.pool,
+                &RegisterRequestData {
+                    firstname: "Logout".to_string(),
+                    lastname: "All".to_string(),
+                    handle: format!("t{}", suffix),
+                    email: email.clone(),
+                    password: PASSWORD.to_string(),
+                    repeat: PASSWORD.to_string(),
+                    invite: None,
+                },
+                None,
+            )
+            .await
+            .expect("Failed to create user");
+
+            email
+        }
+
+        fn refresh_request(&self, access_token: &str, session: &Session) -> TestRequest {
+            TestRequest::post()
+                .uri("/api/auth/refresh")
+                .insert_header(("Authorization", format!("Bearer {}", access_token)))
+                .insert_header((CSRF_HEADER, session.csrf_token.clone()))
+                .cookie(Cookie::new(
+                    self.config.cookie.refresh_cookie_name(),
+                    session.refresh_token.clone(),
+                ))
+                .cookie(Cookie::new(
+                    self.config.cookie.csrf_cookie_name(),
+                    session.csrf_token.clone(),
+                ))
+        }
+    }
+
+    // tokens handed out by a login
+    struct Session {
+        access_token: String,
+        refresh_token: String,
+        csrf_token: String,
+    }
+
+    impl Session {
+        async fn from_response<B: MessageBody>(
+            config: &AppConfig,
+            resp: ServiceResponse<B>,
+        ) -> Session {
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let cookie_value = |name: &str| {
+                resp.response()
+                    .cookies()
+                    .find(|c| c.name() == name)
+                    .map(|c| c.value().to_string())
+                    .expect("Login response is missing a cookie")
+            };
+            let refresh_token = cookie_value(config.cookie.refresh_cookie_name());
+            let csrf_token = cookie_value(config.cookie.csrf_cookie_name());
+
+            let body: serde_json::Value = test::read_body_json(resp).await;
+
+            Session {
+                access_token: body["payload"]
+                    .as_str()
+                    .expect("Login response is missing the access token")
+                    .to_string(),
+                refresh_token,
+                csrf_token,
+            }
+        }
+    }
+
+    fn _login_request(email: &str) -> TestRequest {
+        TestRequest::post()
+            .uri("/api/auth/login")
+            .set_form([("email", email), ("password", PASSWORD)])
+    }
+
+    fn _logout_all_request(access_token: &str) -> TestRequest {
+        TestRequest::post()
+            .uri("/api/auth/logout-all")
+            .insert_header(("Authorization", format!("Bearer {}", access_token)))
+    }
+
+    #[actix_web::test]
+    async fn logout_all_rejects_old_access_token() {
+        let test_app = TestApp::new().await;
+        let app = test::init_service(App::new().configure(|cfg| test_app.configure(cfg))).await;
+        let email = test_app.register().await;
+
+        let resp = test::call_service(&app, _login_request(&email).to_request()).await;
+        let session = Session::from_response(&test_app.config, resp).await;
+
+        let resp = test::call_service(
+            &app,
+            _logout_all_request(&session.access_token).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // still unexpired, but its sub is the authid logout-all replaced
+        let resp = test::call_service(
+            &app,
+            TestRequest::get()
+                .uri("/api/auth/sessions")
+                .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn logout_all_rejects_old_refresh_token() {
+        let test_app = TestApp::new().await;
+        let app = test::init_service(App::new().configure(|cfg| test_app.configure(cfg))).await;
+        let email = test_app.register().await;
+
+        let resp = test::call_service(&app, _login_request(&email).to_request()).await;
+        let old_session = Session::from_response(&test_app.config, resp).await;
+
+        let resp = test::call_service(
+            &app,
+            _logout_all_request(&old_session.access_token).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // a fresh access token gets past AuthRequired, so it's the old refresh token that is refused
+        let resp = test::call_service(&app, _login_request(&email).to_request()).await;
+        let new_session = Session::from_response(&test_app.config, resp).await;
+
+        let resp = test::call_service(
+            &app,
+            test_app
+                .refresh_request(&new_session.access_token, &old_session)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // and with the old access token as well
+        let resp = test::call_service(
+            &app,
+            test_app
+                .refresh_request(&old_session.access_token, &old_session)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
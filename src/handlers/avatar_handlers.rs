@@ -0,0 +1,222 @@
+use actix_multipart::Multipart;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::web;
+
+use futures_util::StreamExt;
+use sqlx::MySqlPool;
+
+use crate::config::app_config::AppConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::models::user_models::user_avatar_model::UserAvatarModel;
+use crate::models::user_models::user_profile_model::UserProfileModel;
+use crate::services::avatar_service::{AvatarService, AvatarUpload};
+use crate::services::profile_service::ProfileService;
+use crate::stores::blob_store::BlobStore;
+use crate::utils::response_utils::ResponseMaker;
+
+// name of the multipart field with the image
+const AVATAR_FIELD: &str = "avatar";
+
+pub struct Avatar {}
+
+impl Avatar {
+    pub async fn upload(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        blob_store: web::Data<dyn BlobStore>,
+        config: web::Data<AppConfig>,
+        payload: Multipart,
+    ) -> impl Responder {
+        /*
+            - Read the avatar field of the multipart body, up to the size limit
+            - Check the image and make its variants - metadata is dropped along the way
+            - Store the variants and make them the user's avatar
+            - Respond with the urls of the variants
+        */
+
+        let content = match _read_field(payload, AVATAR_FIELD, config.avatar.max_bytes).await {
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    format!("Invalid multipart body. {}", e),
+                );
+            }
+            Ok(FieldRead::Missing) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    format!("Missing {} field", AVATAR_FIELD),
+                );
+            }
+            Ok(FieldRead::TooLarge) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::PAYLOAD_TOO_LARGE,
+                    format!("Must not exceed {} bytes", config.avatar.max_bytes),
+                );
+            }
+            Ok(FieldRead::Content(c)) => c,
+        };
+
+        match AvatarService::upload(
+            &pool,
+            blob_store.get_ref(),
+            &config.avatar,
+            auth_user.user.id,
+            content,
+        )
+        .await
+        {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(AvatarUpload::Rejected(reason)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::UNPROCESSABLE_ENTITY,
+                    reason,
+                );
+            }
+            Ok(AvatarUpload::Saved(avatar)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    AvatarService::urls(&config.server, &avatar.value),
+                );
+            }
+        }
+    }
+
+    pub async fn remove(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        blob_store: web::Data<dyn BlobStore>,
+    ) -> impl Responder {
+        /*
+            - Get the user from the access token sub
+            - Remove its avatar and the stored variants
+        */
+
+        match AvatarService::remove(&pool, blob_store.get_ref(), auth_user.user.id).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(false) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::NOT_FOUND,
+                    "No avatar to remove",
+                );
+            }
+            Ok(true) => {
+                return ResponseMaker::general_response(&req, &StatusCode::OK, "Avatar removed");
+            }
+        }
+    }
+
+    pub async fn get(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        blob_store: web::Data<dyn BlobStore>,
+        path: web::Path<(String, String)>,
+    ) -> impl Responder {
+        /*
+            - Get the avatar by its id, and the variant by name
+                - avatars of deactivated and deleted users are hidden
+            - Respond with the image - an avatar id is never reused so it can be cached for good
+        */
+
+        let (avatar_value, variant) = path.into_inner();
+
+        let key = match AvatarService::variant_blob_key(&avatar_value, &variant) {
+            None => return _not_found(&req),
+            Some(k) => k,
+        };
+
+        let avatar = match UserAvatarModel::get_by_value(&pool, &avatar_value).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(None) => return _not_found(&req),
+            Ok(Some(a)) => a,
+        };
+
+        match UserProfileModel::get_by_user_id(&pool, avatar.user_id).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(Some(profile)) if ProfileService::is_visible(&profile) => {}
+            Ok(_) => return _not_found(&req),
+        }
+
+        match blob_store.get(&key).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(None) => {
+                log::error!("Avatar {} has no blob {}", avatar.id, key);
+                return _not_found(&req);
+            }
+            Ok(Some(image)) => {
+                return HttpResponse::Ok()
+                    .content_type("image/webp")
+                    .insert_header(CacheControl(vec![
+                        CacheDirective::Public,
+                        CacheDirective::MaxAge(31_536_000),
+                        CacheDirective::Extension("immutable".to_string(), None),
+                    ]))
+                    .body(image);
+            }
+        }
+    }
+}
+
+// What reading a field of a multipart body found
+enum FieldRead {
+    Content(Vec<u8>),
+    Missing,
+    TooLarge, // reading stopped once the limit was passed
+}
+
+// content of the first field with the given name, other fields are skipped
+async fn _read_field(
+    mut payload: Multipart,
+    field_name: &str,
+    max_bytes: usize,
+) -> Result<FieldRead, actix_multipart::MultipartError> {
+    while let Some(field) = payload.next().await {
+        let mut field = field?;
+        if field.name() != Some(field_name) {
+            continue;
+        }
+
+        let mut content: Vec<u8> = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk?;
+            if content.len() + chunk.len() > max_bytes {
+                return Ok(FieldRead::TooLarge);
+            }
+            content.extend_from_slice(&chunk);
+        }
+
+        return Ok(FieldRead::Content(content));
+    }
+
+    Ok(FieldRead::Missing)
+}
+
+fn _not_found(req: &HttpRequest) -> HttpResponse {
+    ResponseMaker::general_response(req, &StatusCode::NOT_FOUND, "Avatar not found")
+}
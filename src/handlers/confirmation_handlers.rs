@@ -0,0 +1,111 @@
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+use actix_web::web;
+
+use sqlx::MySqlPool;
+
+use validator::Validate;
+
+use crate::config::app_config::AppConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::mailers::mailer::Mailer;
+use crate::services::confirmation_service::ConfirmationService;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::dtos::confirm_dto::ConfirmRequestData;
+
+pub struct Confirmation {}
+
+impl Confirmation {
+    pub async fn confirm(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        data: web::Json<ConfirmRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Use up the confirmation token
+            - Stamp datetime_confirmed of the token's user
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        match ConfirmationService::confirm(&pool, &data.token).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(None) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    "Confirmation token is invalid or has expired",
+                );
+            }
+            Ok(Some(_)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    "Email address confirmed",
+                );
+            }
+        }
+    }
+
+    pub async fn resend(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        mailer: web::Data<dyn Mailer>,
+        config: web::Data<AppConfig>,
+    ) -> impl Responder {
+        /*
+            - Get the authid from access token - auth middleware already takes care of decoding the access token
+            - Get the user
+            - Issue a new confirmation token, this also invalidates the previous one
+        */
+
+        // user of the access token - AuthenticatedUser takes care of looking it up
+        let user = auth_user.user;
+
+        if user.datetime_confirmed.is_some() {
+            return ResponseMaker::general_response(
+                &req,
+                &StatusCode::CONFLICT,
+                "Email address is already confirmed",
+            );
+        }
+
+        match ConfirmationService::send_confirmation(
+            &pool,
+            mailer.get_ref(),
+            &config.mail,
+            &config.tokens,
+            &user,
+        )
+        .await
+        {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(_) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    "Confirmation email sent",
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+use actix_web::web;
+
+use sqlx::MySqlPool;
+
+use validator::Validate;
+
+use crate::config::app_config::AppConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::mailers::mailer::Mailer;
+use crate::models::user_models::user_email_model::UserEmailModel;
+use crate::models::user_models::user_model::UserModel;
+use crate::services::email_change_service::EmailChangeOutcome;
+use crate::services::email_change_service::EmailChangeService;
+use crate::services::refresh_token_service::RefreshTokenService;
+use crate::utils::bcrypt_utils::is_matched;
+use crate::utils::client_utils::ClientInfo;
+use crate::utils::jwt_utils::JwtKeys;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::dtos::confirm_dto::ConfirmRequestData;
+use crate::dtos::email_dto::ChangeEmailRequestData;
+
+pub struct Email {}
+
+impl Email {
+    pub async fn change(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        mailer: web::Data<dyn Mailer>,
+        config: web::Data<AppConfig>,
+        data: web::Json<ChangeEmailRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Get the user from the access token sub
+            - Check the current password
+            - Check the new email is not in use
+            - Mail a confirmation link to the new email
+                - the user keeps the current email until the link is used
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        // user of the access token - AuthenticatedUser takes care of looking it up
+        let user = auth_user.user;
+
+        match is_matched(&data.current_password, &user.password) {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(false) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    "Current password is incorrect",
+                );
+            }
+            Ok(true) => {}
+        }
+
+        // check email if in use
+        match UserEmailModel::get_by_value(&pool, &data.email).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(None) => {}
+            Ok(Some(uem)) => match UserModel::get_by_email_id(&pool, uem.id).await {
+                Err(e) => {
+                    log::error!("{}", e);
+                    return ResponseMaker::respond_with_server_error(&req);
+                }
+                Ok(None) => {}
+                Ok(Some(_)) => {
+                    // already in use - including by the user itself
+                    return ResponseMaker::general_response(
+                        &req,
+                        &StatusCode::CONFLICT,
+                        "Email address is already in use",
+                    );
+                }
+            },
+        }
+
+        match EmailChangeService::request_change(
+            &pool,
+            mailer.get_ref(),
+            &config.mail,
+            &config.tokens,
+            &user,
+            &data.email,
+        )
+        .await
+        {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(_) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::ACCEPTED,
+                    "Confirmation link sent to the new email address",
+                );
+            }
+        }
+    }
+
+    pub async fn confirm(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        jwt_keys: web::Data<JwtKeys>,
+        config: web::Data<AppConfig>,
+        data: web::Json<ConfirmRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Get the user from the access token sub
+            - Use up the email change token and move the user to the new email
+                - user's authid is rotated, this logs out every other session
+            - Respond with new access and refresh tokens so this session stays logged in
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        // user of the access token - AuthenticatedUser takes care of looking it up
+        let mut user = auth_user.user;
+
+        match EmailChangeService::confirm_change(&pool, &mut user, &data.token).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(EmailChangeOutcome::InvalidToken) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    "Email change token is invalid or has expired",
+                );
+            }
+            Ok(EmailChangeOutcome::EmailInUse) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::CONFLICT,
+                    "Email address is already in use",
+                );
+            }
+            Ok(EmailChangeOutcome::Changed) => {}
+        }
+
+        return match RefreshTokenService::start_session(
+            &pool,
+            &jwt_keys,
+            &user,
+            &ClientInfo::from_request(&req),
+        )
+        .await
+        {
+            Err(e) => {
+                log::error!("{}", e);
+                ResponseMaker::respond_with_server_error(&req)
+            }
+            Ok((access_token, refresh_token)) => ResponseMaker::jwt_response(
+                &req,
+                &StatusCode::OK,
+                &config.cookie,
+                &access_token,
+                &refresh_token,
+            ),
+        };
+    }
+}
@@ -0,0 +1,211 @@
+use std::fs;
+
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+use actix_web::http::header::CONTENT_DISPOSITION;
+use actix_web::rt;
+use actix_web::web;
+
+use chrono::Utc;
+use sqlx::MySqlPool;
+
+use crate::config::app_config::AppConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::mailers::mailer::Mailer;
+use crate::models::user_models::user_export_model::UserExportModel;
+use crate::services::export_service::{ExportRequest, ExportService};
+use crate::stores::blob_store::BlobStore;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::dtos::export_dto::ExportDownloadQuery;
+use crate::dtos::export_dto::ExportResponseData;
+
+pub struct Export {}
+
+impl Export {
+    pub async fn request(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        blob_store: web::Data<dyn BlobStore>,
+        mailer: web::Data<dyn Mailer>,
+        config: web::Data<AppConfig>,
+    ) -> impl Responder {
+        /*
+            - Get the user from the access token sub
+            - Start a new export, or point to the one still being built
+            - Build the archive in the background
+                - the download link is mailed to the user once it is ready
+            - Respond with the export so its status can be checked
+        */
+
+        let export = match ExportService::request(&pool, &config.export, auth_user.user.id).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(ExportRequest::InProgress(export)) => export,
+            Ok(ExportRequest::Started(export)) => {
+                let response = _export_response(&config, &export);
+
+                let pool = pool.get_ref().clone();
+                let blob_store = blob_store.into_inner();
+                let mailer = mailer.into_inner();
+                let config = config.into_inner();
+                rt::spawn(async move {
+                    ExportService::build(
+                        &pool,
+                        blob_store.as_ref(),
+                        mailer.as_ref(),
+                        &config.server,
+                        &config.export,
+                        export,
+                    )
+                    .await;
+                });
+
+                return ResponseMaker::general_response(&req, &StatusCode::ACCEPTED, response);
+            }
+        };
+
+        return ResponseMaker::general_response(
+            &req,
+            &StatusCode::ACCEPTED,
+            _export_response(&config, &export),
+        );
+    }
+
+    pub async fn status(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+        path: web::Path<String>,
+    ) -> impl Responder {
+        /*
+            - Get the user from the access token sub
+            - Get the export, it must belong to the user
+            - Respond with its status, and a fresh download link once it is ready
+        */
+
+        match UserExportModel::get_by_value(&pool, &path.into_inner()).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(Some(export)) if export.user_id == auth_user.user.id => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    _export_response(&config, &export),
+                );
+            }
+            Ok(_) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::NOT_FOUND,
+                    "Export not found",
+                );
+            }
+        }
+    }
+
+    pub async fn download(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+        path: web::Path<String>,
+        query: web::Query<ExportDownloadQuery>,
+    ) -> impl Responder {
+        /*
+            - Check the signature and expiry of the link - no access token, the link is the proof
+            - Get the export, it must be ready and not expired
+            - Respond with the archive as an attachment
+        */
+
+        let export_value = path.into_inner();
+
+        if !ExportService::is_valid_download(
+            &config.export,
+            &export_value,
+            query.expires,
+            &query.signature,
+        ) {
+            return ResponseMaker::general_response(
+                &req,
+                &StatusCode::FORBIDDEN,
+                "Download link is invalid or has expired",
+            );
+        }
+
+        let export = match UserExportModel::get_by_value(&pool, &export_value).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(Some(e))
+                if e.datetime_ready.is_some() && e.datetime_ttl > Utc::now().naive_utc() =>
+            {
+                e
+            }
+            Ok(_) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::NOT_FOUND,
+                    "Export not found",
+                );
+            }
+        };
+
+        let archive = match fs::read(ExportService::archive_path(&config.export, &export)) {
+            Err(e) => {
+                log::error!("Unable to read archive of export {}. {}", export.id, e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(a) => a,
+        };
+
+        return HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"export-{}.zip\"", export.value),
+            ))
+            .body(archive);
+    }
+}
+
+// status of an export as the client sees it
+fn _export_response(config: &AppConfig, export: &UserExportModel) -> ExportResponseData {
+    let expired = export.datetime_ttl <= Utc::now().naive_utc();
+
+    let status = if expired {
+        "expired"
+    } else if export.datetime_ready.is_some() {
+        "ready"
+    } else if export.datetime_failed.is_some() {
+        "failed"
+    } else {
+        "pending"
+    };
+
+    let (download_url, datetime_download_url_expires) = match status {
+        "ready" => {
+            let (url, expires) =
+                ExportService::download_url(&config.server, &config.export, export);
+            (Some(url), Some(expires))
+        }
+        _ => (None, None),
+    };
+
+    ExportResponseData {
+        id: export.value.clone(),
+        status: status.to_string(),
+        datetime_created: export.datetime_created,
+        datetime_ttl: export.datetime_ttl,
+        download_url,
+        datetime_download_url_expires,
+    }
+}
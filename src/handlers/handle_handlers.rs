@@ -0,0 +1,174 @@
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+use actix_web::http::header::HeaderValue;
+use actix_web::http::header::LOCATION;
+use actix_web::web;
+
+use sqlx::MySqlPool;
+
+use validator::Validate;
+
+use crate::config::app_config::AppConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::services::handle_service::{HandleChange, HandleLookup, HandleService};
+use crate::services::profile_service::ProfileService;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::dtos::handle_dto::ChangeHandleRequestData;
+use crate::dtos::handle_dto::ChangeHandleTooSoonResponseData;
+use crate::dtos::handle_dto::HandleAvailabilityQuery;
+use crate::dtos::handle_dto::HandleAvailabilityResponseData;
+use crate::dtos::handle_dto::HandleResponseData;
+
+pub struct Handle {}
+
+impl Handle {
+    pub async fn available(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+        query: web::Query<HandleAvailabilityQuery>,
+    ) -> impl Responder {
+        /*
+            - Validate the handle - an invalid one is never available, say why
+            - Check nobody has it or released it recently
+        */
+
+        match query.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        match HandleService::is_available(&pool, &config.account, &query.handle, None).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(available) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    HandleAvailabilityResponseData {
+                        handle: query.into_inner().handle,
+                        available,
+                    },
+                );
+            }
+        }
+    }
+
+    pub async fn change(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+        data: web::Json<ChangeHandleRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Get the user from the access token sub
+            - Check the user hasn't changed its handle too recently
+            - Check the handle is available
+            - Give the user the handle
+                - the old one redirects to the new one for a while
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        match HandleService::change(&pool, &config.account, auth_user.user.id, &data.handle).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(HandleChange::Taken) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::CONFLICT,
+                    "Handle is already taken",
+                );
+            }
+            Ok(HandleChange::TooSoon(datetime_next_change)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::TOO_MANY_REQUESTS,
+                    ChangeHandleTooSoonResponseData {
+                        datetime_next_change,
+                    },
+                );
+            }
+            Ok(HandleChange::Changed(handle)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    HandleResponseData {
+                        handle: handle.value,
+                    },
+                );
+            }
+        }
+    }
+
+    pub async fn get(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+        path: web::Path<String>,
+    ) -> impl Responder {
+        /*
+            - Get the user by handle
+                - deactivated and deleted users are hidden
+            - A recently released handle redirects to the user's current one
+            - Respond with the public part of the profile only
+        */
+
+        match HandleService::resolve(&pool, &config.account, &path.into_inner()).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(HandleLookup::NotFound) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::NOT_FOUND,
+                    "User not found",
+                );
+            }
+            Ok(HandleLookup::Moved(handle)) => {
+                let location = format!("/api/users/handle/{}", handle);
+
+                let mut resp = ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::PERMANENT_REDIRECT,
+                    HandleResponseData { handle },
+                );
+                if let Ok(value) = HeaderValue::from_str(&location) {
+                    resp.headers_mut().insert(LOCATION, value);
+                }
+                return resp;
+            }
+            Ok(HandleLookup::Current(profile)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    ProfileService::to_public(&config.server, profile),
+                );
+            }
+        }
+    }
+}
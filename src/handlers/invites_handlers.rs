@@ -0,0 +1,262 @@
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use actix_web::Scope;
+use actix_web::http::StatusCode;
+use actix_web::web;
+
+use chrono::Utc;
+use sqlx::MySqlPool;
+
+use validator::Validate;
+
+use crate::config::app_config::AppConfig;
+use crate::config::app_config::RateLimitConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::models::user_models::user_invite_model::{InviteRestriction, UserInviteModel};
+use crate::models::user_models::user_invite_use_model::UserInviteUseModel;
+use crate::services::invite_service::InviteService;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::middlewares::jwt_auth_middleware::AuthRequired;
+use crate::middlewares::rate_limit_middleware::RateLimit;
+use crate::middlewares::rate_limit_middleware::RateLimitKey;
+
+use crate::dtos::invite_dto::CreateInviteRequestData;
+use crate::dtos::invite_dto::InviteDetailResponseData;
+use crate::dtos::invite_dto::InviteResponseData;
+use crate::dtos::invite_dto::InviteUseResponseData;
+
+// /api/invites
+// users only ever see the invites they created
+pub fn scopes(rate_limits: &RateLimitConfig) -> Scope {
+    web::scope("/invites")
+        .route(
+            "",
+            web::post()
+                .to(Invites::create)
+                .wrap(RateLimit::new(
+                    "invites_create",
+                    &rate_limits.invites_create,
+                    RateLimitKey::Sub,
+                ))
+                .wrap(AuthRequired {}),
+        )
+        .route("", web::get().to(Invites::list).wrap(AuthRequired {}))
+        .route("/{id}", web::get().to(Invites::get).wrap(AuthRequired {}))
+        .route(
+            "/{id}",
+            web::delete().to(Invites::revoke).wrap(AuthRequired {}),
+        )
+}
+
+pub struct Invites {}
+
+impl Invites {
+    pub async fn create(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+        data: web::Json<CreateInviteRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Check the user may create invites
+            - Create the invite
+            - Respond with the invite and its code - the code can't be seen again
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        let data = data.into_inner();
+        let restriction = match (data.email, data.domain) {
+            (Some(_), Some(_)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    "Only one of email and domain can be given",
+                );
+            }
+            (Some(email), None) => InviteRestriction::Email(email),
+            (None, Some(domain)) => InviteRestriction::Domain(domain),
+            (None, None) => InviteRestriction::Anyone,
+        };
+
+        match InviteService::can_create(&pool, &config.registration, &auth_user.user).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(false) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    "Not allowed to create invites",
+                );
+            }
+            Ok(true) => {}
+        }
+
+        match InviteService::create(
+            &pool,
+            &config.registration,
+            auth_user.user.id,
+            &restriction,
+            data.max_uses.unwrap_or(1),
+            data.expiration_days,
+        )
+        .await
+        {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok((invite, raw_code)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::CREATED,
+                    _invite_response(invite, Some(raw_code)),
+                );
+            }
+        }
+    }
+
+    pub async fn list(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+    ) -> impl Responder {
+        /*
+            - Get the user from the access token sub
+            - Respond with the invites the user created, latest first
+        */
+
+        match UserInviteModel::get_by_created_by_user_id(&pool, auth_user.user.id).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(invites) => {
+                let invites: Vec<InviteResponseData> = invites
+                    .into_iter()
+                    .map(|i| _invite_response(i, None))
+                    .collect();
+                return ResponseMaker::general_response(&req, &StatusCode::OK, invites);
+            }
+        }
+    }
+
+    pub async fn get(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        path: web::Path<String>,
+    ) -> impl Responder {
+        /*
+            - Get the invite, it must have been created by the user
+            - Respond with it and the users that registered with it
+        */
+
+        let invite = match UserInviteModel::get_by_value(&pool, &path.into_inner()).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(Some(i)) if i.created_by_user_id == auth_user.user.id => i,
+            Ok(_) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::NOT_FOUND,
+                    "Invite not found",
+                );
+            }
+        };
+
+        let uses = match UserInviteUseModel::get_by_invite_id(&pool, invite.id).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(u) => u,
+        };
+
+        return ResponseMaker::general_response(
+            &req,
+            &StatusCode::OK,
+            InviteDetailResponseData {
+                invite: _invite_response(invite, None),
+                uses: uses
+                    .into_iter()
+                    .map(|u| InviteUseResponseData {
+                        pid: u.pid,
+                        datetime_created: u.datetime_created,
+                    })
+                    .collect(),
+            },
+        );
+    }
+
+    pub async fn revoke(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        path: web::Path<String>,
+    ) -> impl Responder {
+        /*
+            - Get the invite, it must have been created by the user
+            - Stamp datetime_revoked - users that already registered with it are not affected
+        */
+
+        let mut invite = match UserInviteModel::get_by_value(&pool, &path.into_inner()).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(Some(i)) if i.created_by_user_id == auth_user.user.id => i,
+            Ok(_) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::NOT_FOUND,
+                    "Invite not found",
+                );
+            }
+        };
+
+        match invite.revoke(&pool, &Utc::now().naive_utc()).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(_) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    _invite_response(invite, None),
+                );
+            }
+        }
+    }
+}
+
+fn _invite_response(invite: UserInviteModel, code: Option<String>) -> InviteResponseData {
+    InviteResponseData {
+        id: invite.value,
+        code,
+        email: invite.email,
+        domain: invite.domain,
+        max_uses: invite.max_uses,
+        uses: invite.uses,
+        datetime_ttl: invite.datetime_ttl,
+        datetime_revoked: invite.datetime_revoked,
+        datetime_created: invite.datetime_created,
+    }
+}
@@ -0,0 +1,25 @@
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::web;
+
+use crate::utils::jwt_utils::JwtKeys;
+
+pub struct Jwks {}
+
+impl Jwks {
+    pub async fn get(jwt_keys: web::Data<JwtKeys>) -> impl Responder {
+        /*
+            - Respond with the public keys access tokens can be verified with
+                - plain JWK Set (RFC 7517) instead of the json envelope so standard JWT libraries can read it
+            - Other services cache it for a while and refetch when they see an unknown kid
+        */
+
+        HttpResponse::Ok()
+            .insert_header(CacheControl(vec![
+                CacheDirective::Public,
+                CacheDirective::MaxAge(300),
+            ]))
+            .json(jwt_keys.jwks())
+    }
+}
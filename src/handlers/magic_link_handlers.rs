@@ -0,0 +1,188 @@
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+use actix_web::rt;
+use actix_web::web;
+
+use sqlx::MySqlPool;
+
+use validator::Validate;
+
+use crate::config::app_config::AppConfig;
+use crate::mailers::mailer::Mailer;
+use crate::services::magic_link_service::MagicLinkService;
+use crate::services::mfa_service::MfaService;
+use crate::services::refresh_token_service::RefreshTokenService;
+use crate::utils::client_utils::ClientInfo;
+use crate::utils::jwt_utils::JwtKeys;
+use crate::utils::response_utils::ErrorCode;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::dtos::magic_link_dto::MagicLinkLoginRequestData;
+use crate::dtos::magic_link_dto::MagicLinkRequestData;
+use crate::dtos::mfa_dto::MfaPendingResponseData;
+
+pub struct MagicLink {}
+
+impl MagicLink {
+    pub async fn request(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        mailer: web::Data<dyn Mailer>,
+        config: web::Data<AppConfig>,
+        data: web::Json<MagicLinkRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Look up the email and mail a sign-in link in the background
+            - Always respond the same way so this can't be used to find out which emails are registered
+        */
+
+        if !config.magic_link.enabled {
+            return _disabled(&req);
+        }
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        // done in the background so response time doesn't depend on the email existing
+        let pool = pool.get_ref().clone();
+        let mailer = mailer.into_inner();
+        let config = config.into_inner();
+        let email = data.into_inner().email;
+        rt::spawn(async move {
+            if let Err(e) = MagicLinkService::send(
+                &pool,
+                mailer.as_ref(),
+                &config.mail,
+                &config.magic_link,
+                &email,
+            )
+            .await
+            {
+                log::error!("Unable to send sign-in link email. {}", e);
+            }
+        });
+
+        return ResponseMaker::general_response(
+            &req,
+            &StatusCode::OK,
+            "If the email address is registered, a sign-in link has been sent",
+        );
+    }
+
+    pub async fn login(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        jwt_keys: web::Data<JwtKeys>,
+        config: web::Data<AppConfig>,
+        data: web::Json<MagicLinkLoginRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Use up the sign-in token
+            - Deactivated or deleted accounts are refused, same as login
+            - If the user has 2FA, respond with a pending token for /login/mfa instead
+            - Create access token and refresh token
+            - Access token goes to response body and refresh token goes into cookie
+        */
+
+        if !config.magic_link.enabled {
+            return _disabled(&req);
+        }
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        let user = match MagicLinkService::consume(&pool, &data.token).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(None) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::UNAUTHORIZED,
+                    "Sign-in link is invalid or has expired",
+                );
+            }
+            Ok(Some(u)) => u,
+        };
+
+        if user.datetime_deactivated.is_some() || user.datetime_deleted.is_some() {
+            return ResponseMaker::error_response(
+                &req,
+                &StatusCode::FORBIDDEN,
+                ErrorCode::AccountInactive,
+                "Account is deactivated. Request a reactivation link to bring it back",
+            );
+        }
+
+        // the link stands in for the password only, 2FA users still finish at /login/mfa
+        match MfaService::is_enabled(&pool, user.id).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(true) => {
+                return match MfaService::start_login(&pool, &config.mfa, user.id, false).await {
+                    Err(e) => {
+                        log::error!("{}", e);
+                        ResponseMaker::respond_with_server_error(&req)
+                    }
+                    Ok(mfa_token) => ResponseMaker::general_response(
+                        &req,
+                        &StatusCode::OK,
+                        MfaPendingResponseData {
+                            mfa_required: true,
+                            mfa_token,
+                        },
+                    ),
+                };
+            }
+            Ok(false) => {}
+        }
+
+        return match RefreshTokenService::start_session(
+            &pool,
+            &jwt_keys,
+            &user,
+            &ClientInfo::from_request(&req),
+        )
+        .await
+        {
+            Err(e) => {
+                log::error!("{}", e);
+                ResponseMaker::respond_with_server_error(&req)
+            }
+            Ok((access_token, refresh_token)) => ResponseMaker::jwt_response(
+                &req,
+                &StatusCode::OK,
+                &config.cookie,
+                &access_token,
+                &refresh_token,
+            ),
+        };
+    }
+}
+
+fn _disabled(req: &HttpRequest) -> HttpResponse {
+    ResponseMaker::general_response(req, &StatusCode::NOT_FOUND, "Magic link login is disabled")
+}
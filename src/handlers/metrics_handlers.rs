@@ -0,0 +1,19 @@
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+
+use crate::services::auth_service::AuthService;
+use crate::utils::response_utils::ResponseMaker;
+
+pub struct Metrics {}
+
+impl Metrics {
+    pub async fn cache(req: HttpRequest) -> impl Responder {
+        /*
+            - Respond with entries, hits and misses of the in-process caches of this instance
+            - Only routed when SERVER_METRICS_ENABLED is set
+        */
+
+        return ResponseMaker::general_response(&req, &StatusCode::OK, AuthService::cache_stats());
+    }
+}
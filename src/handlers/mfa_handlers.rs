@@ -0,0 +1,304 @@
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+use actix_web::web;
+
+use sqlx::MySqlPool;
+
+use validator::Validate;
+
+use crate::config::app_config::AppConfig;
+use crate::extractors::auth_extractor::{AuthenticatedUser, ConfirmedUser};
+use crate::services::account_service::AccountService;
+use crate::services::mfa_service::{MfaLoginOutcome, MfaService};
+use crate::services::refresh_token_service::RefreshTokenService;
+use crate::stores::login_attempt_store::LoginAttemptStore;
+use crate::utils::bcrypt_utils::is_matched;
+use crate::utils::client_utils::ClientInfo;
+use crate::utils::jwt_utils::JwtKeys;
+use crate::utils::response_utils::ErrorCode;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::dtos::mfa_dto::MfaDisableRequestData;
+use crate::dtos::mfa_dto::MfaEnrollResponseData;
+use crate::dtos::mfa_dto::MfaLoginRequestData;
+use crate::dtos::mfa_dto::MfaRecoveryCodesResponseData;
+use crate::dtos::mfa_dto::MfaVerifyRequestData;
+
+pub struct Mfa {}
+
+impl Mfa {
+    pub async fn login(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        jwt_keys: web::Data<JwtKeys>,
+        config: web::Data<AppConfig>,
+        login_attempts: web::Data<dyn LoginAttemptStore>,
+        data: web::Json<MfaLoginRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Use up the mfa pending token from the password step and check the code
+                - authenticator code or one of the recovery codes
+                - refused with 429 while the email or ip is backing off or locked out
+                - a wrong code is counted against the email and the ip like a wrong password
+            - Reactivate the account if the password step asked to
+            - Create access token and refresh token
+            - Access token goes to response body and refresh token goes into cookie
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        let client_info = ClientInfo::from_request(&req);
+
+        let (mut user, reactivate) = match MfaService::complete_login(
+            &pool,
+            login_attempts.get_ref(),
+            &config.login_throttle,
+            &data.mfa_token,
+            &data.code,
+            client_info.ip_address.as_deref(),
+        )
+        .await
+        {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(MfaLoginOutcome::RetryAfter(retry_after)) => {
+                return ResponseMaker::too_many_requests_response(
+                    &req,
+                    retry_after,
+                    "Too many failed login attempts. Please try again later",
+                );
+            }
+            Ok(MfaLoginOutcome::InvalidToken) | Ok(MfaLoginOutcome::WrongCode) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::UNAUTHORIZED,
+                    "Invalid code. Please login again",
+                );
+            }
+            Ok(MfaLoginOutcome::Completed { user, reactivate }) => (user, reactivate),
+        };
+
+        if user.datetime_deactivated.is_some() || user.datetime_deleted.is_some() {
+            if !reactivate {
+                return ResponseMaker::error_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    ErrorCode::AccountInactive,
+                    "Account is deactivated. Login with reactivate to bring it back",
+                );
+            }
+
+            match AccountService::reactivate(&pool, &mut user).await {
+                Err(e) => {
+                    log::error!("{}", e);
+                    return ResponseMaker::respond_with_server_error(&req);
+                }
+                Ok(false) => {
+                    // anonymised since the password step
+                    return ResponseMaker::general_response(
+                        &req,
+                        &StatusCode::UNAUTHORIZED,
+                        "Invalid code. Please login again",
+                    );
+                }
+                Ok(true) => {}
+            }
+        }
+
+        return match RefreshTokenService::start_session(&pool, &jwt_keys, &user, &client_info).await
+        {
+            Err(e) => {
+                log::error!("{}", e);
+                ResponseMaker::respond_with_server_error(&req)
+            }
+            Ok((access_token, refresh_token)) => ResponseMaker::jwt_response(
+                &req,
+                &StatusCode::OK,
+                &config.cookie,
+                &access_token,
+                &refresh_token,
+            ),
+        };
+    }
+
+    pub async fn enroll(
+        req: HttpRequest,
+        ConfirmedUser(auth_user): ConfirmedUser,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+    ) -> impl Responder {
+        /*
+            - Get the user from the access token sub
+            - Create a new secret, 2FA stays off until a code from it is verified
+            - Respond with the secret and the otpauth uri for the QR code
+        */
+
+        let user = auth_user.user;
+
+        match MfaService::start_enrollment(&pool, &config.mfa, &user).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(None) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::CONFLICT,
+                    "Two-factor authentication is already enabled",
+                );
+            }
+            Ok(Some((secret, otpauth_uri))) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    MfaEnrollResponseData {
+                        secret,
+                        otpauth_uri,
+                    },
+                );
+            }
+        }
+    }
+
+    pub async fn verify(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        data: web::Json<MfaVerifyRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Get the user from the access token sub
+            - Check the code against the pending secret and turn 2FA on
+            - Respond with the recovery codes - this is the only time they are shown
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        let user = auth_user.user;
+
+        match MfaService::confirm_enrollment(&pool, user.id, &data.code).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(None) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    "Invalid code or no two-factor enrollment in progress",
+                );
+            }
+            Ok(Some(recovery_codes)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    MfaRecoveryCodesResponseData { recovery_codes },
+                );
+            }
+        }
+    }
+
+    pub async fn disable(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        data: web::Json<MfaDisableRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Get the user from the access token sub
+            - Check the current password and a code
+            - Remove the secret and recovery codes
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        let user = auth_user.user;
+
+        match is_matched(&data.current_password, &user.password) {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(false) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    "Current password is incorrect",
+                );
+            }
+            Ok(true) => {}
+        }
+
+        let mut tx = match pool.begin().await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(t) => t,
+        };
+
+        match MfaService::check_code(&pool, &mut tx, user.id, &data.code).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(false) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    "Invalid code",
+                );
+            }
+            Ok(true) => {}
+        }
+
+        if let Err(e) = MfaService::disable(&mut tx, user.id).await {
+            log::error!("{}", e);
+            return ResponseMaker::respond_with_server_error(&req);
+        }
+
+        if let Err(e) = tx.commit().await {
+            log::error!("{}", e);
+            return ResponseMaker::respond_with_server_error(&req);
+        }
+
+        return ResponseMaker::general_response(
+            &req,
+            &StatusCode::OK,
+            "Two-factor authentication disabled",
+        );
+    }
+}
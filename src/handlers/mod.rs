@@ -1,5 +1,18 @@
 // pub mod board_handlers;
 // pub mod organisation_handlers;
-// pub mod user_handlers;
 
+pub mod account_handlers;
 pub mod auth_handlers;
+pub mod avatar_handlers;
+pub mod confirmation_handlers;
+pub mod email_handlers;
+pub mod export_handlers;
+pub mod handle_handlers;
+pub mod invites_handlers;
+pub mod jwks_handlers;
+pub mod magic_link_handlers;
+pub mod metrics_handlers;
+pub mod mfa_handlers;
+pub mod password_handlers;
+pub mod session_handlers;
+pub mod users_handlers;
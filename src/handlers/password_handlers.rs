@@ -0,0 +1,213 @@
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+use actix_web::rt;
+use actix_web::web;
+
+use sqlx::MySqlPool;
+
+use validator::Validate;
+
+use crate::config::app_config::AppConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::mailers::mailer::Mailer;
+use crate::services::password_service::PasswordService;
+use crate::services::refresh_token_service::RefreshTokenService;
+use crate::utils::bcrypt_utils::is_matched;
+use crate::utils::client_utils::ClientInfo;
+use crate::utils::jwt_utils::JwtKeys;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::dtos::password_dto::ChangePasswordRequestData;
+use crate::dtos::password_dto::ForgotPasswordRequestData;
+use crate::dtos::password_dto::ResetPasswordRequestData;
+
+pub struct Password {}
+
+impl Password {
+    pub async fn forgot(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        mailer: web::Data<dyn Mailer>,
+        config: web::Data<AppConfig>,
+        data: web::Json<ForgotPasswordRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Look up the email and mail a reset link in the background
+            - Always respond the same way so this can't be used to find out which emails are registered
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        // done in the background so response time doesn't depend on the email existing
+        let pool = pool.get_ref().clone();
+        let mailer = mailer.into_inner();
+        let config = config.into_inner();
+        let email = data.into_inner().email;
+        rt::spawn(async move {
+            if let Err(e) = PasswordService::send_password_reset(
+                &pool,
+                mailer.as_ref(),
+                &config.mail,
+                &config.tokens,
+                &email,
+            )
+            .await
+            {
+                log::error!("Unable to send password reset email. {}", e);
+            }
+        });
+
+        return ResponseMaker::general_response(
+            &req,
+            &StatusCode::OK,
+            "If the email address is registered, a password reset link has been sent",
+        );
+    }
+
+    pub async fn reset(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        data: web::Json<ResetPasswordRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Check that password and repeat are the same
+            - Use up the reset token, set the new password and rotate the user's authid
+                - new authid logs the user out on all devices
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        if data.password != data.repeat {
+            return ResponseMaker::general_response(
+                &req,
+                &StatusCode::BAD_REQUEST,
+                "Password and Repeat did not match",
+            );
+        }
+
+        match PasswordService::reset_password(&pool, &data.token, &data.password).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(None) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    "Password reset token is invalid or has expired",
+                );
+            }
+            Ok(Some(_)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    "Password has been reset. Please login",
+                );
+            }
+        }
+    }
+
+    pub async fn change(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        jwt_keys: web::Data<JwtKeys>,
+        config: web::Data<AppConfig>,
+        data: web::Json<ChangePasswordRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Check that password and repeat are the same
+            - Get the user from the access token sub
+            - Check the current password
+            - Set the new password and rotate the user's authid
+                - this logs out every other session
+            - Respond with new access and refresh tokens so this session stays logged in
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        if data.password != data.repeat {
+            return ResponseMaker::general_response(
+                &req,
+                &StatusCode::BAD_REQUEST,
+                "Password and Repeat did not match",
+            );
+        }
+
+        // user of the access token - AuthenticatedUser takes care of looking it up
+        let mut user = auth_user.user;
+
+        match is_matched(&data.current_password, &user.password) {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(false) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::FORBIDDEN,
+                    "Current password is incorrect",
+                );
+            }
+            Ok(true) => {}
+        }
+
+        // gives the user a new authid, the new tokens are issued for it
+        if let Err(e) = PasswordService::change_password(&pool, &mut user, &data.password).await {
+            log::error!("{}", e);
+            return ResponseMaker::respond_with_server_error(&req);
+        }
+
+        return match RefreshTokenService::start_session(
+            &pool,
+            &jwt_keys,
+            &user,
+            &ClientInfo::from_request(&req),
+        )
+        .await
+        {
+            Err(e) => {
+                log::error!("{}", e);
+                ResponseMaker::respond_with_server_error(&req)
+            }
+            Ok((access_token, refresh_token)) => ResponseMaker::jwt_response(
+                &req,
+                &StatusCode::OK,
+                &config.cookie,
+                &access_token,
+                &refresh_token,
+            ),
+        };
+    }
+}
@@ -0,0 +1,110 @@
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use actix_web::http::StatusCode;
+use actix_web::web;
+
+use sqlx::MySqlPool;
+
+use crate::config::app_config::AppConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::models::refresh_token_family_models::refresh_token_family_model::RefreshTokenFamilyModel;
+use crate::models::user_models::user_session_model::UserSessionModel;
+use crate::services::session_service::SessionService;
+use crate::utils::jwt_utils::JwtKeys;
+use crate::utils::jwt_utils::decode_refresh_token;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::dtos::session_dto::SessionResponseData;
+
+pub struct Session {}
+
+impl Session {
+    pub async fn list(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        jwt_keys: web::Data<JwtKeys>,
+        config: web::Data<AppConfig>,
+    ) -> impl Responder {
+        /*
+            - Get the user from the access token sub
+            - Get the user's sessions that can still be refreshed
+            - Mark the session of the refresh token cookie, if any, as the current one
+        */
+
+        // user of the access token - AuthenticatedUser takes care of looking it up
+        let user = auth_user.user;
+
+        let sessions = match UserSessionModel::get_active_by_user_id(&pool, user.id).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(s) => s,
+        };
+
+        // family of the refresh token cookie - cookie is only sent to /api/auth paths, unless it has the __Host- prefix
+        let mut current_family_id: Option<i64> = None;
+        if let Some(cookie) = req.cookie(config.cookie.refresh_cookie_name()) {
+            if let Ok(td) = decode_refresh_token(&jwt_keys, cookie.value()) {
+                if let Some(fam) = td.claims.fam {
+                    match RefreshTokenFamilyModel::get_by_value(&pool, &fam).await {
+                        Err(e) => {
+                            log::error!("{}", e);
+                            return ResponseMaker::respond_with_server_error(&req);
+                        }
+                        Ok(None) => {}
+                        Ok(Some(f)) => current_family_id = Some(f.id),
+                    }
+                }
+            }
+        }
+
+        let payload: Vec<SessionResponseData> = sessions
+            .into_iter()
+            .map(|s| SessionResponseData {
+                current: Some(s.family_id) == current_family_id,
+                id: s.value,
+                user_agent: s.user_agent,
+                ip_address: s.ip_address,
+                datetime_created: s.datetime_created,
+                datetime_last_used: s.datetime_last_used,
+            })
+            .collect();
+
+        return ResponseMaker::general_response(&req, &StatusCode::OK, payload);
+    }
+
+    pub async fn revoke(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        path: web::Path<String>,
+    ) -> impl Responder {
+        /*
+            - Get the user from the access token sub
+            - Revoke the refresh token family of the session
+                - the device can't refresh anymore and is logged out once its access token expires
+        */
+
+        // user of the access token - AuthenticatedUser takes care of looking it up
+        let user = auth_user.user;
+
+        match SessionService::revoke(&pool, user.id, &path.into_inner()).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(false) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::NOT_FOUND,
+                    "Session not found",
+                );
+            }
+            Ok(true) => {
+                return ResponseMaker::general_response(&req, &StatusCode::OK, "Session revoked");
+            }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use actix_web::HttpRequest;
+use actix_web::Responder;
+use actix_web::Scope;
+use actix_web::http::StatusCode;
+use actix_web::web;
+
+use sqlx::MySqlPool;
+
+use validator::Validate;
+
+use crate::config::app_config::AppConfig;
+use crate::config::app_config::RateLimitConfig;
+use crate::extractors::auth_extractor::AuthenticatedUser;
+use crate::handlers::avatar_handlers::Avatar;
+use crate::handlers::export_handlers::Export;
+use crate::handlers::handle_handlers::Handle;
+use crate::models::user_models::user_profile_model::UserProfileModel;
+use crate::services::avatar_service::AvatarService;
+use crate::services::mfa_service::MfaService;
+use crate::services::profile_service::ProfileService;
+use crate::utils::response_utils::ResponseMaker;
+
+use crate::middlewares::jwt_auth_middleware::AuthRequired;
+use crate::middlewares::rate_limit_middleware::RateLimit;
+use crate::middlewares::rate_limit_middleware::RateLimitKey;
+
+use crate::dtos::profile_dto::MeResponseData;
+use crate::dtos::profile_dto::UpdateProfileRequestData;
+
+// /api/users
+pub fn scopes(rate_limits: &RateLimitConfig) -> Scope {
+    web::scope("/users")
+        // before /{pid} so "me" is never looked up as a pid
+        .route("/me", web::get().to(Users::me).wrap(AuthRequired {}))
+        .route(
+            "/me",
+            web::patch().to(Users::update_me).wrap(AuthRequired {}),
+        )
+        .route(
+            "/me/handle",
+            web::patch()
+                .to(Handle::change)
+                .wrap(RateLimit::new(
+                    "users_handle",
+                    &rate_limits.users_handle,
+                    RateLimitKey::Sub,
+                ))
+                .wrap(AuthRequired {}),
+        )
+        .route(
+            "/me/avatar",
+            web::put()
+                .to(Avatar::upload)
+                .wrap(RateLimit::new(
+                    "users_avatar",
+                    &rate_limits.users_avatar,
+                    RateLimitKey::Sub,
+                ))
+                .wrap(AuthRequired {}),
+        )
+        .route(
+            "/me/avatar",
+            web::delete().to(Avatar::remove).wrap(AuthRequired {}),
+        )
+        .route(
+            "/me/export",
+            web::post()
+                .to(Export::request)
+                .wrap(RateLimit::new(
+                    "users_export",
+                    &rate_limits.users_export,
+                    RateLimitKey::Sub,
+                ))
+                .wrap(AuthRequired {}),
+        )
+        .route(
+            "/me/export/{id}",
+            web::get().to(Export::status).wrap(AuthRequired {}),
+        )
+        // signed link - works without an access token so it can be opened from the email
+        .route("/me/export/{id}/download", web::get().to(Export::download))
+        .route("/handle/{handle}", web::get().to(Handle::get))
+        .route("/avatars/{id}/{variant}", web::get().to(Avatar::get))
+        .route("/{pid}", web::get().to(Users::get))
+}
+
+pub struct Users {}
+
+impl Users {
+    pub async fn me(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+    ) -> impl Responder {
+        /*
+            - Get the user from the access token sub
+            - Respond with the user's own profile, email included
+        */
+
+        return _me_response(&req, &pool, &config, auth_user.user.id).await;
+    }
+
+    pub async fn update_me(
+        req: HttpRequest,
+        auth_user: AuthenticatedUser,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+        data: web::Json<UpdateProfileRequestData>,
+    ) -> impl Responder {
+        /*
+            - Validate the data
+            - Get the user from the access token sub
+            - Change the names that came with the data
+            - Respond with the user's own profile
+        */
+
+        match data.validate() {
+            Ok(_) => {}
+            Err(e) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                );
+            }
+        }
+
+        let mut user = auth_user.user;
+
+        if data.firstname.is_some() || data.lastname.is_some() {
+            if let Err(e) = ProfileService::update_names(
+                &pool,
+                &mut user,
+                data.firstname.as_deref(),
+                data.lastname.as_deref(),
+            )
+            .await
+            {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+        }
+
+        return _me_response(&req, &pool, &config, user.id).await;
+    }
+
+    pub async fn get(
+        req: HttpRequest,
+        pool: web::Data<MySqlPool>,
+        config: web::Data<AppConfig>,
+        path: web::Path<String>,
+    ) -> impl Responder {
+        /*
+            - Get the user by pid
+                - deactivated and deleted users are hidden
+            - Respond with the public part of the profile only
+        */
+
+        match ProfileService::get_public(&pool, &path.into_inner()).await {
+            Err(e) => {
+                log::error!("{}", e);
+                return ResponseMaker::respond_with_server_error(&req);
+            }
+            Ok(None) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::NOT_FOUND,
+                    "User not found",
+                );
+            }
+            Ok(Some(profile)) => {
+                return ResponseMaker::general_response(
+                    &req,
+                    &StatusCode::OK,
+                    ProfileService::to_public(&config.server, profile),
+                );
+            }
+        }
+    }
+}
+
+// the user's own profile as a response
+async fn _me_response(
+    req: &HttpRequest,
+    pool: &MySqlPool,
+    config: &AppConfig,
+    user_id: i64,
+) -> actix_web::HttpResponse {
+    let profile = match UserProfileModel::get_by_user_id(pool, user_id).await {
+        Err(e) => {
+            log::error!("{}", e);
+            return ResponseMaker::respond_with_server_error(req);
+        }
+        Ok(None) => {
+            log::error!("Unable to get the profile of user {}", user_id);
+            return ResponseMaker::respond_with_server_error(req);
+        }
+        Ok(Some(p)) => p,
+    };
+
+    let mfa_enabled = match MfaService::is_enabled(pool, user_id).await {
+        Err(e) => {
+            log::error!("{}", e);
+            return ResponseMaker::respond_with_server_error(req);
+        }
+        Ok(m) => m,
+    };
+
+    ResponseMaker::general_response(
+        req,
+        &StatusCode::OK,
+        MeResponseData {
+            pid: profile.pid,
+            firstname: profile.firstname,
+            lastname: profile.lastname,
+            handle: profile.handle,
+            avatar: profile
+                .avatar
+                .map(|a| AvatarService::urls(&config.server, &a)),
+            email: profile.email,
+            datetime_created: profile.datetime_created,
+            datetime_confirmed: profile.datetime_confirmed,
+            mfa_enabled,
+        },
+    )
+}
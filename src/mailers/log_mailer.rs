@@ -0,0 +1,53 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Utc;
+
+use crate::mailers::mailer::{MailMessage, Mailer};
+use crate::utils::string_utils::random_alphanumeric;
+
+// Stand-in mailer for local development
+// logs every message and optionally writes it into an outbox directory
+pub struct LogMailer {
+    outbox_dir: Option<PathBuf>,
+}
+
+impl LogMailer {
+    pub fn new(outbox_dir: Option<String>) -> LogMailer {
+        LogMailer {
+            outbox_dir: outbox_dir.map(PathBuf::from),
+        }
+    }
+}
+
+impl Mailer for LogMailer {
+    fn send(&self, message: &MailMessage) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!(
+            "Mail to: {} | Subject: {}\n{}",
+            message.to,
+            message.subject,
+            message.body
+        );
+
+        if let Some(dir) = &self.outbox_dir {
+            fs::create_dir_all(dir)?;
+
+            // timestamp first so the outbox sorts by send time
+            let filename = format!(
+                "{}_{}.txt",
+                Utc::now().format("%Y%m%d%H%M%S%3f"),
+                random_alphanumeric(8)
+            );
+
+            fs::write(
+                dir.join(filename),
+                format!(
+                    "To: {}\nSubject: {}\n\n{}\n",
+                    message.to, message.subject, message.body
+                ),
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use crate::config::app_config::MailConfig;
+use crate::mailers::log_mailer::LogMailer;
+
+#[derive(Debug)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+// Anything that can deliver an email
+// handlers get this as web::Data<dyn Mailer> so the backend can be swapped without touching them
+pub trait Mailer: Send + Sync {
+    fn send(&self, message: &MailMessage) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+// builds the mailer selected by MAILER
+// only "log" is available for now - it is also the default
+pub fn mailer_from_config(
+    config: &MailConfig,
+) -> Result<Arc<dyn Mailer>, Box<dyn std::error::Error>> {
+    match config.mailer.to_lowercase().as_str() {
+        "log" => {
+            // optional directory where every sent mail is also written as a file
+            Ok(Arc::new(LogMailer::new(config.outbox_dir.clone())))
+        }
+        other => Err(format!("Unknown MAILER: {}", other).into()),
+    }
+}
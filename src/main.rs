@@ -13,17 +13,29 @@ use actix_web::web::{self};
 use dotenvy::dotenv;
 use futures_util::FutureExt;
 
-use std::env;
+use std::sync::Arc;
 
+use crate::config::app_config::{AppConfig, CorsConfig};
+use crate::mailers::mailer::Mailer;
+use crate::middlewares::csrf_middleware::CSRF_HEADER;
+use crate::middlewares::rate_limit_middleware::{RateLimit, RateLimitKey};
+use crate::stores::blob_store::BlobStore;
+use crate::stores::login_attempt_store::LoginAttemptStore;
+use crate::utils::jwt_utils::JwtKeys;
 use crate::utils::response_utils::ResponseMaker;
 
+mod config;
 mod constants;
 mod dtos;
+mod extractors;
 mod handlers;
+mod mailers;
 mod middlewares;
 mod models;
 mod repositories;
 mod services;
+mod stores;
+mod tasks;
 mod utils;
 
 #[actix_web::main]
@@ -33,17 +45,17 @@ async fn main() -> std::io::Result<()> {
     // load .env file
     dotenv().ok();
 
-    // get host and port from .env
-    let host = env::var("SERVER_HOST").unwrap_or("0.0.0.0".to_string());
-    let port: u16 = env::var("SERVER_PORT")
-        .unwrap_or("5000".to_string())
-        .parse()
-        .expect("PORT must be a valid u16");
+    // every setting the server needs, a bad one stops it here instead of on the first request
+    let config = match AppConfig::load() {
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+        Ok(c) => c,
+    };
 
     // set and test database
-    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-
-    let dbpool = utils::db_utils::DatabasePool::new(&db_url)
+    let dbpool = utils::db_utils::DatabasePool::new(&config.database)
         .await
         .expect("Failed to create database connection pool");
 
@@ -52,22 +64,79 @@ async fn main() -> std::io::Result<()> {
         Err(e) => log::error!("Error while connecting to database. {}", e),
     };
 
+    // keys for signing and verifying access and refresh tokens
+    let jwt_keys =
+        web::Data::new(JwtKeys::from_config(&config.jwt).expect("Failed to load JWT keys"));
+
+    // mailer used for every outgoing email
+    let mailer: Arc<dyn Mailer> =
+        mailers::mailer::mailer_from_config(&config.mail).expect("Failed to set up mailer");
+
+    // where uploaded files like avatars are kept
+    let blob_store: Arc<dyn BlobStore> =
+        stores::blob_store::blob_store_from_config(&config.blob_store)
+            .expect("Failed to set up blob store");
+
+    // failed login tracking - backoff and lockout
+    let login_attempts: Arc<dyn LoginAttemptStore> =
+        stores::login_attempt_store::login_attempt_store_from_config(
+            &config.login_throttle,
+            &dbpool.pool,
+        )
+        .expect("Failed to set up login attempt store");
+
+    // background cleanup
+    tasks::revoked_token_purge_task::spawn(
+        dbpool.pool.clone(),
+        config.tasks.revoked_token_purge_interval_minutes,
+    );
+    tasks::account_anonymise_task::spawn(
+        dbpool.pool.clone(),
+        blob_store.clone(),
+        config.tasks.account_anonymise_interval_minutes,
+        config.account.deletion_grace_days,
+    );
+    tasks::export_purge_task::spawn(
+        dbpool.pool.clone(),
+        config.export.clone(),
+        config.tasks.export_purge_interval_minutes,
+    );
+    tasks::login_attempt_purge_task::spawn(
+        login_attempts.clone(),
+        config.login_throttle.failure_window_minutes,
+        config.tasks.login_attempt_purge_interval_minutes,
+    );
+
+    if config.cors.allow_any_origin_dev {
+        log::warn!(
+            "CORS_ALLOW_ANY_ORIGIN_DEV is on, any origin is allowed without credentials - local development only"
+        );
+    }
+
+    let bind_address = (config.server.host.clone(), config.server.port);
+    let config = web::Data::new(config);
+
     HttpServer::new(move || {
-        // for testing - change for more secure options in prod
-        let cors = Cors::default()
-            .send_wildcard() // for testing only
-            // .allowed_origin("*")
-            .allow_any_method()
-            .allow_any_header()
-            .supports_credentials();
+        let cors = _cors(&config.cors);
 
         App::new()
             .wrap(Logger::default())
             .wrap(cors)
+            .app_data(config.clone())
             .app_data(web::Data::new(dbpool.pool.clone()))
+            .app_data(jwt_keys.clone())
+            .app_data(web::Data::from(mailer.clone()))
+            .app_data(web::Data::from(blob_store.clone()))
+            .app_data(web::Data::from(login_attempts.clone()))
             .service(
                 // initial scope /api
                 web::scope("/api")
+                    // overall limit per client - routes that are easy to abuse set tighter ones
+                    .wrap(RateLimit::new(
+                        "api",
+                        &config.rate_limit.api,
+                        RateLimitKey::Ip,
+                    ))
                     // simple middleware for changing 405 response into the unified json response
                     // This only check for response status 405, anything else, it will not change the response
                     .wrap_fn(|req, srv| {
@@ -142,40 +211,44 @@ async fn main() -> std::io::Result<()> {
                         InternalError::from_response(err, resp).into()
                     }))
                     // services associated with /api scope
+                    // users scope - /api/users
+                    .service(handlers::users_handlers::scopes(&config.rate_limit))
+                    // invites scope - /api/invites
+                    .service(handlers::invites_handlers::scopes(&config.rate_limit))
                     .service(
-                        handlers::auth_handlers::scopes(), // auth scope - /api/auth
-                                                           // web::scope("/auth")
-                                                           //     // login service - /api/auth/login
-                                                           //     .service(web::resource("/login").route(
-                                                           //         web::post().to(handlers::auth_handlers::Authentication::login),
-                                                           //     ))
-                                                           //     // register service - /api/auth/register
-                                                           //     .service(web::resource("/register").route(
-                                                           //         web::post().to(handlers::auth_handlers::Authentication::register),
-                                                           //     ))
-                                                           //     .service(
-                                                           //         // empty scope, still corresponds to /api/auth
-                                                           //         web::scope("")
-                                                           //             // auth middleware - check that any endpoint after this scope must be authenticated
-                                                           //             // when calling a service
-                                                           //             .wrap(middlewares::jwt_auth_middleware::AuthRequired {})
-                                                           //             // refresh token service - /api/auth/refresh
-                                                           //             .service(
-                                                           //                 web::resource("/refresh").route(
-                                                           //                     web::post().to(
-                                                           //                         handlers::auth_handlers::Authentication::refresh,
-                                                           //                     ),
-                                                           //                 ),
-                                                           //             )
-                                                           //             // logout service - /api/auth/logout
-                                                           //             .service(
-                                                           //                 web::resource("/logout").route(
-                                                           //                     web::post().to(
-                                                           //                         handlers::auth_handlers::Authentication::logout,
-                                                           //                     ),
-                                                           //                 ),
-                                                           //             ),
-                                                           //     ),
+                        handlers::auth_handlers::scopes(&config.rate_limit), // auth scope - /api/auth
+                                                                             // web::scope("/auth")
+                                                                             //     // login service - /api/auth/login
+                                                                             //     .service(web::resource("/login").route(
+                                                                             //         web::post().to(handlers::auth_handlers::Authentication::login),
+                                                                             //     ))
+                                                                             //     // register service - /api/auth/register
+                                                                             //     .service(web::resource("/register").route(
+                                                                             //         web::post().to(handlers::auth_handlers::Authentication::register),
+                                                                             //     ))
+                                                                             //     .service(
+                                                                             //         // empty scope, still corresponds to /api/auth
+                                                                             //         web::scope("")
+                                                                             //             // auth middleware - check that any endpoint after this scope must be authenticated
+                                                                             //             // when calling a service
+                                                                             //             .wrap(middlewares::jwt_auth_middleware::AuthRequired {})
+                                                                             //             // refresh token service - /api/auth/refresh
+                                                                             //             .service(
+                                                                             //                 web::resource("/refresh").route(
+                                                                             //                     web::post().to(
+                                                                             //                         handlers::auth_handlers::Authentication::refresh,
+                                                                             //                     ),
+                                                                             //                 ),
+                                                                             //             )
+                                                                             //             // logout service - /api/auth/logout
+                                                                             //             .service(
+                                                                             //                 web::resource("/logout").route(
+                                                                             //                     web::post().to(
+                                                                             //                         handlers::auth_handlers::Authentication::logout,
+                                                                             //                     ),
+                                                                             //                 ),
+                                                                             //             ),
+                                                                             //     ),
                     ), // services associated with /api scope
                        // .service(
 
@@ -209,6 +282,20 @@ async fn main() -> std::io::Result<()> {
                        //         ),
                        // ),
             )
+            // public keys for verifying access tokens - /.well-known/jwks.json
+            .route(
+                "/.well-known/jwks.json",
+                web::get().to(handlers::jwks_handlers::Jwks::get),
+            )
+            // in-process cache counters - /internal/metrics/cache
+            .configure(|cfg| {
+                if config.server.metrics_enabled {
+                    cfg.route(
+                        "/internal/metrics/cache",
+                        web::get().to(handlers::metrics_handlers::Metrics::cache),
+                    );
+                }
+            })
             // default service - not existent endpoints
             .default_service(web::route().to(|req: HttpRequest| async move {
                 ResponseMaker::general_response(
@@ -223,7 +310,27 @@ async fn main() -> std::io::Result<()> {
                 // )
             }))
     })
-    .bind((host, port))?
+    .bind(bind_address)?
     .run()
     .await
 }
+
+fn _cors(config: &CorsConfig) -> Cors {
+    let cors = Cors::default()
+        .allowed_methods(config.allowed_methods.iter().map(|m| m.as_str()))
+        .allowed_headers(config.allowed_headers.iter().map(|h| h.as_str()))
+        .max_age(config.max_age_seconds)
+        .expose_headers([CSRF_HEADER]);
+
+    // local development only - never with credentials, so no other site can use the refresh cookie
+    if config.allow_any_origin_dev {
+        return cors.allow_any_origin();
+    }
+
+    config
+        .allowed_origins
+        .iter()
+        .fold(cors.supports_credentials(), |cors, origin| {
+            cors.allowed_origin(origin)
+        })
+}
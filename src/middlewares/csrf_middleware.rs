@@ -0,0 +1,159 @@
+use std::future::{Ready, ready};
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_web::body::BoxBody;
+use actix_web::http::StatusCode;
+use actix_web::web;
+use actix_web::{
+    Error,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+};
+
+use futures_util::future::LocalBoxFuture;
+
+use crate::config::app_config::AppConfig;
+use crate::utils::hash_utils::constant_time_eq;
+use crate::utils::header_utils::RequestHeader;
+use crate::utils::response_utils::{ErrorCode, ResponseMaker};
+
+// header the client echoes the csrf token back in
+// the token itself comes with every jwt_response, as a cookie and in this header
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+// For routes that act on the refresh token cookie the browser attaches by itself
+// e.g. .wrap(CsrfProtected {})
+//
+// State changing requests are refused when:
+//  - Origin (or Referer, if there is no Origin) is not one of CORS_ALLOWED_ORIGINS
+//  - the refresh cookie is sent without X-CSRF-Token matching the csrf token cookie (double submit)
+// Safe methods and requests without the refresh cookie are let through, the cookie is what's being protected
+pub struct CsrfProtected {}
+
+impl<S> Transform<S, ServiceRequest> for CsrfProtected
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CsrfProtectedMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CsrfProtectedMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct CsrfProtectedMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S> Service<ServiceRequest> for CsrfProtectedMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, serv_req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        if serv_req.method().is_safe() {
+            return Box::pin(async move { service.call(serv_req).await });
+        }
+
+        let config = match serv_req.app_data::<web::Data<AppConfig>>() {
+            Some(c) => c.clone(),
+            None => {
+                log::error!("App config is missing from app data");
+                let resp = ResponseMaker::respond_with_server_error(&serv_req.request());
+
+                return Box::pin(
+                    async move { Ok(serv_req.into_response(resp.map_into_boxed_body())) },
+                );
+            }
+        };
+
+        if !_is_allowed_origin(&serv_req, &config.cors.allowed_origins) {
+            let resp = ResponseMaker::error_response(
+                &serv_req.request(),
+                &StatusCode::FORBIDDEN,
+                ErrorCode::OriginNotAllowed,
+                "Request origin is not allowed",
+            );
+
+            return Box::pin(async move { Ok(serv_req.into_response(resp.map_into_boxed_body())) });
+        }
+
+        if serv_req
+            .cookie(config.cookie.refresh_cookie_name())
+            .is_some()
+            && !_has_valid_csrf_token(&serv_req, config.cookie.csrf_cookie_name())
+        {
+            let resp = ResponseMaker::error_response(
+                &serv_req.request(),
+                &StatusCode::FORBIDDEN,
+                ErrorCode::CsrfTokenInvalid,
+                "CSRF token is missing or invalid",
+            );
+
+            return Box::pin(async move { Ok(serv_req.into_response(resp.map_into_boxed_body())) });
+        }
+
+        Box::pin(async move { service.call(serv_req).await })
+    }
+}
+
+// no allow-list (only with CORS_ALLOW_ANY_ORIGIN_DEV) or neither header (not a browser) lets the request through
+fn _is_allowed_origin(req: &ServiceRequest, allowed_origins: &[String]) -> bool {
+    if allowed_origins.is_empty() {
+        return true;
+    }
+
+    let origin = match req.get_header_value("origin") {
+        Ok(Some(o)) => Some(o),
+        _ => match req.get_header_value("referer") {
+            Ok(Some(r)) => Some(_origin_of(&r)),
+            _ => None,
+        },
+    };
+
+    match origin {
+        None => true,
+        Some(o) => allowed_origins
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(&o)),
+    }
+}
+
+// scheme://host[:port] of a url
+fn _origin_of(url: &str) -> String {
+    match url.find("://") {
+        None => url.to_string(),
+        Some(i) => {
+            let rest = &url[i + 3..];
+            let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+            url[..i + 3 + end].to_string()
+        }
+    }
+}
+
+fn _has_valid_csrf_token(req: &ServiceRequest, cookie_name: &str) -> bool {
+    let cookie = match req.cookie(cookie_name) {
+        Some(c) => c,
+        None => return false,
+    };
+
+    match req.get_header_value(CSRF_HEADER) {
+        Ok(Some(header)) => constant_time_eq(header.as_bytes(), cookie.value().as_bytes()),
+        _ => false,
+    }
+}
@@ -5,16 +5,31 @@ use std::task::{Context, Poll};
 use actix_web::HttpMessage; // for extension_mut()
 use actix_web::body::BoxBody;
 use actix_web::http::StatusCode;
+use actix_web::web;
 use actix_web::{
     Error,
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
 };
 
 use futures_util::future::LocalBoxFuture;
+use sqlx::MySqlPool;
 
+use crate::services::auth_service::AuthService;
 use crate::utils::header_utils::RequestHeader;
-use crate::utils::jwt_utils::{decode_access_token, decode_access_token_no_validation_exp};
-use crate::utils::response_utils::ResponseMaker;
+use crate::utils::jwt_utils::{
+    JwtError, JwtKeys, decode_access_token, decode_access_token_no_validation_exp,
+};
+use crate::utils::response_utils::{ErrorCode, ResponseMaker};
+
+// sub (authid) of the access token, put into the request extensions by AuthRequired
+// handlers get the user through the AuthenticatedUser extractor instead of reading it directly
+#[derive(Clone)]
+pub struct AuthSub(pub String);
+
+// id of the user the sub resolved to, put there along with AuthSub
+// so AuthenticatedUser loads the user by id instead of resolving the authid a second time
+#[derive(Clone)]
+pub struct AuthUserId(pub i64);
 
 pub struct AuthRequired {}
 
@@ -67,9 +82,10 @@ where
                 );
             }
             Ok(None) => {
-                let resp = ResponseMaker::general_response(
+                let resp = ResponseMaker::error_response(
                     &serv_req.request(),
                     &StatusCode::UNAUTHORIZED,
+                    ErrorCode::TokenMissing,
                     "Access token is required",
                 );
 
@@ -81,9 +97,10 @@ where
                 if let Some(at) = auth_header_value.strip_prefix("Bearer ") {
                     at.to_string()
                 } else {
-                    let resp = ResponseMaker::general_response(
+                    let resp = ResponseMaker::error_response(
                         &serv_req.request(),
                         &StatusCode::UNAUTHORIZED,
+                        ErrorCode::TokenMalformed,
                         "Invalid authorization format. Expected 'Bearer <token>'",
                     );
 
@@ -94,57 +111,53 @@ where
             }
         };
 
+        let jwt_keys = match serv_req.app_data::<web::Data<JwtKeys>>() {
+            Some(k) => k.clone(),
+            None => {
+                log::error!("JWT keys are missing from app data");
+                let resp = ResponseMaker::respond_with_server_error(&serv_req.request());
+
+                return Box::pin(
+                    async move { Ok(serv_req.into_response(resp.map_into_boxed_body())) },
+                );
+            }
+        };
+
         // validate token
-        let token_data = match decode_access_token(&access_token) {
-            Err(e) => {
-                // check if path is refresh endpoint
-                if serv_req.path().eq_ignore_ascii_case("/api/auth/refresh")
-                    && e.to_string().eq_ignore_ascii_case("expiredsignature")
-                {
-                    match decode_access_token_no_validation_exp(&access_token) {
-                        Err(er) => {
-                            log::error!("{}", er);
-                            let resp =
-                                ResponseMaker::respond_with_server_error(&serv_req.request());
-
-                            return Box::pin(async move {
-                                Ok(serv_req.into_response(resp.map_into_boxed_body()))
-                            });
-                        }
-                        Ok(td) => {
-                            return _let_through(service, serv_req, &td.claims.sub);
-                        }
+        let token_data = match decode_access_token(&jwt_keys, &access_token) {
+            // an expired token may still be refreshed
+            Err(JwtError::Expired) if serv_req.path().eq_ignore_ascii_case("/api/auth/refresh") => {
+                match decode_access_token_no_validation_exp(&jwt_keys, &access_token) {
+                    Err(er) => {
+                        log::error!("{}", er);
+                        let resp = ResponseMaker::respond_with_server_error(&serv_req.request());
+
+                        return Box::pin(async move {
+                            Ok(serv_req.into_response(resp.map_into_boxed_body()))
+                        });
+                    }
+                    Ok(td) => {
+                        return _let_through(service, serv_req, &td.claims.sub);
                     }
-                } else if e.to_string().eq_ignore_ascii_case("expiredsignature")
-                    || e.to_string().eq_ignore_ascii_case("invalidsignature")
-                    || e.to_string().eq_ignore_ascii_case("invalidtoken")
-                    || e.to_string().starts_with("Base64")
-                {
-                    let msg = format!("Access token {}", e);
-
-                    let resp = ResponseMaker::general_response(
+                }
+            }
+            Err(e) => {
+                let resp = match e.error_code() {
+                    Some(error_code) => ResponseMaker::error_response(
                         &serv_req.request(),
                         &StatusCode::UNAUTHORIZED,
-                        &msg,
-                    );
-
-                    // let resp = JsonGeneralResponse::make_response(
-                    //     &serv_req.request(),
-                    //     &StatusCode::UNAUTHORIZED,
-                    //     &msg,
-                    // );
-                    return Box::pin(async move {
-                        Ok(serv_req.into_response(resp.map_into_boxed_body()))
-                    });
-                } else {
-                    log::error!("{}", e);
-
-                    let resp = ResponseMaker::respond_with_server_error(&serv_req.request());
+                        error_code,
+                        format!("Access token {}", e),
+                    ),
+                    None => {
+                        log::error!("{}", e);
+                        ResponseMaker::respond_with_server_error(&serv_req.request())
+                    }
+                };
 
-                    return Box::pin(async move {
-                        Ok(serv_req.into_response(resp.map_into_boxed_body()))
-                    });
-                }
+                return Box::pin(
+                    async move { Ok(serv_req.into_response(resp.map_into_boxed_body())) },
+                );
             }
             Ok(token_data) => token_data,
         };
@@ -169,8 +182,40 @@ fn _let_through<S>(
 where
     S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
 {
-    req.extensions_mut().insert(sub.to_string());
+    let pool = match req.app_data::<web::Data<MySqlPool>>() {
+        Some(p) => p.clone(),
+        None => {
+            log::error!("Database pool is missing from app data");
+            let resp = ResponseMaker::respond_with_server_error(&req.request());
+            return Box::pin(async move { Ok(req.into_response(resp.map_into_boxed_body())) });
+        }
+    };
+
+    let sub = sub.to_string();
     Box::pin(async move {
+        // sub must still be the current authid of a user
+        // it won't be once the user's authid has been rotated (e.g. logout on all devices)
+        match AuthService::user_id_by_authid(&pool, &sub).await {
+            Err(e) => {
+                log::error!("{}", e);
+                let resp = ResponseMaker::respond_with_server_error(&req.request());
+                return Ok(req.into_response(resp.map_into_boxed_body()));
+            }
+            Ok(None) => {
+                let resp = ResponseMaker::error_response(
+                    &req.request(),
+                    &StatusCode::UNAUTHORIZED,
+                    ErrorCode::TokenRevoked,
+                    "Access token is no longer valid",
+                );
+                return Ok(req.into_response(resp.map_into_boxed_body()));
+            }
+            Ok(Some(user_id)) => {
+                req.extensions_mut().insert(AuthUserId(user_id));
+            }
+        }
+
+        req.extensions_mut().insert(AuthSub(sub));
         let res = service.call(req).await?;
         Ok(res)
     })
@@ -1 +1,3 @@
+pub mod csrf_middleware;
 pub mod jwt_auth_middleware;
+pub mod rate_limit_middleware;
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::future::{Ready, ready};
+use std::rc::Rc;
+use std::sync::{LazyLock, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::HttpMessage; // for extensions()
+use actix_web::body::BoxBody;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{
+    Error,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+};
+
+use futures_util::future::LocalBoxFuture;
+
+use crate::config::app_config::RateLimitRule;
+use crate::middlewares::jwt_auth_middleware::AuthSub;
+use crate::utils::client_utils::client_ip;
+use crate::utils::response_utils::ResponseMaker;
+
+// buckets are pruned once the map grows past this
+const PRUNE_THRESHOLD: usize = 10_000;
+
+// Buckets of every limiter, shared by all workers
+// HttpServer builds the app (and so every RateLimit) once per worker,
+// keeping the buckets here means a limit holds for the whole process instead of per worker
+static BUCKETS: LazyLock<Mutex<HashMap<String, TokenBucket>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// What requests are counted together
+#[derive(Debug, Clone, Copy)]
+pub enum RateLimitKey {
+    Ip,    // client ip address
+    Sub,   // access token sub - must be wrapped inside AuthRequired, falls back to ip
+    Route, // every client shares the limit of the route
+}
+
+// Token bucket limit for a scope or route
+// capacity requests are allowed at once and tokens come back at capacity per period_seconds
+// e.g. .wrap(RateLimit::new("auth_register", &rate_limits.auth_register, RateLimitKey::Ip))
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    pub name: &'static str, // keeps buckets of different limits apart
+    pub capacity: u32,
+    pub period_seconds: u64,
+    pub key: RateLimitKey,
+}
+
+impl RateLimit {
+    pub fn new(name: &'static str, rule: &RateLimitRule, key: RateLimitKey) -> RateLimit {
+        RateLimit {
+            name,
+            capacity: rule.capacity.max(1),
+            period_seconds: rule.period_seconds.max(1),
+            key,
+        }
+    }
+
+    fn refill_per_second(&self) -> f64 {
+        self.capacity as f64 / self.period_seconds as f64
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limit: self.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limit: RateLimit,
+}
+
+impl<S> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error> + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, serv_req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let limit = self.limit.clone();
+
+        let bucket_key = format!("{}:{}", limit.name, _client_key(&serv_req, &limit.key));
+
+        let decision = match _take(&limit, &bucket_key) {
+            Err(e) => {
+                log::error!("{}", e);
+                let resp = ResponseMaker::respond_with_server_error(&serv_req.request());
+                return Box::pin(
+                    async move { Ok(serv_req.into_response(resp.map_into_boxed_body())) },
+                );
+            }
+            Ok(d) => d,
+        };
+
+        if !decision.allowed {
+            let mut resp = ResponseMaker::too_many_requests_response(
+                &serv_req.request(),
+                decision.retry_after_seconds as i64,
+                "Too many requests. Please try again later",
+            );
+            _insert_headers(resp.headers_mut(), &limit, &decision);
+
+            return Box::pin(async move { Ok(serv_req.into_response(resp.map_into_boxed_body())) });
+        }
+
+        Box::pin(async move {
+            let mut res = service.call(serv_req).await?;
+            _insert_headers(res.headers_mut(), &limit, &decision);
+            Ok(res)
+        })
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+    capacity: f64,
+    refill_per_second: f64,
+}
+
+struct RateLimitDecision {
+    allowed: bool,
+    remaining: u32,
+    reset_seconds: u64,       // until the bucket is full again
+    retry_after_seconds: u64, // until the next request is allowed
+}
+
+fn _client_key(req: &ServiceRequest, key: &RateLimitKey) -> String {
+    // forwarding headers only count behind a trusted proxy, otherwise every forged value would get a bucket
+    let ip = || match client_ip(req.request()) {
+        Some(ip) => ip.to_string(),
+        None => "unknown".to_string(),
+    };
+
+    match key {
+        RateLimitKey::Ip => format!("ip:{}", ip()),
+        RateLimitKey::Sub => match req.extensions().get::<AuthSub>() {
+            Some(sub) => format!("sub:{}", sub.0),
+            None => format!("ip:{}", ip()),
+        },
+        RateLimitKey::Route => format!(
+            "route:{}",
+            req.match_pattern().unwrap_or(req.path().to_string())
+        ),
+    }
+}
+
+// take a token from the bucket of the key, if there is one
+fn _take(limit: &RateLimit, bucket_key: &str) -> Result<RateLimitDecision, String> {
+    let mut buckets = BUCKETS.lock().map_err(|e| e.to_string())?;
+    let now = Instant::now();
+    let capacity = limit.capacity as f64;
+    let refill_per_second = limit.refill_per_second();
+
+    if buckets.len() > PRUNE_THRESHOLD {
+        // buckets that have refilled completely hold no information
+        buckets.retain(|_, b| {
+            b.tokens + now.duration_since(b.last_refill).as_secs_f64() * b.refill_per_second
+                < b.capacity
+        });
+    }
+
+    let bucket = buckets
+        .entry(bucket_key.to_string())
+        .or_insert(TokenBucket {
+            tokens: capacity,
+            last_refill: now,
+            capacity,
+            refill_per_second,
+        });
+
+    let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * refill_per_second).min(capacity);
+    bucket.last_refill = now;
+
+    let allowed = bucket.tokens >= 1.0;
+    if allowed {
+        bucket.tokens -= 1.0;
+    }
+
+    let retry_after_seconds = if allowed {
+        0
+    } else {
+        ((1.0 - bucket.tokens) / refill_per_second).ceil() as u64
+    };
+
+    Ok(RateLimitDecision {
+        allowed,
+        remaining: bucket.tokens.floor() as u32,
+        reset_seconds: ((capacity - bucket.tokens) / refill_per_second).ceil() as u64,
+        retry_after_seconds,
+    })
+}
+
+// RateLimit-Limit, RateLimit-Remaining and RateLimit-Reset (IETF draft)
+// with nested limits the inner one sets them first, an outer one only replaces them if it's tighter
+fn _insert_headers(
+    headers: &mut actix_web::http::header::HeaderMap,
+    limit: &RateLimit,
+    decision: &RateLimitDecision,
+) {
+    let current_remaining = headers
+        .get("ratelimit-remaining")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u32>().ok());
+
+    if current_remaining.is_some_and(|r| r <= decision.remaining) {
+        return;
+    }
+
+    let values = [
+        ("ratelimit-limit", limit.capacity as u64),
+        ("ratelimit-remaining", decision.remaining as u64),
+        ("ratelimit-reset", decision.reset_seconds),
+    ];
+
+    for (name, value) in values {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::http::header::HeaderMap;
+
+    use super::*;
+
+    // buckets are shared by the whole process, every test uses its own limit name
+    fn _limit(name: &'static str, capacity: u32, period_seconds: u64) -> RateLimit {
+        RateLimit::new(
+            name,
+            &RateLimitRule {
+                capacity,
+                period_seconds,
+            },
+            RateLimitKey::Ip,
+        )
+    }
+
+    #[test]
+    fn take_allows_capacity_then_refuses() {
+        let limit = _limit("test_take_capacity", 3, 60);
+
+        for remaining in [2, 1, 0] {
+            let decision = _take(&limit, "test_take_capacity:ip:a").unwrap();
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+            assert_eq!(decision.retry_after_seconds, 0);
+        }
+
+        // a token comes back every 20 seconds
+        let decision = _take(&limit, "test_take_capacity:ip:a").unwrap();
+        assert!(!decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.retry_after_seconds, 20);
+        assert_eq!(decision.reset_seconds, 60);
+    }
+
+    #[test]
+    fn take_refills_over_time() {
+        let limit = _limit("test_take_refill", 2, 60);
+        let key = "test_take_refill:ip:a";
+
+        assert!(_take(&limit, key).unwrap().allowed);
+        assert!(_take(&limit, key).unwrap().allowed);
+        assert!(!_take(&limit, key).unwrap().allowed);
+
+        // half the period gives back one of the two tokens
+        {
+            let mut buckets = BUCKETS.lock().unwrap();
+            let bucket = buckets.get_mut(key).unwrap();
+            bucket.last_refill -= Duration::from_secs(30);
+        }
+
+        let decision = _take(&limit, key).unwrap();
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert!(!_take(&limit, key).unwrap().allowed);
+    }
+
+    #[test]
+    fn take_keeps_clients_apart() {
+        let limit = _limit("test_take_clients", 1, 60);
+
+        assert!(_take(&limit, "test_take_clients:ip:a").unwrap().allowed);
+        assert!(!_take(&limit, "test_take_clients:ip:a").unwrap().allowed);
+        assert!(_take(&limit, "test_take_clients:ip:b").unwrap().allowed);
+    }
+
+    #[test]
+    fn insert_headers_keeps_tightest_limit() {
+        let inner = _limit("test_headers_inner", 5, 60);
+        let outer = _limit("test_headers_outer", 300, 60);
+        let mut headers = HeaderMap::new();
+
+        _insert_headers(
+            &mut headers,
+            &inner,
+            &RateLimitDecision {
+                allowed: true,
+                remaining: 4,
+                reset_seconds: 12,
+                retry_after_seconds: 0,
+            },
+        );
+
+        // the outer limit has more left, the inner one is what the client runs into first
+        _insert_headers(
+            &mut headers,
+            &outer,
+            &RateLimitDecision {
+                allowed: true,
+                remaining: 299,
+                reset_seconds: 1,
+                retry_after_seconds: 0,
+            },
+        );
+        assert_eq!(headers.get("ratelimit-limit").unwrap(), "5");
+        assert_eq!(headers.get("ratelimit-remaining").unwrap(), "4");
+        assert_eq!(headers.get("ratelimit-reset").unwrap(), "12");
+
+        _insert_headers(
+            &mut headers,
+            &outer,
+            &RateLimitDecision {
+                allowed: true,
+                remaining: 2,
+                reset_seconds: 60,
+                retry_after_seconds: 0,
+            },
+        );
+        assert_eq!(headers.get("ratelimit-limit").unwrap(), "300");
+        assert_eq!(headers.get("ratelimit-remaining").unwrap(), "2");
+        assert_eq!(headers.get("ratelimit-reset").unwrap(), "60");
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool};
+
+// Failed logins for a key (hashed email or ip address)
+#[derive(Serialize, Debug, FromRow)]
+pub struct LoginAttemptModel {
+    pub id: i64,
+    pub value: String, // sha256 hex digest of the key
+    pub failures: i32,
+    pub datetime_last_failure: NaiveDateTime,
+    pub datetime_blocked_until: Option<NaiveDateTime>,
+    pub datetime_created: NaiveDateTime,
+}
+
+impl LoginAttemptModel {
+    // count a failed login for the key, creating its row if needed
+    // failures start again from 1 if the last failure is older than window_start
+    // returns LoginAttemptModel instance with the updated values
+    pub async fn record_failure(
+        pool: &Pool<MySql>,
+        value: &str,
+        datetime_now: &NaiveDateTime,
+        window_start: &NaiveDateTime,
+    ) -> Result<LoginAttemptModel, sqlx::error::Error> {
+        // failures is assigned before datetime_last_failure so the IF sees the old timestamp
+        sqlx::query!(
+            r#"
+            INSERT INTO login_attempt (value, failures, datetime_last_failure)
+            VALUES (?, 1, ?)
+            ON DUPLICATE KEY UPDATE
+                failures = IF(datetime_last_failure < ?, 1, failures + 1),
+                datetime_last_failure = VALUES(datetime_last_failure)
+            "#,
+            value,
+            datetime_now,
+            window_start
+        )
+        .execute(pool)
+        .await?;
+
+        let row = sqlx::query_as!(
+            LoginAttemptModel,
+            r#"
+            SELECT id, value, failures, datetime_last_failure, datetime_blocked_until, datetime_created
+            FROM login_attempt
+            WHERE value = ?
+            "#,
+            value
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get single login_attempt row by value (hashed)
+    pub async fn get_by_value(
+        pool: &Pool<MySql>,
+        value: &str,
+    ) -> Result<Option<LoginAttemptModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            LoginAttemptModel,
+            r#"
+            SELECT id, value, failures, datetime_last_failure, datetime_blocked_until, datetime_created
+            FROM login_attempt
+            WHERE value = ?
+            "#,
+            value
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // stop logins for the key until the given time
+    // never shortens a block that is already longer
+    pub async fn update_datetime_blocked_until(
+        pool: &Pool<MySql>,
+        value: &str,
+        datetime_blocked_until: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE login_attempt
+            SET datetime_blocked_until = ?
+            WHERE value = ? AND (datetime_blocked_until IS NULL OR datetime_blocked_until < ?)
+            "#,
+            datetime_blocked_until,
+            value,
+            datetime_blocked_until
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // forget the failures of a key - after a successful login
+    pub async fn delete_by_value(
+        pool: &Pool<MySql>,
+        value: &str,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM login_attempt WHERE value = ?
+            "#,
+            value
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // delete up to limit rows whose failures are forgotten and whose block has ended
+    // returns how many were deleted so callers can keep going until nothing is left
+    pub async fn delete_expired(
+        pool: &Pool<MySql>,
+        datetime_now: &NaiveDateTime,
+        window_start: &NaiveDateTime,
+        limit: u32,
+    ) -> Result<u64, sqlx::error::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM login_attempt
+            WHERE datetime_last_failure < ?
+                AND (datetime_blocked_until IS NULL OR datetime_blocked_until < ?)
+            LIMIT ?
+            "#,
+            window_start,
+            datetime_now,
+            limit
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -13,5 +13,7 @@
 // pub mod user_model;
 // pub mod user_pid_model;
 
+pub mod login_attempt_models;
+pub mod refresh_token_family_models;
 pub mod revoked_token_models;
 pub mod user_models;
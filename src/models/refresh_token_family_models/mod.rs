@@ -0,0 +1 @@
+pub mod refresh_token_family_model;
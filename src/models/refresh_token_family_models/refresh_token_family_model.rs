@@ -0,0 +1,168 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::MySql;
+use sqlx::Pool;
+use sqlx::Transaction;
+use sqlx::prelude::FromRow;
+
+// Every refresh token issued from one login belongs to the same family
+// only the token carrying current_jti may be used, any other one is a reuse
+#[derive(Debug, Serialize, FromRow)]
+pub struct RefreshTokenFamilyModel {
+    pub id: i64,
+    pub value: String, // fam claim of the refresh tokens
+    pub user_id: i64,
+    pub current_jti: String,
+    pub datetime_revoked: Option<NaiveDateTime>,
+    pub datetime_created: NaiveDateTime,
+}
+
+impl RefreshTokenFamilyModel {
+    // insert new row into refresh_token_family table
+    // returns RefreshTokenFamilyModel instance with the newly inserted values
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        value: &str,
+        user_id: i64,
+        current_jti: &str,
+    ) -> Result<RefreshTokenFamilyModel, sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_token_family (value, user_id, current_jti)
+            VALUES (?, ?, ?)
+            "#,
+            value,
+            user_id,
+            current_jti
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        let row = sqlx::query_as!(
+            RefreshTokenFamilyModel,
+            r#"
+            SELECT id, value, user_id, current_jti, datetime_revoked, datetime_created
+            FROM refresh_token_family
+            WHERE id = LAST_INSERT_ID()
+            "#
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get single refresh_token_family row by value
+    pub async fn get_by_value(
+        pool: &Pool<MySql>,
+        value: &str,
+    ) -> Result<Option<RefreshTokenFamilyModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            RefreshTokenFamilyModel,
+            r#"
+            SELECT id, value, user_id, current_jti, datetime_revoked, datetime_created
+            FROM refresh_token_family
+            WHERE value = ?
+            "#,
+            value
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get single refresh_token_family row by id
+    pub async fn get_by_id(
+        pool: &Pool<MySql>,
+        id: i64,
+    ) -> Result<Option<RefreshTokenFamilyModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            RefreshTokenFamilyModel,
+            r#"
+            SELECT id, value, user_id, current_jti, datetime_revoked, datetime_created
+            FROM refresh_token_family
+            WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // move the family to a new jti
+    // only succeeds if nobody else rotated or revoked the family since it was read
+    // returns true if this call did the rotation
+    pub async fn update_current_jti(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        new_jti: &str,
+    ) -> Result<bool, sqlx::error::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE refresh_token_family
+            SET current_jti = ?
+            WHERE id = ? AND current_jti = ? AND datetime_revoked IS NULL
+            "#,
+            new_jti,
+            self.id,
+            self.current_jti
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        self.current_jti = new_jti.to_string();
+
+        Ok(true)
+    }
+
+    // revoke this family - no refresh token of it will work anymore
+    pub async fn revoke(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        datetime_revoked: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE refresh_token_family
+            SET datetime_revoked = ?
+            WHERE id = ? AND datetime_revoked IS NULL
+            "#,
+            datetime_revoked,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.datetime_revoked = Some(*datetime_revoked);
+
+        Ok(())
+    }
+
+    // revoke every family of a user that is still active
+    pub async fn revoke_by_user_id(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+        datetime_revoked: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE refresh_token_family
+            SET datetime_revoked = ?
+            WHERE user_id = ? AND datetime_revoked IS NULL
+            "#,
+            datetime_revoked,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
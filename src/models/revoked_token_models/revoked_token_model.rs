@@ -8,7 +8,7 @@ use sqlx::prelude::FromRow;
 #[derive(Debug, Serialize, FromRow)]
 pub struct RevokedTokenModel {
     pub id: i64,
-    pub value: String,
+    pub value: String, // sha256 hex digest of the token
     pub datetime_ttl: NaiveDateTime,
     pub datetime_created: NaiveDateTime,
 }
@@ -64,4 +64,26 @@ impl RevokedTokenModel {
 
         Ok(row)
     }
+
+    // delete up to limit rows whose ttl has passed
+    // returns how many were deleted so callers can keep going until nothing is left
+    pub async fn delete_expired(
+        pool: &Pool<MySql>,
+        datetime_now: &NaiveDateTime,
+        limit: u32,
+    ) -> Result<u64, sqlx::error::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM revoked_token
+            WHERE datetime_ttl < ?
+            LIMIT ?
+            "#,
+            datetime_now,
+            limit
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
 }
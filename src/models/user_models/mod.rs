@@ -1,5 +1,16 @@
-pub mod user_name_model;
-pub mod user_email_model;
 pub mod user_authid_model;
+pub mod user_avatar_model;
+pub mod user_email_model;
+pub mod user_export_model;
+pub mod user_handle_history_model;
+pub mod user_handle_model;
+pub mod user_invite_model;
+pub mod user_invite_use_model;
+pub mod user_mfa_model;
+pub mod user_mfa_recovery_code_model;
+pub mod user_model;
+pub mod user_name_model;
 pub mod user_pid_model;
-pub mod user_model;
\ No newline at end of file
+pub mod user_profile_model;
+pub mod user_session_model;
+pub mod user_token_model;
@@ -0,0 +1,105 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool, Transaction};
+
+// The current avatar of a user - a user has at most one
+// the images themselves are in the blob store, under the value
+// a new upload gets a new value, so the urls of an avatar never change what they show
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserAvatarModel {
+    pub id: i64,
+    pub user_id: i64,
+    pub value: String, // public id of the avatar
+    pub datetime_created: NaiveDateTime,
+}
+
+impl UserAvatarModel {
+    // insert new row into user_avatar table
+    // returns UserAvatarModel instance with the newly inserted values
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+        value: &str,
+    ) -> Result<UserAvatarModel, sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_avatar (user_id, value)
+            VALUES (?, ?)
+            "#,
+            user_id,
+            value
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        let row = sqlx::query_as!(
+            UserAvatarModel,
+            r#"
+            SELECT id, user_id, value, datetime_created
+            FROM user_avatar
+            WHERE id = LAST_INSERT_ID()
+            "#
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get single user_avatar row by value
+    pub async fn get_by_value(
+        pool: &Pool<MySql>,
+        value: &str,
+    ) -> Result<Option<UserAvatarModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserAvatarModel,
+            r#"
+            SELECT id, user_id, value, datetime_created
+            FROM user_avatar
+            WHERE value = ?
+            "#,
+            value
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get the avatar of a user, if it has one
+    pub async fn get_by_user_id(
+        pool: &Pool<MySql>,
+        user_id: i64,
+    ) -> Result<Option<UserAvatarModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserAvatarModel,
+            r#"
+            SELECT id, user_id, value, datetime_created
+            FROM user_avatar
+            WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // remove the avatar of a user - its images are the caller's to delete
+    pub async fn delete_by_user_id(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM user_avatar WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -79,4 +79,39 @@ impl UserEmailModel {
 
         Ok(row)
     }
+
+    // replace every email of a user with a placeholder that can't receive mail
+    // that is the current email, and emails the user asked to change to unless another user has it now
+    pub async fn anonymise_by_user_id(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_email e
+            INNER JOIN user u ON u.email_id = e.id
+            SET e.value = CONCAT('deleted-', e.id, '@deleted.invalid')
+            WHERE u.id = ?
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE user_email e
+            INNER JOIN user_token t ON t.email_id = e.id
+            SET e.value = CONCAT('deleted-', e.id, '@deleted.invalid')
+            WHERE t.user_id = ?
+                AND NOT EXISTS (SELECT 1 FROM user u WHERE u.email_id = e.id AND u.id <> ?)
+            "#,
+            user_id,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
 }
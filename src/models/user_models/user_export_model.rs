@@ -0,0 +1,175 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool, Transaction};
+
+// An archive of a user's data, built in the background
+// the file itself lives in EXPORT_DIR as <value>.zip until datetime_ttl
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserExportModel {
+    pub id: i64,
+    pub value: String, // public id of the export
+    pub user_id: i64,
+    pub datetime_ready: Option<NaiveDateTime>,
+    pub datetime_failed: Option<NaiveDateTime>,
+    pub datetime_ttl: NaiveDateTime,
+    pub datetime_created: NaiveDateTime,
+}
+
+impl UserExportModel {
+    // insert new row into user_export table
+    // returns UserExportModel instance with the newly inserted values
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        value: &str,
+        user_id: i64,
+        datetime_ttl: &NaiveDateTime,
+    ) -> Result<UserExportModel, sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_export (value, user_id, datetime_ttl)
+            VALUES (?, ?, ?)
+            "#,
+            value,
+            user_id,
+            datetime_ttl
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        let row = sqlx::query_as!(
+            UserExportModel,
+            r#"
+            SELECT id, value, user_id, datetime_ready, datetime_failed, datetime_ttl, datetime_created
+            FROM user_export
+            WHERE id = LAST_INSERT_ID()
+            "#
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get single user_export row by value
+    pub async fn get_by_value(
+        pool: &Pool<MySql>,
+        value: &str,
+    ) -> Result<Option<UserExportModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserExportModel,
+            r#"
+            SELECT id, value, user_id, datetime_ready, datetime_failed, datetime_ttl, datetime_created
+            FROM user_export
+            WHERE value = ?
+            "#,
+            value
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // export of a user that is still being built, if any
+    pub async fn get_in_progress_by_user_id(
+        pool: &Pool<MySql>,
+        user_id: i64,
+        datetime_now: &NaiveDateTime,
+    ) -> Result<Option<UserExportModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserExportModel,
+            r#"
+            SELECT id, value, user_id, datetime_ready, datetime_failed, datetime_ttl, datetime_created
+            FROM user_export
+            WHERE user_id = ? AND datetime_ready IS NULL AND datetime_failed IS NULL AND datetime_ttl > ?
+            LIMIT 1
+            "#,
+            user_id,
+            datetime_now
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // exports whose ttl has passed, oldest first
+    pub async fn get_expired(
+        pool: &Pool<MySql>,
+        datetime_now: &NaiveDateTime,
+        limit: u32,
+    ) -> Result<Vec<UserExportModel>, sqlx::error::Error> {
+        let rows = sqlx::query_as!(
+            UserExportModel,
+            r#"
+            SELECT id, value, user_id, datetime_ready, datetime_failed, datetime_ttl, datetime_created
+            FROM user_export
+            WHERE datetime_ttl <= ?
+            ORDER BY datetime_ttl
+            LIMIT ?
+            "#,
+            datetime_now,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    // the archive has been written
+    pub async fn mark_ready(
+        &mut self,
+        pool: &Pool<MySql>,
+        datetime_ready: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_export SET datetime_ready = ? WHERE id = ?
+            "#,
+            datetime_ready,
+            self.id
+        )
+        .execute(pool)
+        .await?;
+
+        self.datetime_ready = Some(*datetime_ready);
+
+        Ok(())
+    }
+
+    // the archive could not be built, the user has to ask again
+    pub async fn mark_failed(
+        &mut self,
+        pool: &Pool<MySql>,
+        datetime_failed: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_export SET datetime_failed = ? WHERE id = ?
+            "#,
+            datetime_failed,
+            self.id
+        )
+        .execute(pool)
+        .await?;
+
+        self.datetime_failed = Some(*datetime_failed);
+
+        Ok(())
+    }
+
+    // remove this row - the archive file is the caller's to delete
+    pub async fn delete(&self, pool: &Pool<MySql>) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM user_export WHERE id = ?
+            "#,
+            self.id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool, Transaction};
+
+// A handle a user had before changing it
+// for a while after datetime_released it still points to the user and can't be taken by anyone else
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserHandleHistoryModel {
+    pub id: i64,
+    pub user_id: i64,
+    pub value: String,
+    pub normalized: String,
+    pub datetime_released: NaiveDateTime,
+    pub datetime_created: NaiveDateTime,
+}
+
+impl UserHandleHistoryModel {
+    // insert new row into user_handle_history table
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+        value: &str,
+        normalized: &str,
+        datetime_released: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_handle_history (user_id, value, normalized, datetime_released)
+            VALUES (?, ?, ?, ?)
+            "#,
+            user_id,
+            value,
+            normalized,
+            datetime_released
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    // latest user to have released the handle after the cutoff, if any
+    pub async fn get_held_by_normalized(
+        pool: &Pool<MySql>,
+        normalized: &str,
+        datetime_cutoff: &NaiveDateTime,
+    ) -> Result<Option<UserHandleHistoryModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserHandleHistoryModel,
+            r#"
+            SELECT id, user_id, value, normalized, datetime_released, datetime_created
+            FROM user_handle_history
+            WHERE normalized = ? AND datetime_released > ?
+            ORDER BY datetime_released DESC
+            LIMIT 1
+            "#,
+            normalized,
+            datetime_cutoff
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // handles the user had before, latest first
+    pub async fn get_by_user_id(
+        pool: &Pool<MySql>,
+        user_id: i64,
+    ) -> Result<Vec<UserHandleHistoryModel>, sqlx::error::Error> {
+        let rows = sqlx::query_as!(
+            UserHandleHistoryModel,
+            r#"
+            SELECT id, user_id, value, normalized, datetime_released, datetime_created
+            FROM user_handle_history
+            WHERE user_id = ?
+            ORDER BY datetime_released DESC
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    // remove every past handle of a user
+    pub async fn delete_by_user_id(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM user_handle_history WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool, Transaction};
+
+// The current @handle of a user - a user has at most one
+// value keeps the casing the user chose, normalized is lowercase and is what has to be unique
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserHandleModel {
+    pub id: i64,
+    pub user_id: i64,
+    pub value: String,
+    pub normalized: String,
+    pub datetime_taken: NaiveDateTime, // when the user took the current value
+    pub datetime_created: NaiveDateTime,
+}
+
+impl UserHandleModel {
+    // insert new row into user_handle table
+    // returns UserHandleModel instance with the newly inserted values
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+        value: &str,
+        normalized: &str,
+    ) -> Result<UserHandleModel, sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_handle (user_id, value, normalized)
+            VALUES (?, ?, ?)
+            "#,
+            user_id,
+            value,
+            normalized
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        let row = sqlx::query_as!(
+            UserHandleModel,
+            r#"
+            SELECT id, user_id, value, normalized, datetime_taken, datetime_created
+            FROM user_handle
+            WHERE id = LAST_INSERT_ID()
+            "#
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get single user_handle row by its normalized value
+    pub async fn get_by_normalized(
+        pool: &Pool<MySql>,
+        normalized: &str,
+    ) -> Result<Option<UserHandleModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserHandleModel,
+            r#"
+            SELECT id, user_id, value, normalized, datetime_taken, datetime_created
+            FROM user_handle
+            WHERE normalized = ?
+            "#,
+            normalized
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get the handle of a user, if it has one
+    pub async fn get_by_user_id(
+        pool: &Pool<MySql>,
+        user_id: i64,
+    ) -> Result<Option<UserHandleModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserHandleModel,
+            r#"
+            SELECT id, user_id, value, normalized, datetime_taken, datetime_created
+            FROM user_handle
+            WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // replace the handle of this row
+    pub async fn update_value(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        value: &str,
+        normalized: &str,
+        datetime_taken: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_handle SET value = ?, normalized = ?, datetime_taken = ? WHERE id = ?
+            "#,
+            value,
+            normalized,
+            datetime_taken,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.value = value.to_string();
+        self.normalized = normalized.to_string();
+        self.datetime_taken = *datetime_taken;
+
+        Ok(())
+    }
+
+    // remove the handle of a user, it is free to be taken right away
+    pub async fn delete_by_user_id(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM user_handle WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
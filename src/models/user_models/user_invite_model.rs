@@ -0,0 +1,184 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool, Transaction};
+
+// Who may register with an invite
+pub enum InviteRestriction {
+    Anyone,
+    Email(String),  // only this email, compared case-insensitively
+    Domain(String), // only emails of this domain, e.g. example.com
+}
+
+// An invite code that lets people register while registration is invite only
+// it can be locked to a single email or to the emails of a domain
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserInviteModel {
+    pub id: i64,
+    pub value: String, // public id of the invite
+    pub code: String, // sha256 hex digest of the code - raw code is only shown to its creator, once
+    pub created_by_user_id: i64,
+    pub email: Option<String>,
+    pub domain: Option<String>,
+    pub max_uses: u32,
+    pub uses: u32,
+    pub datetime_ttl: NaiveDateTime,
+    pub datetime_revoked: Option<NaiveDateTime>,
+    pub datetime_created: NaiveDateTime,
+}
+
+impl UserInviteModel {
+    // insert new row into user_invite table
+    // returns UserInviteModel instance with the newly inserted values
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        value: &str,
+        code: &str,
+        created_by_user_id: i64,
+        restriction: &InviteRestriction,
+        max_uses: u32,
+        datetime_ttl: &NaiveDateTime,
+    ) -> Result<UserInviteModel, sqlx::error::Error> {
+        let (email, domain) = match restriction {
+            InviteRestriction::Anyone => (None, None),
+            InviteRestriction::Email(e) => (Some(e.as_str()), None),
+            InviteRestriction::Domain(d) => (None, Some(d.as_str())),
+        };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_invite (value, code, created_by_user_id, email, domain, max_uses, datetime_ttl)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            value,
+            code,
+            created_by_user_id,
+            email,
+            domain,
+            max_uses,
+            datetime_ttl
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        let row = sqlx::query_as!(
+            UserInviteModel,
+            r#"
+            SELECT id, value, code, created_by_user_id, email, domain, max_uses, uses,
+                datetime_ttl, datetime_revoked, datetime_created
+            FROM user_invite
+            WHERE id = LAST_INSERT_ID()
+            "#
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get single user_invite row by value
+    pub async fn get_by_value(
+        pool: &Pool<MySql>,
+        value: &str,
+    ) -> Result<Option<UserInviteModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserInviteModel,
+            r#"
+            SELECT id, value, code, created_by_user_id, email, domain, max_uses, uses,
+                datetime_ttl, datetime_revoked, datetime_created
+            FROM user_invite
+            WHERE value = ?
+            "#,
+            value
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get single user_invite row by the hash of its code
+    pub async fn get_by_code(
+        pool: &Pool<MySql>,
+        code: &str,
+    ) -> Result<Option<UserInviteModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserInviteModel,
+            r#"
+            SELECT id, value, code, created_by_user_id, email, domain, max_uses, uses,
+                datetime_ttl, datetime_revoked, datetime_created
+            FROM user_invite
+            WHERE code = ?
+            "#,
+            code
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // invites created by a user, latest first
+    pub async fn get_by_created_by_user_id(
+        pool: &Pool<MySql>,
+        created_by_user_id: i64,
+    ) -> Result<Vec<UserInviteModel>, sqlx::error::Error> {
+        let rows = sqlx::query_as!(
+            UserInviteModel,
+            r#"
+            SELECT id, value, code, created_by_user_id, email, domain, max_uses, uses,
+                datetime_ttl, datetime_revoked, datetime_created
+            FROM user_invite
+            WHERE created_by_user_id = ?
+            ORDER BY datetime_created DESC
+            "#,
+            created_by_user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    // count one use of the invite, only if it is still usable
+    // returns false if it was used up, revoked or expired in the meantime
+    pub async fn use_once(
+        tx: &mut Transaction<'_, MySql>,
+        id: i64,
+        datetime_now: &NaiveDateTime,
+    ) -> Result<bool, sqlx::error::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_invite
+            SET uses = uses + 1
+            WHERE id = ? AND uses < max_uses AND datetime_revoked IS NULL AND datetime_ttl > ?
+            "#,
+            id,
+            datetime_now
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    // the invite can't be used anymore, the uses it already had are kept
+    pub async fn revoke(
+        &mut self,
+        pool: &Pool<MySql>,
+        datetime_revoked: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_invite SET datetime_revoked = ? WHERE id = ? AND datetime_revoked IS NULL
+            "#,
+            datetime_revoked,
+            self.id
+        )
+        .execute(pool)
+        .await?;
+
+        self.datetime_revoked = self.datetime_revoked.or(Some(*datetime_revoked));
+
+        Ok(())
+    }
+}
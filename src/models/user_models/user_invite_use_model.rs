@@ -0,0 +1,59 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool, Transaction};
+
+// A user that registered with an invite, with its pid filled in
+// together with the invite's creator this is who invited whom
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserInviteUseModel {
+    pub id: i64,
+    pub invite_id: i64,
+    pub user_id: i64,
+    pub pid: String,
+    pub datetime_created: NaiveDateTime,
+}
+
+impl UserInviteUseModel {
+    // insert new row into user_invite_use table
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        invite_id: i64,
+        user_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_invite_use (invite_id, user_id)
+            VALUES (?, ?)
+            "#,
+            invite_id,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    // users that registered with the invite, in the order they did
+    pub async fn get_by_invite_id(
+        pool: &Pool<MySql>,
+        invite_id: i64,
+    ) -> Result<Vec<UserInviteUseModel>, sqlx::error::Error> {
+        let rows = sqlx::query_as!(
+            UserInviteUseModel,
+            r#"
+            SELECT iu.id, iu.invite_id, iu.user_id, p.value AS pid, iu.datetime_created
+            FROM user_invite_use iu
+            INNER JOIN user u ON u.id = iu.user_id
+            INNER JOIN user_pid p ON p.id = u.pid_id
+            WHERE iu.invite_id = ?
+            ORDER BY iu.datetime_created
+            "#,
+            invite_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool, Transaction};
+
+// TOTP settings of a user
+// row exists with datetime_enabled NULL while enrollment is not yet verified
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserMfaModel {
+    pub id: i64,
+    pub user_id: i64,
+    pub secret: String,              // base32 TOTP secret
+    pub last_used_step: Option<i64>, // time step of the last accepted code - stops replays
+    pub datetime_enabled: Option<NaiveDateTime>,
+    pub datetime_created: NaiveDateTime,
+}
+
+impl UserMfaModel {
+    // insert new row into user_mfa table
+    // returns UserMfaModel instance with the newly inserted values
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+        secret: &str,
+    ) -> Result<UserMfaModel, sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_mfa (user_id, secret)
+            VALUES (?, ?)
+            "#,
+            user_id,
+            secret
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        let row = sqlx::query_as!(
+            UserMfaModel,
+            r#"
+            SELECT id, user_id, secret, last_used_step, datetime_enabled, datetime_created
+            FROM user_mfa
+            WHERE id = LAST_INSERT_ID()
+            "#
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get the user_mfa row of a user
+    pub async fn get_by_user_id(
+        pool: &Pool<MySql>,
+        user_id: i64,
+    ) -> Result<Option<UserMfaModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserMfaModel,
+            r#"
+            SELECT id, user_id, secret, last_used_step, datetime_enabled, datetime_created
+            FROM user_mfa
+            WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // mark enrollment as verified
+    pub async fn update_datetime_enabled(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        datetime_enabled: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_mfa SET datetime_enabled = ? WHERE id = ?
+            "#,
+            datetime_enabled,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.datetime_enabled = Some(*datetime_enabled);
+
+        Ok(())
+    }
+
+    // remember the time step of an accepted code
+    // only succeeds if no code of this step or a later one was accepted before
+    // returns true if this call recorded the step
+    pub async fn update_last_used_step(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        step: i64,
+    ) -> Result<bool, sqlx::error::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_mfa
+            SET last_used_step = ?
+            WHERE id = ? AND (last_used_step IS NULL OR last_used_step < ?)
+            "#,
+            step,
+            self.id,
+            step
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        self.last_used_step = Some(step);
+
+        Ok(true)
+    }
+
+    // delete the user_mfa row of a user
+    pub async fn delete_by_user_id(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM user_mfa WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool, Transaction};
+
+// One-time code a user can log in with when the authenticator app is not available
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserMfaRecoveryCodeModel {
+    pub id: i64,
+    pub user_id: i64,
+    pub value: String, // sha256 hex digest of the code
+    pub datetime_used: Option<NaiveDateTime>,
+    pub datetime_created: NaiveDateTime,
+}
+
+impl UserMfaRecoveryCodeModel {
+    // insert new row into user_mfa_recovery_code table
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+        value: &str,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_mfa_recovery_code (user_id, value)
+            VALUES (?, ?)
+            "#,
+            user_id,
+            value
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    // get an unused recovery code of a user by value (hashed)
+    pub async fn get_unused_by_value(
+        pool: &Pool<MySql>,
+        user_id: i64,
+        value: &str,
+    ) -> Result<Option<UserMfaRecoveryCodeModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserMfaRecoveryCodeModel,
+            r#"
+            SELECT id, user_id, value, datetime_used, datetime_created
+            FROM user_mfa_recovery_code
+            WHERE user_id = ? AND value = ? AND datetime_used IS NULL
+            "#,
+            user_id,
+            value
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // mark the code as used, only if it is still unused
+    // returns true if this call is the one that used the code
+    pub async fn mark_used(
+        &self,
+        tx: &mut Transaction<'_, MySql>,
+        datetime_now: &NaiveDateTime,
+    ) -> Result<bool, sqlx::error::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_mfa_recovery_code
+            SET datetime_used = ?
+            WHERE id = ? AND datetime_used IS NULL
+            "#,
+            datetime_now,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    // delete every recovery code of a user
+    pub async fn delete_by_user_id(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM user_mfa_recovery_code WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
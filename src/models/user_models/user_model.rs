@@ -77,6 +77,26 @@ impl UserModel {
         Ok(row)
     }
 
+    // get single user using the value of its current authid
+    // old authid values of the user don't match anymore
+    pub async fn get_by_authid_value(
+        pool: &Pool<MySql>,
+        authid_value: &str,
+    ) -> Result<Option<UserModel>, sqlx::error::Error> {
+        let row: Option<UserModel> = sqlx::query_as!(
+            UserModel,
+            r#"
+            SELECT u.id, u.password, u.datetime_created, u.firstname_id, u.lastname_id, u.email_id, u.pid_id, u.authid_id, u.datetime_confirmed, u.datetime_deactivated, u.datetime_deleted
+            FROM user u
+            INNER JOIN user_authid a ON a.id = u.authid_id
+            WHERE a.value = ?
+            "#,
+            authid_value
+        ).fetch_optional(pool).await?;
+
+        Ok(row)
+    }
+
     // get single user using the email id
     pub async fn get_by_email_id(
         pool: &Pool<MySql>,
@@ -94,4 +114,261 @@ impl UserModel {
 
         Ok(row)
     }
+
+    // set datetime_confirmed of this user
+    pub async fn update_datetime_confirmed(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        datetime_confirmed: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user SET datetime_confirmed = ? WHERE id = ?
+            "#,
+            datetime_confirmed,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.datetime_confirmed = Some(*datetime_confirmed);
+
+        Ok(())
+    }
+
+    // set password of this user - value must already be hashed
+    pub async fn update_password(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        hashed_pw: &str,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user SET password = ? WHERE id = ?
+            "#,
+            hashed_pw,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.password = hashed_pw.to_string();
+
+        Ok(())
+    }
+
+    // point this user to another authid row
+    pub async fn update_authid_id(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        authid_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user SET authid_id = ? WHERE id = ?
+            "#,
+            authid_id,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.authid_id = authid_id;
+
+        Ok(())
+    }
+
+    // point this user to another email row
+    pub async fn update_email_id(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        email_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user SET email_id = ? WHERE id = ?
+            "#,
+            email_id,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.email_id = email_id;
+
+        Ok(())
+    }
+
+    // point this user to other firstname and lastname rows
+    pub async fn update_name_ids(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        firstname_id: i64,
+        lastname_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user SET firstname_id = ?, lastname_id = ? WHERE id = ?
+            "#,
+            firstname_id,
+            lastname_id,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.firstname_id = firstname_id;
+        self.lastname_id = lastname_id;
+
+        Ok(())
+    }
+
+    // get single user using the id
+    pub async fn get_by_id(
+        pool: &Pool<MySql>,
+        id: i64,
+    ) -> Result<Option<UserModel>, sqlx::error::Error> {
+        let row: Option<UserModel> = sqlx::query_as!(
+            UserModel,
+            r#"
+            SELECT id, password, datetime_created, firstname_id, lastname_id, email_id, pid_id, authid_id, datetime_confirmed, datetime_deactivated, datetime_deleted
+            FROM user
+            WHERE id = ?
+            "#,
+            id
+        ).fetch_optional(pool).await?;
+
+        Ok(row)
+    }
+
+    // set or clear datetime_deactivated of this user
+    pub async fn update_datetime_deactivated(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        datetime_deactivated: Option<NaiveDateTime>,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user SET datetime_deactivated = ? WHERE id = ?
+            "#,
+            datetime_deactivated,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.datetime_deactivated = datetime_deactivated;
+
+        Ok(())
+    }
+
+    // set or clear datetime_deleted of this user - the time deletion was asked for
+    pub async fn update_datetime_deleted(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        datetime_deleted: Option<NaiveDateTime>,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user SET datetime_deleted = ? WHERE id = ?
+            "#,
+            datetime_deleted,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.datetime_deleted = datetime_deleted;
+
+        Ok(())
+    }
+
+    // clear datetime_deactivated and datetime_deleted of this user
+    // returns false if the user has already been anonymised, there's nothing to bring back then
+    pub async fn reactivate(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+    ) -> Result<bool, sqlx::error::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user
+            SET datetime_deactivated = NULL, datetime_deleted = NULL
+            WHERE id = ? AND datetime_anonymised IS NULL
+            "#,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        self.datetime_deactivated = None;
+        self.datetime_deleted = None;
+
+        Ok(true)
+    }
+
+    // ids of users that asked for deletion on or before the cutoff and are not anonymised yet
+    pub async fn get_ids_due_for_anonymising(
+        pool: &Pool<MySql>,
+        datetime_cutoff: &NaiveDateTime,
+        limit: u32,
+    ) -> Result<Vec<i64>, sqlx::error::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id
+            FROM user
+            WHERE datetime_deleted <= ? AND datetime_anonymised IS NULL
+            ORDER BY datetime_deleted
+            LIMIT ?
+            "#,
+            datetime_cutoff,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.id).collect())
+    }
+
+    // replace the password and names of this user and mark it as anonymised
+    // only if deletion is still pending and was asked for on or before the cutoff
+    // returns false if the user reactivated in the meantime
+    pub async fn anonymise(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        datetime_cutoff: &NaiveDateTime,
+        hashed_pw: &str,
+        firstname_id: i64,
+        lastname_id: i64,
+        datetime_anonymised: &NaiveDateTime,
+    ) -> Result<bool, sqlx::error::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user
+            SET password = ?, firstname_id = ?, lastname_id = ?, datetime_anonymised = ?
+            WHERE id = ? AND datetime_deleted <= ? AND datetime_anonymised IS NULL
+            "#,
+            hashed_pw,
+            firstname_id,
+            lastname_id,
+            datetime_anonymised,
+            self.id,
+            datetime_cutoff
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        self.password = hashed_pw.to_string();
+        self.firstname_id = firstname_id;
+        self.lastname_id = lastname_id;
+
+        Ok(true)
+    }
 }
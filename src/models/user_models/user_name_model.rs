@@ -79,4 +79,26 @@ impl UserNameModel {
 
         Ok(row)
     }
+
+    // delete a user_name row that no user has as firstname or lastname anymore
+    // rows are shared between users with the same name, so one still in use is kept
+    pub async fn delete_if_unused(
+        tx: &mut Transaction<'_, MySql>,
+        id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM user_name
+            WHERE id = ?
+                AND NOT EXISTS (SELECT 1 FROM user u WHERE u.firstname_id = ? OR u.lastname_id = ?)
+            "#,
+            id,
+            id,
+            id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
 }
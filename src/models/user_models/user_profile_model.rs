@@ -0,0 +1,76 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool};
+
+// A user with its pid, names, email, handle and avatar filled in - read only
+// what a client gets to see of it is decided by the profile dtos
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserProfileModel {
+    pub user_id: i64,
+    pub pid: String,
+    pub firstname: String,
+    pub lastname: String,
+    pub email: String,
+    pub handle: Option<String>, // users from before handles may not have one
+    pub avatar: Option<String>, // value of the avatar, None until one is uploaded
+    pub datetime_created: NaiveDateTime,
+    pub datetime_confirmed: Option<NaiveDateTime>,
+    pub datetime_deactivated: Option<NaiveDateTime>,
+    pub datetime_deleted: Option<NaiveDateTime>,
+}
+
+impl UserProfileModel {
+    // get the profile of a user using the user id
+    pub async fn get_by_user_id(
+        pool: &Pool<MySql>,
+        user_id: i64,
+    ) -> Result<Option<UserProfileModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserProfileModel,
+            r#"
+            SELECT u.id AS user_id, p.value AS pid, f.value AS firstname, l.value AS lastname, e.value AS email,
+                h.value AS "handle?", a.value AS "avatar?", u.datetime_created, u.datetime_confirmed, u.datetime_deactivated, u.datetime_deleted
+            FROM user u
+            INNER JOIN user_pid p ON p.id = u.pid_id
+            INNER JOIN user_name f ON f.id = u.firstname_id
+            INNER JOIN user_name l ON l.id = u.lastname_id
+            INNER JOIN user_email e ON e.id = u.email_id
+            LEFT JOIN user_handle h ON h.user_id = u.id
+            LEFT JOIN user_avatar a ON a.user_id = u.id
+            WHERE u.id = ?
+            "#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get the profile of a user using the value of its pid
+    pub async fn get_by_pid_value(
+        pool: &Pool<MySql>,
+        pid_value: &str,
+    ) -> Result<Option<UserProfileModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserProfileModel,
+            r#"
+            SELECT u.id AS user_id, p.value AS pid, f.value AS firstname, l.value AS lastname, e.value AS email,
+                h.value AS "handle?", a.value AS "avatar?", u.datetime_created, u.datetime_confirmed, u.datetime_deactivated, u.datetime_deleted
+            FROM user u
+            INNER JOIN user_pid p ON p.id = u.pid_id
+            INNER JOIN user_name f ON f.id = u.firstname_id
+            INNER JOIN user_name l ON l.id = u.lastname_id
+            INNER JOIN user_email e ON e.id = u.email_id
+            LEFT JOIN user_handle h ON h.user_id = u.id
+            LEFT JOIN user_avatar a ON a.user_id = u.id
+            WHERE p.value = ?
+            "#,
+            pid_value
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+}
@@ -0,0 +1,192 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool, Transaction};
+
+// A device/browser the user is signed in on
+// one session per refresh token family - revoking the family ends the session
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserSessionModel {
+    pub id: i64,
+    pub value: String, // public id of the session
+    pub user_id: i64,
+    pub family_id: i64,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub datetime_last_used: NaiveDateTime,
+    pub datetime_created: NaiveDateTime,
+}
+
+impl UserSessionModel {
+    // insert new row into user_session table
+    // returns UserSessionModel instance with the newly inserted values
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        value: &str,
+        user_id: i64,
+        family_id: i64,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+        datetime_last_used: &NaiveDateTime,
+    ) -> Result<UserSessionModel, sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_session (value, user_id, family_id, user_agent, ip_address, datetime_last_used)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            value,
+            user_id,
+            family_id,
+            user_agent,
+            ip_address,
+            datetime_last_used
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        let row = sqlx::query_as!(
+            UserSessionModel,
+            r#"
+            SELECT id, value, user_id, family_id, user_agent, ip_address, datetime_last_used, datetime_created
+            FROM user_session
+            WHERE id = LAST_INSERT_ID()
+            "#
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get single user_session row by value
+    pub async fn get_by_value(
+        pool: &Pool<MySql>,
+        value: &str,
+    ) -> Result<Option<UserSessionModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserSessionModel,
+            r#"
+            SELECT id, value, user_id, family_id, user_agent, ip_address, datetime_last_used, datetime_created
+            FROM user_session
+            WHERE value = ?
+            "#,
+            value
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get the session of a refresh token family
+    pub async fn get_by_family_id(
+        pool: &Pool<MySql>,
+        family_id: i64,
+    ) -> Result<Option<UserSessionModel>, sqlx::error::Error> {
+        let row = sqlx::query_as!(
+            UserSessionModel,
+            r#"
+            SELECT id, value, user_id, family_id, user_agent, ip_address, datetime_last_used, datetime_created
+            FROM user_session
+            WHERE family_id = ?
+            "#,
+            family_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get every session of a user whose refresh token family is not revoked
+    // most recently used first
+    pub async fn get_active_by_user_id(
+        pool: &Pool<MySql>,
+        user_id: i64,
+    ) -> Result<Vec<UserSessionModel>, sqlx::error::Error> {
+        let rows = sqlx::query_as!(
+            UserSessionModel,
+            r#"
+            SELECT s.id, s.value, s.user_id, s.family_id, s.user_agent, s.ip_address, s.datetime_last_used, s.datetime_created
+            FROM user_session s
+            INNER JOIN refresh_token_family f ON f.id = s.family_id
+            WHERE s.user_id = ? AND f.datetime_revoked IS NULL
+            ORDER BY s.datetime_last_used DESC
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    // get every session a user has ever had, revoked ones included
+    // most recently used first
+    pub async fn get_by_user_id(
+        pool: &Pool<MySql>,
+        user_id: i64,
+    ) -> Result<Vec<UserSessionModel>, sqlx::error::Error> {
+        let rows = sqlx::query_as!(
+            UserSessionModel,
+            r#"
+            SELECT id, value, user_id, family_id, user_agent, ip_address, datetime_last_used, datetime_created
+            FROM user_session
+            WHERE user_id = ?
+            ORDER BY datetime_last_used DESC
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    // record that the session was used again, and from where
+    pub async fn update_last_used(
+        &mut self,
+        tx: &mut Transaction<'_, MySql>,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+        datetime_last_used: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_session
+            SET user_agent = ?, ip_address = ?, datetime_last_used = ?
+            WHERE id = ?
+            "#,
+            user_agent,
+            ip_address,
+            datetime_last_used,
+            self.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        self.user_agent = user_agent.map(|ua| ua.to_string());
+        self.ip_address = ip_address.map(|ip| ip.to_string());
+        self.datetime_last_used = *datetime_last_used;
+
+        Ok(())
+    }
+
+    // forget the user agent and ip address of every session of a user
+    pub async fn clear_client_info_by_user_id(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_session
+            SET user_agent = NULL, ip_address = NULL
+            WHERE user_id = ?
+            "#,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, MySql, Pool, Transaction};
+
+// What a user_token row can be used for
+// value is stored in the purpose column
+pub enum UserTokenPurpose {
+    ConfirmEmail,
+    ResetPassword,
+    ChangeEmail,
+    MfaPending,
+    MfaPendingReactivate, // 2FA login of an inactive account that asked to be reactivated
+    ReactivateAccount,
+    MagicLogin,
+}
+
+impl UserTokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserTokenPurpose::ConfirmEmail => "confirm_email",
+            UserTokenPurpose::ResetPassword => "reset_password",
+            UserTokenPurpose::ChangeEmail => "change_email",
+            UserTokenPurpose::MfaPending => "mfa_pending",
+            UserTokenPurpose::MfaPendingReactivate => "mfa_pending_reactivate",
+            UserTokenPurpose::ReactivateAccount => "reactivate_account",
+            UserTokenPurpose::MagicLogin => "magic_login",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, FromRow)]
+pub struct UserTokenModel {
+    pub id: i64,
+    pub user_id: i64,
+    pub purpose: String,
+    pub value: String, // sha256 hex digest of the token - raw token is only ever sent to the user
+    pub datetime_ttl: NaiveDateTime,
+    pub datetime_used: Option<NaiveDateTime>,
+    pub datetime_created: NaiveDateTime,
+    pub email_id: Option<i64>, // email the token is for, only used by email change and magic login
+}
+
+impl UserTokenModel {
+    // insert new row into user_token table
+    // returns UserTokenModel instance with the newly inserted values
+    pub async fn new(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+        purpose: &UserTokenPurpose,
+        value: &str,
+        datetime_ttl: &NaiveDateTime,
+        email_id: Option<i64>,
+    ) -> Result<UserTokenModel, sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_token (user_id, purpose, value, datetime_ttl, email_id)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            user_id,
+            purpose.as_str(),
+            value,
+            datetime_ttl,
+            email_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        let row = sqlx::query_as!(
+            UserTokenModel,
+            r#"
+            SELECT id, user_id, purpose, value, datetime_ttl, datetime_used, datetime_created, email_id
+            FROM user_token
+            WHERE id = LAST_INSERT_ID()
+            "#
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(row)
+    }
+
+    // get single user_token row by value (hashed) and purpose
+    pub async fn get_by_value(
+        pool: &Pool<MySql>,
+        purpose: &UserTokenPurpose,
+        value: &str,
+    ) -> Result<Option<UserTokenModel>, sqlx::error::Error> {
+        let row: Option<UserTokenModel> = sqlx::query_as!(
+            UserTokenModel,
+            r#"
+            SELECT id, user_id, purpose, value, datetime_ttl, datetime_used, datetime_created, email_id
+            FROM user_token
+            WHERE value = ? AND purpose = ?
+            "#,
+            value,
+            purpose.as_str()
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    // mark a token as used, only if it is still unused and not yet expired
+    // returns true if this call is the one that used the token
+    pub async fn mark_used(
+        &self,
+        tx: &mut Transaction<'_, MySql>,
+        datetime_now: &NaiveDateTime,
+    ) -> Result<bool, sqlx::error::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_token
+            SET datetime_used = ?
+            WHERE id = ? AND datetime_used IS NULL AND datetime_ttl > ?
+            "#,
+            datetime_now,
+            self.id,
+            datetime_now
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    // mark every unused token of a user for a purpose as used
+    // used when a new token is issued so only the latest one works
+    pub async fn invalidate_by_user_id(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+        purpose: &UserTokenPurpose,
+        datetime_now: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_token
+            SET datetime_used = ?
+            WHERE user_id = ? AND purpose = ? AND datetime_used IS NULL
+            "#,
+            datetime_now,
+            user_id,
+            purpose.as_str()
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    // mark every unused token of a user as used, whatever the purpose
+    pub async fn invalidate_all_by_user_id(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+        datetime_now: &NaiveDateTime,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_token
+            SET datetime_used = ?
+            WHERE user_id = ? AND datetime_used IS NULL
+            "#,
+            datetime_now,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
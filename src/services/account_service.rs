@@ -0,0 +1,221 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::{MySql, Pool};
+
+use crate::config::app_config::{AccountConfig, MailConfig};
+use crate::mailers::mailer::{MailMessage, Mailer};
+use crate::models::user_models::user_avatar_model::UserAvatarModel;
+use crate::models::user_models::user_email_model::UserEmailModel;
+use crate::models::user_models::user_handle_history_model::UserHandleHistoryModel;
+use crate::models::user_models::user_handle_model::UserHandleModel;
+use crate::models::user_models::user_model::UserModel;
+use crate::models::user_models::user_name_model::UserNameModel;
+use crate::models::user_models::user_session_model::UserSessionModel;
+use crate::models::user_models::user_token_model::{UserTokenModel, UserTokenPurpose};
+use crate::services::avatar_service::AvatarService;
+use crate::services::user_service::UserService;
+use crate::services::user_token_service::UserTokenService;
+use crate::stores::blob_store::BlobStore;
+use crate::utils::bcrypt_utils::make_hash;
+use crate::utils::string_utils::random_alphanumeric;
+
+// name an anonymised user is shown with - anything the user wrote stays, attributed to "Deleted user"
+const DELETED_USER_FIRSTNAME: &str = "Deleted";
+const DELETED_USER_LASTNAME: &str = "user";
+
+pub struct AccountService {}
+
+impl AccountService {
+    // hide the user and log it out on all devices
+    // nothing is removed, logging in with reactivate or the emailed link brings the account back
+    pub async fn deactivate(
+        pool: &Pool<MySql>,
+        user: &mut UserModel,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tx = pool.begin().await?;
+        user.update_datetime_deactivated(&mut tx, Some(Utc::now().naive_utc()))
+            .await?;
+        let rotation = UserService::rotate_authid(pool, &mut tx, user).await?;
+        tx.commit().await?;
+        rotation.committed();
+
+        Ok(())
+    }
+
+    // same as deactivate, and the account is anonymised once the grace period is over
+    // returns when that will happen at the earliest
+    pub async fn request_deletion(
+        pool: &Pool<MySql>,
+        account_config: &AccountConfig,
+        user: &mut UserModel,
+    ) -> Result<NaiveDateTime, Box<dyn std::error::Error>> {
+        let now = Utc::now().naive_utc();
+
+        let mut tx = pool.begin().await?;
+        user.update_datetime_deleted(&mut tx, Some(now)).await?;
+        let rotation = UserService::rotate_authid(pool, &mut tx, user).await?;
+        tx.commit().await?;
+        rotation.committed();
+
+        Ok(now + Duration::days(account_config.deletion_grace_days))
+    }
+
+    // undo deactivation and cancel a pending deletion
+    // returns false if the user has already been anonymised
+    pub async fn reactivate(
+        pool: &Pool<MySql>,
+        user: &mut UserModel,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut tx = pool.begin().await?;
+        if !user.reactivate(&mut tx).await? {
+            return Ok(false);
+        }
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    // mail a reactivation link if the email belongs to a deactivated or deleted (not yet anonymised) user
+    // does nothing otherwise, callers must not tell the difference to the client
+    pub async fn send_reactivation(
+        pool: &Pool<MySql>,
+        mailer: &dyn Mailer,
+        mail_config: &MailConfig,
+        account_config: &AccountConfig,
+        email: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let user_email = match UserEmailModel::get_by_value(pool, email).await? {
+            None => return Ok(()),
+            Some(e) => e,
+        };
+
+        let user = match UserModel::get_by_email_id(pool, user_email.id).await? {
+            None => return Ok(()),
+            Some(u) => u,
+        };
+
+        if user.datetime_deactivated.is_none() && user.datetime_deleted.is_none() {
+            return Ok(());
+        }
+
+        let exp_minutes = account_config.reactivation_token_expiration_minutes;
+
+        let raw_token = UserTokenService::issue(
+            pool,
+            user.id,
+            &UserTokenPurpose::ReactivateAccount,
+            Duration::minutes(exp_minutes),
+            None,
+        )
+        .await?;
+
+        mailer.send(&MailMessage {
+            to: user_email.value,
+            subject: "Reactivate your account".to_string(),
+            body: format!(
+                "Use the link below to reactivate your account. It expires in {} minutes.\nIf you did not ask for this, you can ignore this email.\n\n{}/account/reactivate?token={}",
+                exp_minutes, mail_config.frontend_url, raw_token
+            ),
+        })?;
+
+        Ok(())
+    }
+
+    // use a reactivation token and reactivate its user
+    // returns None if the token is unknown, expired or already used, or the user is already anonymised
+    pub async fn reactivate_with_token(
+        pool: &Pool<MySql>,
+        raw_token: &str,
+    ) -> Result<Option<UserModel>, Box<dyn std::error::Error>> {
+        let mut tx = pool.begin().await?;
+
+        let token = match UserTokenService::consume(
+            pool,
+            &mut tx,
+            &UserTokenPurpose::ReactivateAccount,
+            raw_token,
+        )
+        .await?
+        {
+            None => return Ok(None),
+            Some(t) => t,
+        };
+
+        let mut user = UserModel::get_by_id(pool, token.user_id)
+            .await?
+            .ok_or(format!("Token {} has no user", token.id))?;
+
+        if !user.reactivate(&mut tx).await? {
+            return Ok(None);
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(user))
+    }
+
+    // remove the personal data of a user that asked for deletion on or before the cutoff
+    // the user row stays so whatever it authored keeps pointing to it
+    // returns false if there was nothing to do, e.g. the user reactivated in the meantime
+    pub async fn anonymise(
+        pool: &Pool<MySql>,
+        blob_store: &dyn BlobStore,
+        user_id: i64,
+        datetime_cutoff: &NaiveDateTime,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut user = match UserModel::get_by_id(pool, user_id).await? {
+            None => return Ok(false),
+            Some(u) => u,
+        };
+
+        let old_firstname_id = user.firstname_id;
+        let old_lastname_id = user.lastname_id;
+        let avatar = UserAvatarModel::get_by_user_id(pool, user.id).await?;
+
+        // nobody knows this password, so the account can't be logged into anymore
+        let hashed_pw = make_hash(&random_alphanumeric(64))?;
+        let now = Utc::now().naive_utc();
+
+        let mut tx = pool.begin().await?;
+
+        let firstname = match UserNameModel::get_by_value(pool, DELETED_USER_FIRSTNAME).await? {
+            Some(f) => f,
+            None => UserNameModel::new(&mut tx, DELETED_USER_FIRSTNAME).await?,
+        };
+        let lastname = match UserNameModel::get_by_value(pool, DELETED_USER_LASTNAME).await? {
+            Some(l) => l,
+            None => UserNameModel::new(&mut tx, DELETED_USER_LASTNAME).await?,
+        };
+
+        if !user
+            .anonymise(
+                &mut tx,
+                datetime_cutoff,
+                &hashed_pw,
+                firstname.id,
+                lastname.id,
+                &now,
+            )
+            .await?
+        {
+            return Ok(false);
+        }
+
+        UserEmailModel::anonymise_by_user_id(&mut tx, user.id).await?;
+        // handles are freed right away, old ones must not lead to the user anymore
+        UserHandleModel::delete_by_user_id(&mut tx, user.id).await?;
+        UserHandleHistoryModel::delete_by_user_id(&mut tx, user.id).await?;
+        UserAvatarModel::delete_by_user_id(&mut tx, user.id).await?;
+        UserNameModel::delete_if_unused(&mut tx, old_firstname_id).await?;
+        UserNameModel::delete_if_unused(&mut tx, old_lastname_id).await?;
+        UserSessionModel::clear_client_info_by_user_id(&mut tx, user.id).await?;
+        UserTokenModel::invalidate_all_by_user_id(&mut tx, user.id, &now).await?;
+
+        tx.commit().await?;
+
+        if let Some(avatar) = avatar {
+            AvatarService::delete_blobs(blob_store, &avatar.value).await;
+        }
+
+        Ok(true)
+    }
+}
@@ -1,20 +1,91 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+
 use chrono::NaiveDateTime;
 use sqlx::MySql;
 use sqlx::Pool;
 
 use crate::models::revoked_token_models::revoked_token_model::RevokedTokenModel;
+use crate::models::user_models::user_model::UserModel;
+use crate::utils::cache_utils::{CacheStats, TtlCache};
+use crate::utils::hash_utils::sha256_hex;
+
+const CACHE_CAPACITY: usize = 10_000;
+
+// token digest => revoked
+// a token revoked on another instance is only seen here once its entry expires,
+// refresh token reuse is still caught by its family - see RefreshTokenService::rotate
+static REVOKED_TOKEN_CACHE: LazyLock<TtlCache<bool>> =
+    LazyLock::new(|| TtlCache::new("revoked_token", Duration::from_secs(60), CACHE_CAPACITY));
+
+// authid value => id of its user, None once the authid is no longer any user's
+static AUTHID_CACHE: LazyLock<TtlCache<Option<i64>>> =
+    LazyLock::new(|| TtlCache::new("user_authid", Duration::from_secs(30), CACHE_CAPACITY));
 
 pub struct AuthService {}
 
 impl AuthService {
+    // only the digest of the token is stored, a dump of the table holds no usable tokens
     pub async fn create_revoked(
         pool: &Pool<MySql>,
         token: &str,
         datetime_ttl: &NaiveDateTime,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let value = sha256_hex(token);
+
         let mut tx = pool.begin().await?;
-        RevokedTokenModel::new(&mut tx, token, datetime_ttl).await?;
+        RevokedTokenModel::new(&mut tx, &value, datetime_ttl).await?;
         tx.commit().await?;
+
+        REVOKED_TOKEN_CACHE.insert(&value, true);
         Ok(())
     }
+
+    pub async fn is_revoked(
+        pool: &Pool<MySql>,
+        token: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let value = sha256_hex(token);
+
+        if let Some(revoked) = REVOKED_TOKEN_CACHE.get(&value) {
+            return Ok(revoked);
+        }
+
+        let revoked = RevokedTokenModel::get_by_value(pool, &value)
+            .await?
+            .is_some();
+        REVOKED_TOKEN_CACHE.insert(&value, revoked);
+
+        Ok(revoked)
+    }
+
+    // id of the user the authid (access token sub) currently belongs to
+    // None once the authid has been rotated
+    pub async fn user_id_by_authid(
+        pool: &Pool<MySql>,
+        authid_value: &str,
+    ) -> Result<Option<i64>, Box<dyn std::error::Error>> {
+        if let Some(user_id) = AUTHID_CACHE.get(authid_value) {
+            return Ok(user_id);
+        }
+
+        // taken before reading, so what was read is dropped if a rotation committed meanwhile
+        let generation = AUTHID_CACHE.generation();
+        let user_id = UserModel::get_by_authid_value(pool, authid_value)
+            .await?
+            .map(|u| u.id);
+        AUTHID_CACHE.insert_unless_invalidated(authid_value, user_id, generation);
+
+        Ok(user_id)
+    }
+
+    // the authid has been rotated away - only call once the rotation is committed
+    // a lookup that read the database before the commit won't cache its result, see user_id_by_authid
+    pub fn forget_authid(authid_value: &str) {
+        AUTHID_CACHE.invalidate(authid_value);
+    }
+
+    pub fn cache_stats() -> Vec<CacheStats> {
+        vec![REVOKED_TOKEN_CACHE.stats(), AUTHID_CACHE.stats()]
+    }
 }
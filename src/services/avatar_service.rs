@@ -0,0 +1,145 @@
+use actix_web::web;
+use sqlx::{MySql, Pool};
+
+use crate::config::app_config::{AvatarConfig, ServerConfig};
+use crate::dtos::avatar_dto::AvatarResponseData;
+use crate::models::user_models::user_avatar_model::UserAvatarModel;
+use crate::stores::blob_store::BlobStore;
+use crate::utils::image_utils::{ImageError, square_webp_variants};
+use crate::utils::string_utils::random_alphanumeric;
+
+// variants made of every avatar by name, the name is part of their url
+const AVATAR_VARIANTS: [(&str, u32); 3] = [("small", 64), ("medium", 128), ("large", 256)];
+
+// What uploading an avatar ended up doing
+pub enum AvatarUpload {
+    Saved(UserAvatarModel),
+    Rejected(String), // the image can't be used, with why - safe to show to the client
+}
+
+pub struct AvatarService {}
+
+impl AvatarService {
+    // make the variants of the image and make them the user's avatar
+    // the previous avatar of the user is deleted once nothing points to it anymore
+    pub async fn upload(
+        pool: &Pool<MySql>,
+        blob_store: &dyn BlobStore,
+        avatar_config: &AvatarConfig,
+        user_id: i64,
+        content: Vec<u8>,
+    ) -> Result<AvatarUpload, Box<dyn std::error::Error>> {
+        let sizes: Vec<u32> = AVATAR_VARIANTS.iter().map(|(_, size)| *size).collect();
+        let (min_dimension, max_dimension) =
+            (avatar_config.min_dimension, avatar_config.max_dimension);
+
+        // decoding and resizing is cpu heavy, kept off the worker threads
+        let variants = match web::block(move || {
+            square_webp_variants(&content, &sizes, min_dimension, max_dimension)
+        })
+        .await?
+        {
+            Err(ImageError::Rejected(reason)) => return Ok(AvatarUpload::Rejected(reason)),
+            Err(e) => return Err(e.into()),
+            Ok(v) => v,
+        };
+
+        let value = random_alphanumeric(32);
+        for ((name, _), variant) in AVATAR_VARIANTS.iter().zip(&variants) {
+            blob_store
+                .put(&AvatarService::blob_key(&value, name), variant)
+                .await?;
+        }
+
+        let old_avatar = UserAvatarModel::get_by_user_id(pool, user_id).await?;
+
+        let avatar = match _replace_row(pool, user_id, &value).await {
+            Err(e) => {
+                AvatarService::delete_blobs(blob_store, &value).await;
+                return Err(e);
+            }
+            Ok(a) => a,
+        };
+
+        if let Some(old_avatar) = old_avatar {
+            AvatarService::delete_blobs(blob_store, &old_avatar.value).await;
+        }
+
+        Ok(AvatarUpload::Saved(avatar))
+    }
+
+    // take the avatar of the user away
+    // returns false if the user had none
+    pub async fn remove(
+        pool: &Pool<MySql>,
+        blob_store: &dyn BlobStore,
+        user_id: i64,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let avatar = match UserAvatarModel::get_by_user_id(pool, user_id).await? {
+            None => return Ok(false),
+            Some(a) => a,
+        };
+
+        let mut tx = pool.begin().await?;
+        UserAvatarModel::delete_by_user_id(&mut tx, user_id).await?;
+        tx.commit().await?;
+
+        AvatarService::delete_blobs(blob_store, &avatar.value).await;
+
+        Ok(true)
+    }
+
+    // delete every variant of an avatar from the blob store
+    // failures are only logged - the row is gone already, so a left over file is never served
+    pub async fn delete_blobs(blob_store: &dyn BlobStore, avatar_value: &str) {
+        for (name, _) in AVATAR_VARIANTS.iter() {
+            let key = AvatarService::blob_key(avatar_value, name);
+            if let Err(e) = blob_store.delete(&key).await {
+                log::error!("Unable to delete blob {}. {}", key, e);
+            }
+        }
+    }
+
+    // where a variant of an avatar is kept in the blob store
+    // None if there is no variant by that name
+    pub fn variant_blob_key(avatar_value: &str, variant: &str) -> Option<String> {
+        AVATAR_VARIANTS
+            .iter()
+            .find(|(name, _)| *name == variant)
+            .map(|(name, _)| AvatarService::blob_key(avatar_value, name))
+    }
+
+    // urls of every variant of an avatar
+    pub fn urls(server_config: &ServerConfig, avatar_value: &str) -> AvatarResponseData {
+        let url = |variant: &str| {
+            format!(
+                "{}/api/users/avatars/{}/{}",
+                server_config.public_url, avatar_value, variant
+            )
+        };
+
+        AvatarResponseData {
+            small: url("small"),
+            medium: url("medium"),
+            large: url("large"),
+        }
+    }
+
+    fn blob_key(avatar_value: &str, variant: &str) -> String {
+        format!("avatars/{}/{}.webp", avatar_value, variant)
+    }
+}
+
+// point the user to the new avatar in one go
+async fn _replace_row(
+    pool: &Pool<MySql>,
+    user_id: i64,
+    value: &str,
+) -> Result<UserAvatarModel, Box<dyn std::error::Error>> {
+    let mut tx = pool.begin().await?;
+    UserAvatarModel::delete_by_user_id(&mut tx, user_id).await?;
+    let avatar = UserAvatarModel::new(&mut tx, user_id, value).await?;
+    tx.commit().await?;
+
+    Ok(avatar)
+}
@@ -0,0 +1,102 @@
+use chrono::{Duration, Utc};
+use sqlx::{MySql, Pool};
+
+use crate::config::app_config::{MailConfig, TokenConfig, UnconfirmedConfig};
+use crate::mailers::mailer::{MailMessage, Mailer};
+use crate::models::user_models::user_email_model::UserEmailModel;
+use crate::models::user_models::user_model::UserModel;
+use crate::models::user_models::user_token_model::UserTokenPurpose;
+use crate::services::user_token_service::UserTokenService;
+
+// Actions that can be allowed or blocked for users that have not confirmed their email yet
+pub enum UnconfirmedAction {
+    Refresh,
+}
+
+pub struct ConfirmationService {}
+
+impl ConfirmationService {
+    // confirmed users are always allowed
+    pub fn allows(
+        config: &UnconfirmedConfig,
+        user: &UserModel,
+        action: &UnconfirmedAction,
+    ) -> bool {
+        if user.datetime_confirmed.is_some() {
+            return true;
+        }
+
+        match action {
+            UnconfirmedAction::Refresh => config.allow_refresh,
+        }
+    }
+
+    // issue a new confirmation token for the user and mail it to the user's email
+    pub async fn send_confirmation(
+        pool: &Pool<MySql>,
+        mailer: &dyn Mailer,
+        mail_config: &MailConfig,
+        token_config: &TokenConfig,
+        user: &UserModel,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let user_email = UserEmailModel::get_by_id(pool, user.email_id)
+            .await?
+            .ok_or(format!("User {} doesn't have an email", user.id))?;
+
+        let exp_hours = token_config.confirmation_expiration_hours;
+
+        let raw_token = UserTokenService::issue(
+            pool,
+            user.id,
+            &UserTokenPurpose::ConfirmEmail,
+            Duration::hours(exp_hours),
+            None,
+        )
+        .await?;
+
+        mailer.send(&MailMessage {
+            to: user_email.value,
+            subject: "Confirm your email address".to_string(),
+            body: format!(
+                "Use the link below to confirm your email address. It expires in {} hours.\n\n{}/confirm?token={}",
+                exp_hours, mail_config.frontend_url, raw_token
+            ),
+        })?;
+
+        Ok(())
+    }
+
+    // use a confirmation token and stamp datetime_confirmed of its user
+    // returns None if the token is unknown, expired or already used
+    pub async fn confirm(
+        pool: &Pool<MySql>,
+        raw_token: &str,
+    ) -> Result<Option<UserModel>, Box<dyn std::error::Error>> {
+        let mut tx = pool.begin().await?;
+
+        let token = match UserTokenService::consume(
+            pool,
+            &mut tx,
+            &UserTokenPurpose::ConfirmEmail,
+            raw_token,
+        )
+        .await?
+        {
+            None => return Ok(None),
+            Some(t) => t,
+        };
+
+        let mut user = UserModel::get_by_id(pool, token.user_id)
+            .await?
+            .ok_or(format!("Token {} has no user", token.id))?;
+
+        if user.datetime_confirmed.is_none() {
+            user.update_datetime_confirmed(&mut tx, &Utc::now().naive_utc())
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(user))
+    }
+}
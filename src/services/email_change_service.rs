@@ -0,0 +1,127 @@
+use chrono::{Duration, Utc};
+use sqlx::{MySql, Pool};
+
+use crate::config::app_config::{MailConfig, TokenConfig};
+use crate::mailers::mailer::{MailMessage, Mailer};
+use crate::models::user_models::user_email_model::UserEmailModel;
+use crate::models::user_models::user_model::UserModel;
+use crate::models::user_models::user_token_model::UserTokenPurpose;
+use crate::services::user_service::UserService;
+use crate::services::user_token_service::UserTokenService;
+
+pub enum EmailChangeOutcome {
+    Changed, // the user has a new authid
+    InvalidToken,
+    EmailInUse,
+}
+
+pub struct EmailChangeService {}
+
+impl EmailChangeService {
+    // start moving the user to a new email
+    // the user keeps the current email until the link sent to the new one is used
+    pub async fn request_change(
+        pool: &Pool<MySql>,
+        mailer: &dyn Mailer,
+        mail_config: &MailConfig,
+        token_config: &TokenConfig,
+        user: &UserModel,
+        new_email: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // email rows are never deleted, reuse the row if the address was seen before
+        let new_email_obj = match UserEmailModel::get_by_value(pool, new_email).await? {
+            Some(e) => e,
+            None => {
+                let mut tx = pool.begin().await?;
+                let e = UserEmailModel::new(&mut tx, new_email).await?;
+                tx.commit().await?;
+                e
+            }
+        };
+
+        let current_email_obj = UserEmailModel::get_by_id(pool, user.email_id)
+            .await?
+            .ok_or(format!("User {} doesn't have an email", user.id))?;
+
+        let exp_hours = token_config.email_change_expiration_hours;
+
+        let raw_token = UserTokenService::issue(
+            pool,
+            user.id,
+            &UserTokenPurpose::ChangeEmail,
+            Duration::hours(exp_hours),
+            Some(new_email_obj.id),
+        )
+        .await?;
+
+        mailer.send(&MailMessage {
+            to: new_email_obj.value.clone(),
+            subject: "Confirm your new email address".to_string(),
+            body: format!(
+                "Use the link below to confirm your new email address. It expires in {} hours.\n\n{}/email/confirm?token={}",
+                exp_hours, mail_config.frontend_url, raw_token
+            ),
+        })?;
+
+        // let the current address know, in case this wasn't the owner
+        mailer.send(&MailMessage {
+            to: current_email_obj.value,
+            subject: "Your email address is being changed".to_string(),
+            body: format!(
+                "A request was made to change the email address of your account to {}.\nIf this wasn't you, change your password right away.",
+                new_email_obj.value
+            ),
+        })?;
+
+        Ok(())
+    }
+
+    // use an email change token of the user and move the user to the new email
+    // the new email counts as confirmed, and the user gets a new authid so other sessions end
+    pub async fn confirm_change(
+        pool: &Pool<MySql>,
+        user: &mut UserModel,
+        raw_token: &str,
+    ) -> Result<EmailChangeOutcome, Box<dyn std::error::Error>> {
+        let mut tx = pool.begin().await?;
+
+        let token = match UserTokenService::consume(
+            pool,
+            &mut tx,
+            &UserTokenPurpose::ChangeEmail,
+            raw_token,
+        )
+        .await?
+        {
+            None => return Ok(EmailChangeOutcome::InvalidToken),
+            Some(t) => t,
+        };
+
+        // token must belong to the caller
+        if token.user_id != user.id {
+            return Ok(EmailChangeOutcome::InvalidToken);
+        }
+
+        let new_email_id = token
+            .email_id
+            .ok_or(format!("Email change token {} has no email", token.id))?;
+
+        // someone else could have taken the email since the change was requested
+        if let Some(other) = UserModel::get_by_email_id(pool, new_email_id).await? {
+            if other.id != user.id {
+                return Ok(EmailChangeOutcome::EmailInUse);
+            }
+        }
+
+        user.update_email_id(&mut tx, new_email_id).await?;
+        user.update_datetime_confirmed(&mut tx, &Utc::now().naive_utc())
+            .await?;
+        let rotation = UserService::rotate_authid(pool, &mut tx, user).await?;
+
+        tx.commit().await?;
+
+        rotation.committed();
+
+        Ok(EmailChangeOutcome::Changed)
+    }
+}
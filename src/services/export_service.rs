@@ -0,0 +1,324 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use sqlx::{MySql, Pool};
+use zip::CompressionMethod;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use crate::config::app_config::{ExportConfig, ServerConfig};
+use crate::dtos::export_dto::{
+    ExportHandleData, ExportInviteData, ExportManifestData, ExportProfileData, ExportSessionData,
+};
+use crate::mailers::mailer::{MailMessage, Mailer};
+use crate::models::user_models::user_export_model::UserExportModel;
+use crate::models::user_models::user_handle_history_model::UserHandleHistoryModel;
+use crate::models::user_models::user_invite_model::UserInviteModel;
+use crate::models::user_models::user_profile_model::UserProfileModel;
+use crate::models::user_models::user_session_model::UserSessionModel;
+use crate::services::avatar_service::AvatarService;
+use crate::services::mfa_service::MfaService;
+use crate::stores::blob_store::BlobStore;
+use crate::utils::hash_utils::{constant_time_eq, hmac_sha256_hex};
+use crate::utils::string_utils::random_alphanumeric;
+
+// What asking for an export ended up doing
+pub enum ExportRequest {
+    Started(UserExportModel),    // new export, build it
+    InProgress(UserExportModel), // the user already has one being built
+}
+
+pub struct ExportService {}
+
+impl ExportService {
+    // start a new export for the user, unless one is still being built
+    pub async fn request(
+        pool: &Pool<MySql>,
+        export_config: &ExportConfig,
+        user_id: i64,
+    ) -> Result<ExportRequest, Box<dyn std::error::Error>> {
+        let now = Utc::now().naive_utc();
+
+        if let Some(export) =
+            UserExportModel::get_in_progress_by_user_id(pool, user_id, &now).await?
+        {
+            return Ok(ExportRequest::InProgress(export));
+        }
+
+        let mut tx = pool.begin().await?;
+        let export = UserExportModel::new(
+            &mut tx,
+            &random_alphanumeric(32),
+            user_id,
+            &(now + Duration::hours(export_config.retention_hours)),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(ExportRequest::Started(export))
+    }
+
+    // write the archive of the export and mail its download link to the user
+    // meant to run in the background - the export is marked failed if the archive can't be written
+    pub async fn build(
+        pool: &Pool<MySql>,
+        blob_store: &dyn BlobStore,
+        mailer: &dyn Mailer,
+        server_config: &ServerConfig,
+        export_config: &ExportConfig,
+        mut export: UserExportModel,
+    ) {
+        let email = match _write_archive(pool, blob_store, export_config, &export).await {
+            Err(e) => {
+                log::error!("Unable to build export {}. {}", export.id, e);
+
+                if let Err(e) = export.mark_failed(pool, &Utc::now().naive_utc()).await {
+                    log::error!("{}", e);
+                }
+                return;
+            }
+            Ok(email) => email,
+        };
+
+        if let Err(e) = export.mark_ready(pool, &Utc::now().naive_utc()).await {
+            log::error!("{}", e);
+            return;
+        }
+
+        // the link can also be fetched from the export's status, a lost email is not fatal
+        let (url, _) = ExportService::download_url(server_config, export_config, &export);
+        if let Err(e) = mailer.send(&MailMessage {
+            to: email,
+            subject: "Your data export is ready".to_string(),
+            body: format!(
+                "Use the link below to download a copy of your data. It expires in {} minutes, a new link can be requested until {}.\n\n{}",
+                export_config.link_expiration_minutes,
+                export.datetime_ttl.format("%Y-%m-%d %H:%M UTC"),
+                url
+            ),
+        }) {
+            log::error!("Unable to send export email. {}", e);
+        }
+    }
+
+    // signed link to download the archive of the export, and when it stops working
+    // the link carries its own proof so it works without an access token (e.g. from an email)
+    pub fn download_url(
+        server_config: &ServerConfig,
+        export_config: &ExportConfig,
+        export: &UserExportModel,
+    ) -> (String, NaiveDateTime) {
+        let expires = (Utc::now().naive_utc()
+            + Duration::minutes(export_config.link_expiration_minutes))
+        .min(export.datetime_ttl);
+        let expires_ts = expires.and_utc().timestamp();
+
+        let signature = _signature(export_config, &export.value, expires_ts);
+
+        (
+            format!(
+                "{}/api/users/me/export/{}/download?expires={}&signature={}",
+                server_config.public_url, export.value, expires_ts, signature
+            ),
+            expires,
+        )
+    }
+
+    // true if the link was signed by download_url for this export and hasn't expired yet
+    pub fn is_valid_download(
+        export_config: &ExportConfig,
+        export_value: &str,
+        expires: i64,
+        signature: &str,
+    ) -> bool {
+        let expires_at = match DateTime::from_timestamp(expires, 0) {
+            None => return false,
+            Some(dt) => dt,
+        };
+
+        if expires_at <= Utc::now() {
+            return false;
+        }
+
+        let expected = _signature(export_config, export_value, expires);
+        constant_time_eq(expected.as_bytes(), signature.as_bytes())
+    }
+
+    // where the archive of an export is written
+    pub fn archive_path(export_config: &ExportConfig, export: &UserExportModel) -> PathBuf {
+        PathBuf::from(&export_config.dir).join(format!("{}.zip", export.value))
+    }
+
+    // delete exports whose ttl has passed, archives first
+    // returns how many were deleted
+    pub async fn purge_expired(
+        pool: &Pool<MySql>,
+        export_config: &ExportConfig,
+        limit: u32,
+    ) -> Result<u64, Box<dyn std::error::Error>> {
+        let exports = UserExportModel::get_expired(pool, &Utc::now().naive_utc(), limit).await?;
+
+        for export in &exports {
+            let path = ExportService::archive_path(export_config, export);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            export.delete(pool).await?;
+        }
+
+        Ok(exports.len() as u64)
+    }
+}
+
+// gather everything stored about the user of the export into a zip of json files
+// only data that has a table yet is exported - board memberships, authored content and
+// audit events are to be added here along with their tables
+// returns the user's email so the caller can tell the user
+async fn _write_archive(
+    pool: &Pool<MySql>,
+    blob_store: &dyn BlobStore,
+    export_config: &ExportConfig,
+    export: &UserExportModel,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let profile = UserProfileModel::get_by_user_id(pool, export.user_id)
+        .await?
+        .ok_or(format!("Export {} has no user", export.id))?;
+    let email = profile.email.clone();
+    let pid = profile.pid.clone();
+
+    // the largest variant is all there is of the uploaded image, the original isn't kept
+    let avatar = match &profile.avatar {
+        None => None,
+        Some(value) => match AvatarService::variant_blob_key(value, "large") {
+            None => None,
+            Some(key) => blob_store.get(&key).await?,
+        },
+    };
+
+    let profile = ExportProfileData {
+        pid: profile.pid,
+        firstname: profile.firstname,
+        lastname: profile.lastname,
+        handle: profile.handle,
+        email: profile.email,
+        datetime_created: profile.datetime_created,
+        datetime_confirmed: profile.datetime_confirmed,
+        datetime_deactivated: profile.datetime_deactivated,
+        datetime_deleted: profile.datetime_deleted,
+        mfa_enabled: MfaService::is_enabled(pool, export.user_id).await?,
+    };
+
+    let sessions: Vec<ExportSessionData> = UserSessionModel::get_by_user_id(pool, export.user_id)
+        .await?
+        .into_iter()
+        .map(|s| ExportSessionData {
+            id: s.value,
+            user_agent: s.user_agent,
+            ip_address: s.ip_address,
+            datetime_created: s.datetime_created,
+            datetime_last_used: s.datetime_last_used,
+        })
+        .collect();
+
+    let handle_history: Vec<ExportHandleData> =
+        UserHandleHistoryModel::get_by_user_id(pool, export.user_id)
+            .await?
+            .into_iter()
+            .map(|h| ExportHandleData {
+                handle: h.value,
+                datetime_released: h.datetime_released,
+            })
+            .collect();
+
+    let invites: Vec<ExportInviteData> =
+        UserInviteModel::get_by_created_by_user_id(pool, export.user_id)
+            .await?
+            .into_iter()
+            .map(|i| ExportInviteData {
+                id: i.value,
+                email: i.email,
+                domain: i.domain,
+                max_uses: i.max_uses,
+                uses: i.uses,
+                datetime_ttl: i.datetime_ttl,
+                datetime_revoked: i.datetime_revoked,
+                datetime_created: i.datetime_created,
+            })
+            .collect();
+
+    let now = Utc::now().naive_utc();
+
+    let mut files: Vec<(String, Vec<u8>)> = vec![
+        (
+            "profile.json".to_string(),
+            serde_json::to_vec_pretty(&profile)?,
+        ),
+        (
+            "sessions.json".to_string(),
+            serde_json::to_vec_pretty(&sessions)?,
+        ),
+        (
+            "handle_history.json".to_string(),
+            serde_json::to_vec_pretty(&handle_history)?,
+        ),
+        (
+            "invites.json".to_string(),
+            serde_json::to_vec_pretty(&invites)?,
+        ),
+    ];
+    if let Some(avatar) = avatar {
+        files.push(("avatar.webp".to_string(), avatar));
+    }
+
+    let manifest = ExportManifestData {
+        user: pid,
+        datetime_generated: now,
+        files: files.iter().map(|(name, _)| name.to_string()).collect(),
+    };
+    files.insert(
+        0,
+        (
+            "manifest.json".to_string(),
+            serde_json::to_vec_pretty(&manifest)?,
+        ),
+    );
+
+    fs::create_dir_all(&export_config.dir)?;
+    _zip_files(
+        &ExportService::archive_path(export_config, export),
+        &files,
+        &now,
+    )?;
+
+    Ok(email)
+}
+
+// deflated zip of the (name, content) files, written to path
+fn _zip_files(
+    path: &Path,
+    files: &[(String, Vec<u8>)],
+    modified: &NaiveDateTime,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(zip::DateTime::try_from(*modified)?);
+
+    let mut archive = ZipWriter::new(fs::File::create(path)?);
+    for (name, content) in files {
+        archive.start_file(name.as_str(), options)?;
+        archive.write_all(content)?;
+    }
+    archive.finish()?;
+
+    Ok(())
+}
+
+// hex hmac of the export and the link's expiry, so neither can be changed
+fn _signature(export_config: &ExportConfig, export_value: &str, expires: i64) -> String {
+    hmac_sha256_hex(
+        &export_config.signing_secret,
+        &format!("{}:{}", export_value, expires),
+    )
+}
@@ -0,0 +1,187 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::{MySql, Pool};
+
+use crate::config::app_config::AccountConfig;
+use crate::models::user_models::user_handle_history_model::UserHandleHistoryModel;
+use crate::models::user_models::user_handle_model::UserHandleModel;
+use crate::models::user_models::user_profile_model::UserProfileModel;
+use crate::services::profile_service::ProfileService;
+
+// What asking for a new handle ended up doing
+pub enum HandleChange {
+    Changed(UserHandleModel),
+    Taken,                  // someone else has it, or released it too recently
+    TooSoon(NaiveDateTime), // earliest the user can change its handle again
+}
+
+// Who a handle points to
+pub enum HandleLookup {
+    Current(UserProfileModel), // the handle is the user's current one
+    Moved(String),             // an old handle of the user, with the user's current handle
+    NotFound,
+}
+
+pub struct HandleService {}
+
+impl HandleService {
+    // handles are unique regardless of casing, this is the form they are compared in
+    pub fn normalize(handle: &str) -> String {
+        handle.to_ascii_lowercase()
+    }
+
+    // true if the handle can be taken by the user - None for someone that has no account yet
+    // a handle is taken while it is someone's current one and for a while after it was released
+    pub async fn is_available(
+        pool: &Pool<MySql>,
+        account_config: &AccountConfig,
+        handle: &str,
+        user_id: Option<i64>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let normalized = HandleService::normalize(handle);
+
+        if let Some(current) = UserHandleModel::get_by_normalized(pool, &normalized).await? {
+            if Some(current.user_id) != user_id {
+                return Ok(false);
+            }
+        }
+
+        if let Some(released) = UserHandleHistoryModel::get_held_by_normalized(
+            pool,
+            &normalized,
+            &_redirect_cutoff(account_config),
+        )
+        .await?
+        {
+            if Some(released.user_id) != user_id {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    // give the user the handle, the old one is kept in the history and keeps pointing to the user for a while
+    // changing only the casing of the current handle is always allowed and is not recorded
+    pub async fn change(
+        pool: &Pool<MySql>,
+        account_config: &AccountConfig,
+        user_id: i64,
+        handle: &str,
+    ) -> Result<HandleChange, Box<dyn std::error::Error>> {
+        let normalized = HandleService::normalize(handle);
+        let now = Utc::now().naive_utc();
+
+        let current = UserHandleModel::get_by_user_id(pool, user_id).await?;
+
+        if let Some(mut current) = current {
+            if current.normalized == normalized {
+                let datetime_taken = current.datetime_taken;
+
+                let mut tx = pool.begin().await?;
+                current
+                    .update_value(&mut tx, handle, &normalized, &datetime_taken)
+                    .await?;
+                tx.commit().await?;
+
+                return Ok(HandleChange::Changed(current));
+            }
+
+            let datetime_next_change =
+                current.datetime_taken + Duration::days(account_config.handle_change_interval_days);
+            if datetime_next_change > now {
+                return Ok(HandleChange::TooSoon(datetime_next_change));
+            }
+
+            if !HandleService::is_available(pool, account_config, handle, Some(user_id)).await? {
+                return Ok(HandleChange::Taken);
+            }
+
+            let mut tx = pool.begin().await?;
+            UserHandleHistoryModel::new(
+                &mut tx,
+                user_id,
+                &current.value,
+                &current.normalized,
+                &now,
+            )
+            .await?;
+
+            // the unique index has the last word if someone took it since the check
+            match current
+                .update_value(&mut tx, handle, &normalized, &now)
+                .await
+            {
+                Err(e) if _is_unique_violation(&e) => return Ok(HandleChange::Taken),
+                Err(e) => return Err(e.into()),
+                Ok(_) => {}
+            }
+            tx.commit().await?;
+
+            return Ok(HandleChange::Changed(current));
+        }
+
+        if !HandleService::is_available(pool, account_config, handle, Some(user_id)).await? {
+            return Ok(HandleChange::Taken);
+        }
+
+        let mut tx = pool.begin().await?;
+        let created = match UserHandleModel::new(&mut tx, user_id, handle, &normalized).await {
+            Err(e) if _is_unique_violation(&e) => return Ok(HandleChange::Taken),
+            Err(e) => return Err(e.into()),
+            Ok(h) => h,
+        };
+        tx.commit().await?;
+
+        Ok(HandleChange::Changed(created))
+    }
+
+    // find the user a handle points to, either as its current handle or as a recently released one
+    // deactivated and deleted users are hidden - NotFound, same as a handle nobody has
+    pub async fn resolve(
+        pool: &Pool<MySql>,
+        account_config: &AccountConfig,
+        handle: &str,
+    ) -> Result<HandleLookup, Box<dyn std::error::Error>> {
+        let normalized = HandleService::normalize(handle);
+
+        if let Some(current) = UserHandleModel::get_by_normalized(pool, &normalized).await? {
+            return match UserProfileModel::get_by_user_id(pool, current.user_id).await? {
+                Some(profile) if ProfileService::is_visible(&profile) => {
+                    Ok(HandleLookup::Current(profile))
+                }
+                _ => Ok(HandleLookup::NotFound),
+            };
+        }
+
+        if let Some(released) = UserHandleHistoryModel::get_held_by_normalized(
+            pool,
+            &normalized,
+            &_redirect_cutoff(account_config),
+        )
+        .await?
+        {
+            match UserProfileModel::get_by_user_id(pool, released.user_id).await? {
+                Some(profile) if ProfileService::is_visible(&profile) => {
+                    if let Some(current_handle) = profile.handle {
+                        return Ok(HandleLookup::Moved(current_handle));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(HandleLookup::NotFound)
+    }
+}
+
+// handles released after this still point to their user
+fn _redirect_cutoff(account_config: &AccountConfig) -> NaiveDateTime {
+    Utc::now().naive_utc() - Duration::days(account_config.handle_redirect_days)
+}
+
+fn _is_unique_violation(e: &sqlx::error::Error) -> bool {
+    match e {
+        sqlx::error::Error::Database(db_err) => db_err.is_unique_violation(),
+        _ => false,
+    }
+}
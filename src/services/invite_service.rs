@@ -0,0 +1,100 @@
+use chrono::{Duration, Utc};
+use sqlx::{MySql, Pool};
+
+use crate::config::app_config::RegistrationConfig;
+use crate::models::user_models::user_invite_model::{InviteRestriction, UserInviteModel};
+use crate::models::user_models::user_model::UserModel;
+use crate::models::user_models::user_pid_model::UserPidModel;
+use crate::utils::hash_utils::sha256_hex;
+use crate::utils::string_utils::random_alphanumeric;
+
+pub struct InviteService {}
+
+impl InviteService {
+    // true if the user may create invites
+    // only users listed in REGISTRATION_INVITE_CREATOR_PIDS for now, this is the place for roles once there are any
+    pub async fn can_create(
+        pool: &Pool<MySql>,
+        registration_config: &RegistrationConfig,
+        user: &UserModel,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        if registration_config.invite_creator_pids.is_empty() {
+            return Ok(false);
+        }
+
+        let pid = UserPidModel::get_by_id(pool, user.pid_id)
+            .await?
+            .ok_or(format!("User {} has no pid", user.id))?;
+
+        Ok(registration_config
+            .invite_creator_pids
+            .iter()
+            .any(|p| *p == pid.value))
+    }
+
+    // create an invite of the user
+    // returns the raw code with it - only its hash is stored
+    pub async fn create(
+        pool: &Pool<MySql>,
+        registration_config: &RegistrationConfig,
+        created_by_user_id: i64,
+        restriction: &InviteRestriction,
+        max_uses: u32,
+        expiration_days: Option<i64>,
+    ) -> Result<(UserInviteModel, String), Box<dyn std::error::Error>> {
+        let raw_code = random_alphanumeric(24);
+        let datetime_ttl = Utc::now().naive_utc()
+            + Duration::days(expiration_days.unwrap_or(registration_config.invite_expiration_days));
+
+        let mut tx = pool.begin().await?;
+        let invite = UserInviteModel::new(
+            &mut tx,
+            &random_alphanumeric(32),
+            &sha256_hex(&raw_code),
+            created_by_user_id,
+            restriction,
+            max_uses,
+            &datetime_ttl,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok((invite, raw_code))
+    }
+
+    // the invite of the code, if it can be used to register the email right now
+    // None if the code is unknown, expired, revoked, used up, or locked to another email or domain
+    // the use itself is only counted when the user is created
+    pub async fn check(
+        pool: &Pool<MySql>,
+        raw_code: &str,
+        email: &str,
+    ) -> Result<Option<UserInviteModel>, Box<dyn std::error::Error>> {
+        let invite = match UserInviteModel::get_by_code(pool, &sha256_hex(raw_code)).await? {
+            None => return Ok(None),
+            Some(i) => i,
+        };
+
+        if invite.datetime_revoked.is_some()
+            || invite.datetime_ttl <= Utc::now().naive_utc()
+            || invite.uses >= invite.max_uses
+        {
+            return Ok(None);
+        }
+
+        if let Some(invite_email) = &invite.email {
+            if !invite_email.eq_ignore_ascii_case(email) {
+                return Ok(None);
+            }
+        }
+
+        if let Some(invite_domain) = &invite.domain {
+            let email_domain = email.rsplit_once('@').map(|(_, d)| d).unwrap_or("");
+            if !invite_domain.eq_ignore_ascii_case(email_domain) {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(invite))
+    }
+}
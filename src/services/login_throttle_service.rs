@@ -0,0 +1,111 @@
+use chrono::{Duration, Utc};
+
+use crate::config::app_config::LoginThrottleConfig;
+use crate::stores::login_attempt_store::LoginAttemptStore;
+use crate::utils::hash_utils::sha256_hex;
+
+pub struct LoginThrottleService {}
+
+impl LoginThrottleService {
+    // seconds the client has to wait before trying this email or ip again
+    // returns None when a login attempt is allowed
+    pub async fn retry_after(
+        store: &dyn LoginAttemptStore,
+        email: &str,
+        ip_address: Option<&str>,
+    ) -> Result<Option<i64>, Box<dyn std::error::Error>> {
+        let now = Utc::now().naive_utc();
+        let mut wait_seconds: i64 = 0;
+
+        for key in _keys(email, ip_address) {
+            if let Some(attempt) = store.get(&key).await? {
+                if let Some(blocked_until) = attempt.blocked_until {
+                    // round up so clients never retry a moment too early
+                    let remaining = (blocked_until - now).num_milliseconds();
+                    wait_seconds = wait_seconds.max((remaining + 999) / 1000);
+                }
+            }
+        }
+
+        if wait_seconds > 0 {
+            return Ok(Some(wait_seconds));
+        }
+
+        Ok(None)
+    }
+
+    // count a failed login against both the email and the ip
+    pub async fn record_failure(
+        store: &dyn LoginAttemptStore,
+        throttle_config: &LoginThrottleConfig,
+        email: &str,
+        ip_address: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = Utc::now().naive_utc();
+        let window_start = now - Duration::minutes(throttle_config.failure_window_minutes);
+
+        let email_key = _email_key(email);
+        let attempt = store.record_failure(&email_key, now, window_start).await?;
+        let block_for = _block_for(
+            throttle_config,
+            attempt.failures,
+            throttle_config.max_failures_per_email,
+        );
+        store.block_until(&email_key, now + block_for).await?;
+
+        if let Some(ip) = ip_address {
+            let ip_key = _ip_key(ip);
+            let attempt = store.record_failure(&ip_key, now, window_start).await?;
+            let block_for = _block_for(
+                throttle_config,
+                attempt.failures,
+                throttle_config.max_failures_per_ip,
+            );
+            store.block_until(&ip_key, now + block_for).await?;
+        }
+
+        Ok(())
+    }
+
+    // forget the failures of an email after a successful login
+    // the ip keeps its count so one valid account can't be used to reset it
+    pub async fn record_success(
+        store: &dyn LoginAttemptStore,
+        email: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        store.clear(&_email_key(email)).await
+    }
+}
+
+// keys are hashed so neither store keeps emails or ips in the clear
+fn _email_key(email: &str) -> String {
+    sha256_hex(&format!("email:{}", email.trim().to_lowercase()))
+}
+
+fn _ip_key(ip_address: &str) -> String {
+    sha256_hex(&format!("ip:{}", ip_address))
+}
+
+fn _keys(email: &str, ip_address: Option<&str>) -> Vec<String> {
+    let mut keys = vec![_email_key(email)];
+    if let Some(ip) = ip_address {
+        keys.push(_ip_key(ip));
+    }
+    keys
+}
+
+// how long to block a key after its nth failure
+fn _block_for(throttle_config: &LoginThrottleConfig, failures: u32, max_failures: u32) -> Duration {
+    if failures >= max_failures {
+        return Duration::minutes(throttle_config.lockout_minutes);
+    }
+
+    // 2^(failures - 1), capped so the shift can't overflow
+    let factor = 1i64 << failures.saturating_sub(1).min(30);
+    Duration::seconds(
+        throttle_config
+            .backoff_base_seconds
+            .saturating_mul(factor)
+            .min(throttle_config.backoff_max_seconds),
+    )
+}
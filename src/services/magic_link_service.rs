@@ -0,0 +1,100 @@
+use chrono::{Duration, Utc};
+use sqlx::{MySql, Pool};
+
+use crate::config::app_config::{MagicLinkConfig, MailConfig};
+use crate::mailers::mailer::{MailMessage, Mailer};
+use crate::models::user_models::user_email_model::UserEmailModel;
+use crate::models::user_models::user_model::UserModel;
+use crate::models::user_models::user_token_model::UserTokenPurpose;
+use crate::services::user_token_service::UserTokenService;
+
+pub struct MagicLinkService {}
+
+impl MagicLinkService {
+    // mail a single-use sign-in link if the email belongs to an active user
+    // does nothing otherwise, callers must not tell the difference to the client
+    pub async fn send(
+        pool: &Pool<MySql>,
+        mailer: &dyn Mailer,
+        mail_config: &MailConfig,
+        magic_link_config: &MagicLinkConfig,
+        email: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let user_email = match UserEmailModel::get_by_value(pool, email).await? {
+            None => return Ok(()),
+            Some(e) => e,
+        };
+
+        let user = match UserModel::get_by_email_id(pool, user_email.id).await? {
+            None => return Ok(()),
+            Some(u) => u,
+        };
+
+        // deactivated accounts come back through the reactivation link instead
+        if user.datetime_deactivated.is_some() || user.datetime_deleted.is_some() {
+            return Ok(());
+        }
+
+        let exp_minutes = magic_link_config.expiration_minutes;
+
+        // tied to the email so the link stops working if the user changes it
+        let raw_token = UserTokenService::issue(
+            pool,
+            user.id,
+            &UserTokenPurpose::MagicLogin,
+            Duration::minutes(exp_minutes),
+            Some(user_email.id),
+        )
+        .await?;
+
+        mailer.send(&MailMessage {
+            to: user_email.value,
+            subject: "Your sign-in link".to_string(),
+            body: format!(
+                "Use the link below to sign in. It expires in {} minutes and works once.\nIf you did not ask for this, you can ignore this email.\n\n{}/login/magic?token={}",
+                exp_minutes, mail_config.frontend_url, raw_token
+            ),
+        })?;
+
+        Ok(())
+    }
+
+    // use a sign-in token and get its user
+    // following the link proves the user reads the email, so an unconfirmed email is confirmed along the way
+    // returns None if the token is unknown, expired or already used, or the user's email changed since
+    pub async fn consume(
+        pool: &Pool<MySql>,
+        raw_token: &str,
+    ) -> Result<Option<UserModel>, Box<dyn std::error::Error>> {
+        let mut tx = pool.begin().await?;
+
+        let token = match UserTokenService::consume(
+            pool,
+            &mut tx,
+            &UserTokenPurpose::MagicLogin,
+            raw_token,
+        )
+        .await?
+        {
+            None => return Ok(None),
+            Some(t) => t,
+        };
+
+        let mut user = UserModel::get_by_id(pool, token.user_id)
+            .await?
+            .ok_or(format!("Token {} has no user", token.id))?;
+
+        if token.email_id != Some(user.email_id) {
+            return Ok(None);
+        }
+
+        if user.datetime_confirmed.is_none() {
+            user.update_datetime_confirmed(&mut tx, &Utc::now().naive_utc())
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(user))
+    }
+}
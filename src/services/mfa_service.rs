@@ -0,0 +1,255 @@
+use chrono::{Duration, Utc};
+use sqlx::{MySql, Pool, Transaction};
+
+use crate::config::app_config::{LoginThrottleConfig, MfaConfig};
+
+use crate::models::user_models::user_email_model::UserEmailModel;
+use crate::models::user_models::user_mfa_model::UserMfaModel;
+use crate::models::user_models::user_mfa_recovery_code_model::UserMfaRecoveryCodeModel;
+use crate::models::user_models::user_model::UserModel;
+use crate::models::user_models::user_token_model::{UserTokenModel, UserTokenPurpose};
+use crate::services::login_throttle_service::LoginThrottleService;
+use crate::services::user_token_service::UserTokenService;
+use crate::stores::login_attempt_store::LoginAttemptStore;
+use crate::utils::hash_utils::sha256_hex;
+use crate::utils::string_utils::random_alphanumeric;
+use crate::utils::totp_utils;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+// How the second step of a 2FA login went
+pub enum MfaLoginOutcome {
+    InvalidToken,    // pending token is unknown, expired or already used
+    RetryAfter(i64), // email or ip is backing off or locked out, seconds to wait
+    WrongCode,       // counted as a failed login
+    Completed {
+        user: UserModel,
+        reactivate: bool, // the password step asked to bring the inactive account back
+    },
+}
+
+pub struct MfaService {}
+
+impl MfaService {
+    // true if the user has a verified authenticator
+    pub async fn is_enabled(
+        pool: &Pool<MySql>,
+        user_id: i64,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mfa = UserMfaModel::get_by_user_id(pool, user_id).await?;
+        Ok(mfa.is_some_and(|m| m.datetime_enabled.is_some()))
+    }
+
+    // create a new secret for the user
+    // an earlier unverified enrollment is replaced
+    // returns None if 2FA is already enabled, otherwise the secret and its otpauth uri
+    pub async fn start_enrollment(
+        pool: &Pool<MySql>,
+        mfa_config: &MfaConfig,
+        user: &UserModel,
+    ) -> Result<Option<(String, String)>, Box<dyn std::error::Error>> {
+        if let Some(mfa) = UserMfaModel::get_by_user_id(pool, user.id).await? {
+            if mfa.datetime_enabled.is_some() {
+                return Ok(None);
+            }
+        }
+
+        let user_email = UserEmailModel::get_by_id(pool, user.email_id)
+            .await?
+            .ok_or(format!("User {} has no email", user.id))?;
+
+        let secret = totp_utils::generate_secret();
+
+        let mut tx = pool.begin().await?;
+        UserMfaModel::delete_by_user_id(&mut tx, user.id).await?;
+        UserMfaModel::new(&mut tx, user.id, &secret).await?;
+        tx.commit().await?;
+
+        let uri = totp_utils::otpauth_uri(&mfa_config.issuer, &user_email.value, &secret);
+
+        Ok(Some((secret, uri)))
+    }
+
+    // verify the first code from the authenticator app and turn 2FA on
+    // returns None if there is no pending enrollment or the code is wrong
+    // otherwise the raw recovery codes - only their hashes are stored
+    pub async fn confirm_enrollment(
+        pool: &Pool<MySql>,
+        user_id: i64,
+        code: &str,
+    ) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+        let mut mfa = match UserMfaModel::get_by_user_id(pool, user_id).await? {
+            Some(m) if m.datetime_enabled.is_none() => m,
+            _ => return Ok(None),
+        };
+
+        let step = match totp_utils::verify_code(&mfa.secret, code, Utc::now().timestamp())? {
+            None => return Ok(None),
+            Some(s) => s,
+        };
+
+        let mut tx = pool.begin().await?;
+
+        if !mfa.update_last_used_step(&mut tx, step).await? {
+            return Ok(None);
+        }
+        mfa.update_datetime_enabled(&mut tx, &Utc::now().naive_utc())
+            .await?;
+
+        UserMfaRecoveryCodeModel::delete_by_user_id(&mut tx, user_id).await?;
+        let mut recovery_codes: Vec<String> = Vec::with_capacity(RECOVERY_CODE_COUNT);
+        for _ in 0..RECOVERY_CODE_COUNT {
+            let raw_code = _generate_recovery_code();
+            UserMfaRecoveryCodeModel::new(
+                &mut tx,
+                user_id,
+                &sha256_hex(&_normalise_recovery_code(&raw_code)),
+            )
+            .await?;
+            recovery_codes.push(raw_code);
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(recovery_codes))
+    }
+
+    // check a code from the authenticator app or a recovery code
+    // the code is spent inside the caller's transaction so it can't be used twice
+    // returns false if 2FA isn't enabled or the code doesn't match
+    pub async fn check_code(
+        pool: &Pool<MySql>,
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+        code: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut mfa = match UserMfaModel::get_by_user_id(pool, user_id).await? {
+            Some(m) if m.datetime_enabled.is_some() => m,
+            _ => return Ok(false),
+        };
+
+        if let Some(step) = totp_utils::verify_code(&mfa.secret, code, Utc::now().timestamp())? {
+            return Ok(mfa.update_last_used_step(tx, step).await?);
+        }
+
+        let hashed_code = sha256_hex(&_normalise_recovery_code(code));
+        match UserMfaRecoveryCodeModel::get_unused_by_value(pool, user_id, &hashed_code).await? {
+            None => Ok(false),
+            Some(rc) => Ok(rc.mark_used(tx, &Utc::now().naive_utc()).await?),
+        }
+    }
+
+    // turn 2FA off and remove the secret and recovery codes
+    pub async fn disable(
+        tx: &mut Transaction<'_, MySql>,
+        user_id: i64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        UserMfaRecoveryCodeModel::delete_by_user_id(tx, user_id).await?;
+        UserMfaModel::delete_by_user_id(tx, user_id).await?;
+
+        Ok(())
+    }
+
+    // first step of a 2FA login - password was correct
+    // reactivate is carried by the token, an inactive account only comes back once the code matched
+    // returns the raw short-lived token the client sends back with the code
+    pub async fn start_login(
+        pool: &Pool<MySql>,
+        mfa_config: &MfaConfig,
+        user_id: i64,
+        reactivate: bool,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let purpose = match reactivate {
+            true => UserTokenPurpose::MfaPendingReactivate,
+            false => UserTokenPurpose::MfaPending,
+        };
+
+        UserTokenService::issue(
+            pool,
+            user_id,
+            &purpose,
+            Duration::minutes(mfa_config.pending_token_expiration_minutes),
+            None,
+        )
+        .await
+    }
+
+    // second step of a 2FA login
+    // the pending token is spent on every attempt, a wrong code means logging in again
+    // wrong codes count as failed logins against the user's email and the ip, like a wrong password
+    // and the email's failures are only forgotten once the code matched
+    pub async fn complete_login(
+        pool: &Pool<MySql>,
+        login_attempts: &dyn LoginAttemptStore,
+        throttle_config: &LoginThrottleConfig,
+        raw_token: &str,
+        code: &str,
+        ip_address: Option<&str>,
+    ) -> Result<MfaLoginOutcome, Box<dyn std::error::Error>> {
+        let mut tx = pool.begin().await?;
+
+        let mut reactivate = false;
+        let mut token =
+            UserTokenService::consume(pool, &mut tx, &UserTokenPurpose::MfaPending, raw_token)
+                .await?;
+        if token.is_none() {
+            reactivate = true;
+            token = UserTokenService::consume(
+                pool,
+                &mut tx,
+                &UserTokenPurpose::MfaPendingReactivate,
+                raw_token,
+            )
+            .await?;
+        }
+        let token: UserTokenModel = match token {
+            None => return Ok(MfaLoginOutcome::InvalidToken),
+            Some(t) => t,
+        };
+
+        let user = UserModel::get_by_id(pool, token.user_id)
+            .await?
+            .ok_or(format!("Token {} has no user", token.id))?;
+        let user_email = UserEmailModel::get_by_id(pool, user.email_id)
+            .await?
+            .ok_or(format!("User {} has no email", user.id))?;
+
+        // a token handed out before the lockout started doesn't get around it
+        if let Some(retry_after) =
+            LoginThrottleService::retry_after(login_attempts, &user_email.value, ip_address).await?
+        {
+            tx.commit().await?;
+            return Ok(MfaLoginOutcome::RetryAfter(retry_after));
+        }
+
+        let matched = Self::check_code(pool, &mut tx, user.id, code).await?;
+
+        // commit either way so the pending token stays spent
+        tx.commit().await?;
+
+        if !matched {
+            LoginThrottleService::record_failure(
+                login_attempts,
+                throttle_config,
+                &user_email.value,
+                ip_address,
+            )
+            .await?;
+            return Ok(MfaLoginOutcome::WrongCode);
+        }
+
+        LoginThrottleService::record_success(login_attempts, &user_email.value).await?;
+
+        Ok(MfaLoginOutcome::Completed { user, reactivate })
+    }
+}
+
+// e.g. "k3f9a-0zq7m" - lowercase so it's easy to type
+fn _generate_recovery_code() -> String {
+    let raw = random_alphanumeric(10).to_lowercase();
+    format!("{}-{}", &raw[..5], &raw[5..])
+}
+
+fn _normalise_recovery_code(code: &str) -> String {
+    code.trim().to_lowercase().replace('-', "")
+}
@@ -1,4 +1,19 @@
 // pub mod auth_service;
 // pub mod board_service;
+pub mod account_service;
+pub mod auth_service;
+pub mod avatar_service;
+pub mod confirmation_service;
+pub mod email_change_service;
+pub mod export_service;
+pub mod handle_service;
+pub mod invite_service;
+pub mod login_throttle_service;
+pub mod magic_link_service;
+pub mod mfa_service;
+pub mod password_service;
+pub mod profile_service;
+pub mod refresh_token_service;
+pub mod session_service;
 pub mod user_service;
-pub mod auth_service;
\ No newline at end of file
+pub mod user_token_service;
@@ -0,0 +1,104 @@
+use chrono::Duration;
+use sqlx::{MySql, Pool};
+
+use crate::config::app_config::{MailConfig, TokenConfig};
+use crate::mailers::mailer::{MailMessage, Mailer};
+use crate::models::user_models::user_email_model::UserEmailModel;
+use crate::models::user_models::user_model::UserModel;
+use crate::models::user_models::user_token_model::UserTokenPurpose;
+use crate::services::user_service::UserService;
+use crate::services::user_token_service::UserTokenService;
+
+pub struct PasswordService {}
+
+impl PasswordService {
+    // mail a password reset link if the email belongs to a user
+    // does nothing when it doesn't, callers must not tell the difference to the client
+    pub async fn send_password_reset(
+        pool: &Pool<MySql>,
+        mailer: &dyn Mailer,
+        mail_config: &MailConfig,
+        token_config: &TokenConfig,
+        email: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let user_email = match UserEmailModel::get_by_value(pool, email).await? {
+            None => return Ok(()),
+            Some(e) => e,
+        };
+
+        let user = match UserModel::get_by_email_id(pool, user_email.id).await? {
+            None => return Ok(()),
+            Some(u) => u,
+        };
+
+        let exp_minutes = token_config.password_reset_expiration_minutes;
+
+        let raw_token = UserTokenService::issue(
+            pool,
+            user.id,
+            &UserTokenPurpose::ResetPassword,
+            Duration::minutes(exp_minutes),
+            None,
+        )
+        .await?;
+
+        mailer.send(&MailMessage {
+            to: user_email.value,
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Use the link below to set a new password. It expires in {} minutes.\nIf you did not ask for this, you can ignore this email.\n\n{}/password/reset?token={}",
+                exp_minutes, mail_config.frontend_url, raw_token
+            ),
+        })?;
+
+        Ok(())
+    }
+
+    // use a reset token, set the new password and rotate the user's authid
+    // returns None if the token is unknown, expired or already used
+    pub async fn reset_password(
+        pool: &Pool<MySql>,
+        raw_token: &str,
+        new_password: &str,
+    ) -> Result<Option<UserModel>, Box<dyn std::error::Error>> {
+        let mut tx = pool.begin().await?;
+
+        let token = match UserTokenService::consume(
+            pool,
+            &mut tx,
+            &UserTokenPurpose::ResetPassword,
+            raw_token,
+        )
+        .await?
+        {
+            None => return Ok(None),
+            Some(t) => t,
+        };
+
+        let mut user = UserModel::get_by_id(pool, token.user_id)
+            .await?
+            .ok_or(format!("Token {} has no user", token.id))?;
+
+        let rotation = UserService::update_password(pool, &mut tx, &mut user, new_password).await?;
+
+        tx.commit().await?;
+        rotation.committed();
+
+        Ok(Some(user))
+    }
+
+    // set the new password of an authenticated user
+    // the user gets a new authid, the caller issues new tokens for it to stay logged in
+    pub async fn change_password(
+        pool: &Pool<MySql>,
+        user: &mut UserModel,
+        new_password: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tx = pool.begin().await?;
+        let rotation = UserService::update_password(pool, &mut tx, user, new_password).await?;
+        tx.commit().await?;
+        rotation.committed();
+
+        Ok(())
+    }
+}
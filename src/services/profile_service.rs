@@ -0,0 +1,95 @@
+use sqlx::{MySql, Pool};
+
+use crate::config::app_config::ServerConfig;
+use crate::dtos::profile_dto::PublicProfileResponseData;
+
+use crate::models::user_models::user_model::UserModel;
+use crate::models::user_models::user_name_model::UserNameModel;
+use crate::models::user_models::user_profile_model::UserProfileModel;
+use crate::services::avatar_service::AvatarService;
+
+pub struct ProfileService {}
+
+impl ProfileService {
+    // profile of a user as anyone may see it
+    // deactivated users and users that asked for deletion are hidden - None, same as a user that doesn't exist
+    pub async fn get_public(
+        pool: &Pool<MySql>,
+        pid_value: &str,
+    ) -> Result<Option<UserProfileModel>, Box<dyn std::error::Error>> {
+        let profile = match UserProfileModel::get_by_pid_value(pool, pid_value).await? {
+            None => return Ok(None),
+            Some(p) => p,
+        };
+
+        if !ProfileService::is_visible(&profile) {
+            return Ok(None);
+        }
+
+        Ok(Some(profile))
+    }
+
+    // false for users that are deactivated or asked for deletion, they are not shown to anyone else
+    pub fn is_visible(profile: &UserProfileModel) -> bool {
+        profile.datetime_deactivated.is_none() && profile.datetime_deleted.is_none()
+    }
+
+    // what anyone gets to see of the profile
+    pub fn to_public(
+        server_config: &ServerConfig,
+        profile: UserProfileModel,
+    ) -> PublicProfileResponseData {
+        PublicProfileResponseData {
+            pid: profile.pid,
+            firstname: profile.firstname,
+            lastname: profile.lastname,
+            handle: profile.handle,
+            avatar: profile
+                .avatar
+                .map(|a| AvatarService::urls(server_config, &a)),
+            datetime_created: profile.datetime_created,
+        }
+    }
+
+    // change the firstname and/or lastname of the user
+    // name rows are shared between users, so the user is pointed to other rows instead of changing them
+    pub async fn update_names(
+        pool: &Pool<MySql>,
+        user: &mut UserModel,
+        firstname: Option<&str>,
+        lastname: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let old_firstname_id = user.firstname_id;
+        let old_lastname_id = user.lastname_id;
+
+        let mut tx = pool.begin().await?;
+
+        let firstname_id = match firstname {
+            None => old_firstname_id,
+            Some(value) => match UserNameModel::get_by_value(pool, value).await? {
+                Some(f) => f.id,
+                None => UserNameModel::new(&mut tx, value).await?.id,
+            },
+        };
+
+        let lastname_id = match lastname {
+            None => old_lastname_id,
+            Some(value) if Some(value) == firstname => firstname_id,
+            Some(value) => match UserNameModel::get_by_value(pool, value).await? {
+                Some(l) => l.id,
+                None => UserNameModel::new(&mut tx, value).await?.id,
+            },
+        };
+
+        user.update_name_ids(&mut tx, firstname_id, lastname_id)
+            .await?;
+
+        // names nobody has anymore are not kept around
+        UserNameModel::delete_if_unused(&mut tx, old_firstname_id).await?;
+        UserNameModel::delete_if_unused(&mut tx, old_lastname_id).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use chrono::Utc;
+use sqlx::{MySql, Pool};
+
+use crate::models::refresh_token_family_models::refresh_token_family_model::RefreshTokenFamilyModel;
+use crate::models::user_models::user_authid_model::UserAuthidModel;
+use crate::models::user_models::user_model::UserModel;
+use crate::models::user_models::user_session_model::UserSessionModel;
+use crate::services::user_service::UserService;
+use crate::utils::client_utils::ClientInfo;
+use crate::utils::jwt_utils::Claims;
+use crate::utils::jwt_utils::JwtKeys;
+use crate::utils::jwt_utils::generate_access_token;
+use crate::utils::jwt_utils::generate_refresh_token;
+use crate::utils::string_utils::random_alphanumeric;
+
+pub enum RefreshRotation {
+    Rotated(String), // next refresh token of the family
+    Invalid,         // no family, unknown or revoked family, or family of another user
+    Reused,          // token was already rotated out - family has been revoked
+}
+
+pub struct RefreshTokenService {}
+
+impl RefreshTokenService {
+    // start a new family for a fresh login and return its first refresh token
+    // the family is recorded as a session of the user along with the client it was issued to
+    pub async fn start_family(
+        pool: &Pool<MySql>,
+        jwt_keys: &JwtKeys,
+        user_id: i64,
+        auth_identity_value: &str,
+        client: &ClientInfo,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let family_value = random_alphanumeric(32);
+        let jti = random_alphanumeric(32);
+
+        let mut tx = pool.begin().await?;
+        let family = RefreshTokenFamilyModel::new(&mut tx, &family_value, user_id, &jti).await?;
+        UserSessionModel::new(
+            &mut tx,
+            &random_alphanumeric(32),
+            user_id,
+            family.id,
+            client.user_agent.as_deref(),
+            client.ip_address.as_deref(),
+            &Utc::now().naive_utc(),
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(generate_refresh_token(
+            jwt_keys,
+            auth_identity_value,
+            &family_value,
+            &jti,
+        )?)
+    }
+
+    // tokens for a fresh login of the user - an access token and the first refresh token of a new family
+    // issued for the user's current authid, so after rotating it only call this once that is committed
+    // returns (access token, refresh token)
+    pub async fn start_session(
+        pool: &Pool<MySql>,
+        jwt_keys: &JwtKeys,
+        user: &UserModel,
+        client: &ClientInfo,
+    ) -> Result<(String, String), Box<dyn std::error::Error>> {
+        let user_authid = UserAuthidModel::get_by_id(pool, user.authid_id)
+            .await?
+            .ok_or(format!("User {} doesn't have authid", user.id))?;
+
+        let access_token = generate_access_token(jwt_keys, &user_authid.value)?;
+        let refresh_token =
+            Self::start_family(pool, jwt_keys, user.id, &user_authid.value, client).await?;
+
+        Ok((access_token, refresh_token))
+    }
+
+    // swap a valid refresh token for the next one of its family
+    // also marks the family's session as used by the client
+    pub async fn rotate(
+        pool: &Pool<MySql>,
+        jwt_keys: &JwtKeys,
+        user: &UserModel,
+        claims: &Claims,
+        client: &ClientInfo,
+        rotate_authid_on_reuse: bool,
+    ) -> Result<RefreshRotation, Box<dyn std::error::Error>> {
+        let family_value = match &claims.fam {
+            None => return Ok(RefreshRotation::Invalid),
+            Some(f) => f,
+        };
+
+        let mut family = match RefreshTokenFamilyModel::get_by_value(pool, family_value).await? {
+            None => return Ok(RefreshRotation::Invalid),
+            Some(f) => f,
+        };
+
+        if family.user_id != user.id || family.datetime_revoked.is_some() {
+            return Ok(RefreshRotation::Invalid);
+        }
+
+        // token of the family but not the latest one - someone is replaying an old token
+        if family.current_jti != claims.jti {
+            RefreshTokenService::handle_reuse(pool, claims, rotate_authid_on_reuse).await?;
+            return Ok(RefreshRotation::Reused);
+        }
+
+        let new_jti = random_alphanumeric(32);
+
+        let mut tx = pool.begin().await?;
+        if !family.update_current_jti(&mut tx, &new_jti).await? {
+            // another request rotated this same token first
+            tx.rollback().await?;
+            RefreshTokenService::handle_reuse(pool, claims, rotate_authid_on_reuse).await?;
+            return Ok(RefreshRotation::Reused);
+        }
+
+        if let Some(mut session) = UserSessionModel::get_by_family_id(pool, family.id).await? {
+            session
+                .update_last_used(
+                    &mut tx,
+                    client.user_agent.as_deref(),
+                    client.ip_address.as_deref(),
+                    &Utc::now().naive_utc(),
+                )
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(RefreshRotation::Rotated(generate_refresh_token(
+            jwt_keys,
+            &claims.sub,
+            family_value,
+            &new_jti,
+        )?))
+    }
+
+    // a refresh token that was already rotated out or revoked has been presented again
+    // revoke its whole family, and rotate the user's authid as well if rotate_authid is set
+    // families that are already revoked are left alone so a replayed logout doesn't escalate
+    pub async fn handle_reuse(
+        pool: &Pool<MySql>,
+        claims: &Claims,
+        rotate_authid: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let family_value = match &claims.fam {
+            None => return Ok(()),
+            Some(f) => f,
+        };
+
+        let mut family = match RefreshTokenFamilyModel::get_by_value(pool, family_value).await? {
+            None => return Ok(()),
+            Some(f) => f,
+        };
+
+        if family.datetime_revoked.is_some() {
+            return Ok(());
+        }
+
+        log::warn!(
+            "Refresh token reuse detected. Revoking family {} of user {}",
+            family.id,
+            family.user_id
+        );
+
+        let mut tx = pool.begin().await?;
+        family.revoke(&mut tx, &Utc::now().naive_utc()).await?;
+        tx.commit().await?;
+
+        if rotate_authid {
+            if let Some(mut user) = UserModel::get_by_id(pool, family.user_id).await? {
+                UserService::update_user_authid(pool, &mut user).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // end the family of a refresh token, e.g. on logout
+    pub async fn revoke_family(
+        pool: &Pool<MySql>,
+        claims: &Claims,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let family_value = match &claims.fam {
+            None => return Ok(()),
+            Some(f) => f,
+        };
+
+        if let Some(mut family) = RefreshTokenFamilyModel::get_by_value(pool, family_value).await? {
+            let mut tx = pool.begin().await?;
+            family.revoke(&mut tx, &Utc::now().naive_utc()).await?;
+            tx.commit().await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use chrono::Utc;
+use sqlx::{MySql, Pool};
+
+use crate::models::refresh_token_family_models::refresh_token_family_model::RefreshTokenFamilyModel;
+use crate::models::user_models::user_session_model::UserSessionModel;
+
+pub struct SessionService {}
+
+impl SessionService {
+    // end one session of the user by revoking its refresh token family
+    // the device's access token keeps working until it expires, it just can't be refreshed
+    // returns false if the user has no such session
+    pub async fn revoke(
+        pool: &Pool<MySql>,
+        user_id: i64,
+        session_value: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let session = match UserSessionModel::get_by_value(pool, session_value).await? {
+            None => return Ok(false),
+            Some(s) => s,
+        };
+
+        if session.user_id != user_id {
+            return Ok(false);
+        }
+
+        let mut family = match RefreshTokenFamilyModel::get_by_id(pool, session.family_id).await? {
+            None => return Ok(false),
+            Some(f) => f,
+        };
+
+        if family.datetime_revoked.is_some() {
+            return Ok(false);
+        }
+
+        let mut tx = pool.begin().await?;
+        family.revoke(&mut tx, &Utc::now().naive_utc()).await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+}
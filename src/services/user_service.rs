@@ -1,21 +1,48 @@
 use crate::dtos::register_dto::RegisterRequestData;
+use crate::models::refresh_token_family_models::refresh_token_family_model::RefreshTokenFamilyModel;
 use crate::models::user_models::user_authid_model::UserAuthidModel;
 use crate::models::user_models::user_email_model::UserEmailModel;
+use crate::models::user_models::user_handle_model::UserHandleModel;
+use crate::models::user_models::user_invite_model::UserInviteModel;
+use crate::models::user_models::user_invite_use_model::UserInviteUseModel;
 use crate::models::user_models::user_model::UserModel;
 use crate::models::user_models::user_name_model::UserNameModel;
 use crate::models::user_models::user_pid_model::UserPidModel;
+use crate::services::auth_service::AuthService;
+use crate::services::handle_service::HandleService;
 use crate::utils::bcrypt_utils::make_hash;
 use crate::utils::string_utils::random_alphanumeric;
 
 // use chrono::{Duration, Utc};
-use sqlx::{MySql, Pool};
+use chrono::Utc;
+use sqlx::{MySql, Pool, Transaction};
+
+// A new authid that is part of a transaction not committed yet
+// committed() has to be called once it is, so lookups stop resolving the old authid from the cache
+#[must_use]
+pub struct AuthidRotation {
+    authid: UserAuthidModel,
+    old_value: Option<String>,
+}
+
+impl AuthidRotation {
+    // returns the new authid
+    pub fn committed(self) -> UserAuthidModel {
+        if let Some(old_value) = &self.old_value {
+            AuthService::forget_authid(old_value);
+        }
+        self.authid
+    }
+}
 
 pub struct UserService {}
 
 impl UserService {
+    // invite_id is the invite the user registers with, checked by the caller - its use is counted here
     pub async fn create_user(
         pool: &Pool<MySql>,
         data: &RegisterRequestData,
+        invite_id: Option<i64>,
     ) -> Result<UserModel, Box<dyn std::error::Error>> {
         // create transaction instance
         let mut tx = pool.begin().await?;
@@ -36,28 +63,7 @@ impl UserService {
         };
 
         // create authid
-        let user_authid_obj: UserAuthidModel;
-        let mut authid_counter: i32 = 0;
-        loop {
-            // will only try 5 times
-            if authid_counter == 5 {
-                let err_msg = String::from(
-                    "Error while creating UserAuthidModel. Try limit has been reached",
-                );
-                log::error!("{}", err_msg);
-                return Err(err_msg.into());
-            }
-
-            authid_counter += 1;
-            let authid_value = random_alphanumeric(32);
-            match UserAuthidModel::get_by_value(&pool, &authid_value).await? {
-                Some(_) => continue,
-                None => {
-                    user_authid_obj = UserAuthidModel::new(&mut tx, &authid_value).await?;
-                    break;
-                }
-            }
-        }
+        let user_authid_obj: UserAuthidModel = _create_authid(&pool, &mut tx).await?;
 
         // create pid
         let user_pid_obj: UserPidModel;
@@ -109,11 +115,32 @@ impl UserService {
         )
         .await?;
 
+        // create handle - availability is for the caller to check, the unique index catches a race
+        UserHandleModel::new(
+            &mut tx,
+            user_obj.id,
+            &data.handle,
+            &HandleService::normalize(&data.handle),
+        )
+        .await?;
+
+        // use the invite - its last use may have been taken since the caller checked it
+        if let Some(invite_id) = invite_id {
+            if !UserInviteModel::use_once(&mut tx, invite_id, &Utc::now().naive_utc()).await? {
+                let err_msg = format!("Invite {} can't be used anymore", invite_id);
+                log::error!("{}", err_msg);
+                return Err(err_msg.into());
+            }
+            UserInviteUseModel::new(&mut tx, invite_id, user_obj.id).await?;
+        }
+
         tx.commit().await?;
 
         Ok(user_obj)
     }
 
+    // give the user a new authid and save it
+    // logs the user out on all devices since every token carries the old authid as sub
     pub async fn update_user_authid(
         pool: &Pool<MySql>,
         user: &mut UserModel,
@@ -121,34 +148,70 @@ impl UserService {
         // create transaction instance
         let mut tx = pool.begin().await?;
 
-        // create authid
-        let user_authid_obj: UserAuthidModel;
-        let mut authid_counter: i32 = 0;
-        loop {
-            // will only try 5 times
-            if authid_counter == 5 {
-                let err_msg = String::from(
-                    "Error while creating UserAuthidModel. Try limit has been reached",
-                );
-                log::error!("{}", err_msg);
-                return Err(err_msg.into());
-            }
+        let rotation = UserService::rotate_authid(pool, &mut tx, user).await?;
 
-            authid_counter += 1;
-            let authid_value = random_alphanumeric(32);
-            match UserAuthidModel::get_by_value(&pool, &authid_value).await? {
-                Some(_) => continue,
-                None => {
-                    user_authid_obj = UserAuthidModel::new(&mut tx, &authid_value).await?;
-                    break;
-                }
-            }
-        }
+        tx.commit().await?;
 
-        user.authid_id = user_authid_obj.id;
+        Ok(rotation.committed())
+    }
 
-        tx.commit().await?;
+    // set a new password for the user and give the user a new authid
+    // the new authid invalidates every token issued from the old one
+    pub async fn update_password(
+        pool: &Pool<MySql>,
+        tx: &mut Transaction<'_, MySql>,
+        user: &mut UserModel,
+        new_password: &str,
+    ) -> Result<AuthidRotation, Box<dyn std::error::Error>> {
+        let hashed_pw = make_hash(new_password)?;
+        user.update_password(tx, &hashed_pw).await?;
 
-        Ok(user_authid_obj)
+        UserService::rotate_authid(pool, tx, user).await
+    }
+
+    // give the user a brand new authid inside the given transaction
+    // every token issued from the old authid stops working once committed
+    // refresh token families of the user are revoked along with it
+    pub async fn rotate_authid(
+        pool: &Pool<MySql>,
+        tx: &mut Transaction<'_, MySql>,
+        user: &mut UserModel,
+    ) -> Result<AuthidRotation, Box<dyn std::error::Error>> {
+        let old_authid = UserAuthidModel::get_by_id(pool, user.authid_id).await?;
+
+        let user_authid_obj = _create_authid(pool, tx).await?;
+        user.update_authid_id(tx, user_authid_obj.id).await?;
+        RefreshTokenFamilyModel::revoke_by_user_id(tx, user.id, &Utc::now().naive_utc()).await?;
+
+        Ok(AuthidRotation {
+            authid: user_authid_obj,
+            old_value: old_authid.map(|a| a.value),
+        })
+    }
+}
+
+// create a new unique authid row inside the given transaction
+async fn _create_authid(
+    pool: &Pool<MySql>,
+    tx: &mut Transaction<'_, MySql>,
+) -> Result<UserAuthidModel, Box<dyn std::error::Error>> {
+    let mut authid_counter: i32 = 0;
+    loop {
+        // will only try 5 times
+        if authid_counter == 5 {
+            let err_msg =
+                String::from("Error while creating UserAuthidModel. Try limit has been reached");
+            log::error!("{}", err_msg);
+            return Err(err_msg.into());
+        }
+
+        authid_counter += 1;
+        let authid_value = random_alphanumeric(32);
+        match UserAuthidModel::get_by_value(&pool, &authid_value).await? {
+            Some(_) => continue,
+            None => {
+                return Ok(UserAuthidModel::new(tx, &authid_value).await?);
+            }
+        }
     }
 }
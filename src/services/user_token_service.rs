@@ -0,0 +1,62 @@
+use chrono::{Duration, Utc};
+use sqlx::{MySql, Pool, Transaction};
+
+use crate::models::user_models::user_token_model::{UserTokenModel, UserTokenPurpose};
+use crate::utils::hash_utils::sha256_hex;
+use crate::utils::string_utils::random_alphanumeric;
+
+pub struct UserTokenService {}
+
+impl UserTokenService {
+    // creates a new single-use token for the user
+    // any older unused token for the same purpose stops working
+    // email_id is only for tokens that act on a specific email (e.g. email change)
+    // returns the raw token - only its hash is stored
+    pub async fn issue(
+        pool: &Pool<MySql>,
+        user_id: i64,
+        purpose: &UserTokenPurpose,
+        valid_for: Duration,
+        email_id: Option<i64>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let raw_token = random_alphanumeric(64);
+        let now = Utc::now().naive_utc();
+
+        let mut tx = pool.begin().await?;
+        UserTokenModel::invalidate_by_user_id(&mut tx, user_id, purpose, &now).await?;
+        UserTokenModel::new(
+            &mut tx,
+            user_id,
+            purpose,
+            &sha256_hex(&raw_token),
+            &(now + valid_for),
+            email_id,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(raw_token)
+    }
+
+    // uses up a raw token inside the caller's transaction
+    // so the token is only spent if whatever it unlocks is committed too
+    // returns None if token doesn't exist, has expired or was already used
+    pub async fn consume(
+        pool: &Pool<MySql>,
+        tx: &mut Transaction<'_, MySql>,
+        purpose: &UserTokenPurpose,
+        raw_token: &str,
+    ) -> Result<Option<UserTokenModel>, Box<dyn std::error::Error>> {
+        let token =
+            match UserTokenModel::get_by_value(pool, purpose, &sha256_hex(raw_token)).await? {
+                None => return Ok(None),
+                Some(t) => t,
+            };
+
+        if !token.mark_used(tx, &Utc::now().naive_utc()).await? {
+            return Ok(None);
+        }
+
+        Ok(Some(token))
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use futures_util::future::LocalBoxFuture;
+
+use crate::config::app_config::BlobStoreConfig;
+use crate::stores::local_blob_store::LocalBlobStore;
+
+// Anywhere uploaded files can be kept
+// keys look like paths ("avatars/<id>/small.webp") and are always made by the server, never by the client
+// handlers get this as web::Data<dyn BlobStore> so the backend can be swapped without touching them
+pub trait BlobStore: Send + Sync {
+    // replaces whatever is stored under the key
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        content: &'a [u8],
+    ) -> LocalBoxFuture<'a, Result<(), Box<dyn std::error::Error>>>;
+
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Option<Vec<u8>>, Box<dyn std::error::Error>>>;
+
+    // deleting a key that doesn't exist is not an error
+    fn delete<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> LocalBoxFuture<'a, Result<(), Box<dyn std::error::Error>>>;
+}
+
+// builds the store selected by BLOB_STORE
+// only "local" is available for now - it is also the default
+pub fn blob_store_from_config(
+    config: &BlobStoreConfig,
+) -> Result<Arc<dyn BlobStore>, Box<dyn std::error::Error>> {
+    match config.kind.to_lowercase().as_str() {
+        "local" => Ok(Arc::new(LocalBlobStore::new(config.dir.clone()))),
+        other => Err(format!("Unknown BLOB_STORE: {}", other).into()),
+    }
+}
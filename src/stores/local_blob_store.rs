@@ -0,0 +1,93 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use futures_util::future::LocalBoxFuture;
+
+use crate::stores::blob_store::BlobStore;
+
+// Keeps blobs as files under a directory, the key is the path below it
+// only suits a single instance, or instances sharing the directory
+pub struct LocalBlobStore {
+    dir: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(dir: String) -> LocalBlobStore {
+        LocalBlobStore {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    // file of the key - keys that could point outside of the directory are refused
+    fn path(&self, key: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(format!("Invalid blob key: {}", key).into());
+        }
+
+        Ok(self.dir.join(relative))
+    }
+}
+
+impl BlobStore for LocalBlobStore {
+    fn put<'a>(
+        &'a self,
+        key: &'a str,
+        content: &'a [u8],
+    ) -> LocalBoxFuture<'a, Result<(), Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            // written next to it first so a reader never sees half a file
+            let partial = path.with_extension("partial");
+            fs::write(&partial, content)?;
+            fs::rename(&partial, &path)?;
+
+            Ok(())
+        })
+    }
+
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Option<Vec<u8>>, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            match fs::read(self.path(key)?) {
+                Ok(content) => Ok(Some(content)),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn delete<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> LocalBoxFuture<'a, Result<(), Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let path = self.path(key)?;
+            match fs::remove_file(&path) {
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            // the directory of the key goes too once it is empty, failing that is harmless
+            if let Some(parent) = path.parent() {
+                if parent != self.dir.as_path() {
+                    let _ = fs::remove_dir(parent);
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
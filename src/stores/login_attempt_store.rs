@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use futures_util::future::LocalBoxFuture;
+use sqlx::MySqlPool;
+
+use crate::config::app_config::LoginThrottleConfig;
+
+use crate::stores::memory_login_attempt_store::MemoryLoginAttemptStore;
+use crate::stores::mysql_login_attempt_store::MysqlLoginAttemptStore;
+
+// Failed logins recorded for a key
+#[derive(Debug, Clone)]
+pub struct LoginAttempt {
+    pub failures: u32,
+    pub blocked_until: Option<NaiveDateTime>,
+}
+
+// Anywhere failed logins can be kept
+// keys are opaque to the store - the caller decides what they stand for
+// handlers get this as web::Data<dyn LoginAttemptStore> so the backend can be swapped without touching them
+pub trait LoginAttemptStore: Send + Sync {
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Option<LoginAttempt>, Box<dyn std::error::Error>>>;
+
+    // count a failure, starting again from 1 if the last one is older than window_start
+    fn record_failure<'a>(
+        &'a self,
+        key: &'a str,
+        now: NaiveDateTime,
+        window_start: NaiveDateTime,
+    ) -> LocalBoxFuture<'a, Result<LoginAttempt, Box<dyn std::error::Error>>>;
+
+    // never shortens a block that is already longer
+    fn block_until<'a>(
+        &'a self,
+        key: &'a str,
+        until: NaiveDateTime,
+    ) -> LocalBoxFuture<'a, Result<(), Box<dyn std::error::Error>>>;
+
+    fn clear<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> LocalBoxFuture<'a, Result<(), Box<dyn std::error::Error>>>;
+
+    // forget keys whose last failure is older than window_start and that aren't blocked anymore
+    // returns how many were removed
+    fn purge_expired<'a>(
+        &'a self,
+        now: NaiveDateTime,
+        window_start: NaiveDateTime,
+    ) -> LocalBoxFuture<'a, Result<u64, Box<dyn std::error::Error>>>;
+}
+
+// builds the store selected by LOGIN_ATTEMPT_STORE
+// "mysql" (default) is shared by every instance, "memory" only suits a single instance
+pub fn login_attempt_store_from_config(
+    config: &LoginThrottleConfig,
+    pool: &MySqlPool,
+) -> Result<Arc<dyn LoginAttemptStore>, Box<dyn std::error::Error>> {
+    match config.store.to_lowercase().as_str() {
+        "mysql" => Ok(Arc::new(MysqlLoginAttemptStore::new(pool.clone()))),
+        "memory" => Ok(Arc::new(MemoryLoginAttemptStore::default())),
+        other => Err(format!("Unknown LOGIN_ATTEMPT_STORE: {}", other).into()),
+    }
+}
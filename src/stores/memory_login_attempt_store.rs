@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::NaiveDateTime;
+use futures_util::future::LocalBoxFuture;
+
+use crate::stores::login_attempt_store::{LoginAttempt, LoginAttemptStore};
+
+// entries are pruned once the map grows past this
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct MemoryEntry {
+    failures: u32,
+    last_failure: NaiveDateTime,
+    blocked_until: Option<NaiveDateTime>,
+}
+
+// Keeps failed logins in process memory
+// counts are lost on restart and not shared between instances
+#[derive(Default)]
+pub struct MemoryLoginAttemptStore {
+    entries: Mutex<HashMap<String, MemoryEntry>>,
+}
+
+impl LoginAttemptStore for MemoryLoginAttemptStore {
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Option<LoginAttempt>, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let entries = self.entries.lock().map_err(|e| e.to_string())?;
+            Ok(entries.get(key).map(|e| LoginAttempt {
+                failures: e.failures,
+                blocked_until: e.blocked_until,
+            }))
+        })
+    }
+
+    fn record_failure<'a>(
+        &'a self,
+        key: &'a str,
+        now: NaiveDateTime,
+        window_start: NaiveDateTime,
+    ) -> LocalBoxFuture<'a, Result<LoginAttempt, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+
+            if entries.len() > PRUNE_THRESHOLD {
+                entries.retain(|_, e| {
+                    e.last_failure >= window_start || e.blocked_until.is_some_and(|b| b > now)
+                });
+            }
+
+            let entry = entries.entry(key.to_string()).or_insert(MemoryEntry {
+                failures: 0,
+                last_failure: now,
+                blocked_until: None,
+            });
+
+            if entry.last_failure < window_start {
+                entry.failures = 0;
+            }
+            entry.failures += 1;
+            entry.last_failure = now;
+
+            Ok(LoginAttempt {
+                failures: entry.failures,
+                blocked_until: entry.blocked_until,
+            })
+        })
+    }
+
+    fn block_until<'a>(
+        &'a self,
+        key: &'a str,
+        until: NaiveDateTime,
+    ) -> LocalBoxFuture<'a, Result<(), Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+            if let Some(entry) = entries.get_mut(key) {
+                if entry.blocked_until.is_none_or(|b| b < until) {
+                    entry.blocked_until = Some(until);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn clear<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> LocalBoxFuture<'a, Result<(), Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+            entries.remove(key);
+            Ok(())
+        })
+    }
+
+    fn purge_expired<'a>(
+        &'a self,
+        now: NaiveDateTime,
+        window_start: NaiveDateTime,
+    ) -> LocalBoxFuture<'a, Result<u64, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+            let before = entries.len();
+            entries.retain(|_, e| {
+                e.last_failure >= window_start || e.blocked_until.is_some_and(|b| b > now)
+            });
+            Ok((before - entries.len()) as u64)
+        })
+    }
+}
@@ -0,0 +1,5 @@
+pub mod blob_store;
+pub mod local_blob_store;
+pub mod login_attempt_store;
+pub mod memory_login_attempt_store;
+pub mod mysql_login_attempt_store;
@@ -0,0 +1,98 @@
+use chrono::NaiveDateTime;
+use futures_util::future::LocalBoxFuture;
+use sqlx::MySqlPool;
+
+use crate::models::login_attempt_models::login_attempt_model::LoginAttemptModel;
+use crate::stores::login_attempt_store::{LoginAttempt, LoginAttemptStore};
+
+// rows deleted per statement - keeps each delete short so it doesn't hold locks for long
+const PURGE_BATCH_SIZE: u32 = 1000;
+
+// Keeps failed logins in the login_attempt table so every instance sees the same counts
+pub struct MysqlLoginAttemptStore {
+    pool: MySqlPool,
+}
+
+impl MysqlLoginAttemptStore {
+    pub fn new(pool: MySqlPool) -> MysqlLoginAttemptStore {
+        MysqlLoginAttemptStore { pool }
+    }
+}
+
+impl LoginAttemptStore for MysqlLoginAttemptStore {
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Option<LoginAttempt>, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let row = LoginAttemptModel::get_by_value(&self.pool, key).await?;
+            Ok(row.map(_to_login_attempt))
+        })
+    }
+
+    fn record_failure<'a>(
+        &'a self,
+        key: &'a str,
+        now: NaiveDateTime,
+        window_start: NaiveDateTime,
+    ) -> LocalBoxFuture<'a, Result<LoginAttempt, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let row =
+                LoginAttemptModel::record_failure(&self.pool, key, &now, &window_start).await?;
+            Ok(_to_login_attempt(row))
+        })
+    }
+
+    fn block_until<'a>(
+        &'a self,
+        key: &'a str,
+        until: NaiveDateTime,
+    ) -> LocalBoxFuture<'a, Result<(), Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            LoginAttemptModel::update_datetime_blocked_until(&self.pool, key, &until).await?;
+            Ok(())
+        })
+    }
+
+    fn clear<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> LocalBoxFuture<'a, Result<(), Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            LoginAttemptModel::delete_by_value(&self.pool, key).await?;
+            Ok(())
+        })
+    }
+
+    fn purge_expired<'a>(
+        &'a self,
+        now: NaiveDateTime,
+        window_start: NaiveDateTime,
+    ) -> LocalBoxFuture<'a, Result<u64, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let mut total: u64 = 0;
+
+            loop {
+                let deleted = LoginAttemptModel::delete_expired(
+                    &self.pool,
+                    &now,
+                    &window_start,
+                    PURGE_BATCH_SIZE,
+                )
+                .await?;
+                total += deleted;
+
+                if deleted < PURGE_BATCH_SIZE as u64 {
+                    return Ok(total);
+                }
+            }
+        })
+    }
+}
+
+fn _to_login_attempt(row: LoginAttemptModel) -> LoginAttempt {
+    LoginAttempt {
+        failures: row.failures.max(0) as u32,
+        blocked_until: row.datetime_blocked_until,
+    }
+}
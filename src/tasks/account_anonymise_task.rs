@@ -0,0 +1,59 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::rt;
+use chrono::Utc;
+use sqlx::MySqlPool;
+
+use crate::models::user_models::user_model::UserModel;
+use crate::services::account_service::AccountService;
+use crate::stores::blob_store::BlobStore;
+
+// users looked up per query - each of them is anonymised in its own transaction
+const ANONYMISE_BATCH_SIZE: u32 = 100;
+
+// Anonymises users whose deletion grace period is over, once at startup and then every interval
+pub fn spawn(
+    pool: MySqlPool,
+    blob_store: Arc<dyn BlobStore>,
+    interval_minutes: u64,
+    grace_days: i64,
+) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(Duration::from_secs(interval_minutes * 60));
+
+        loop {
+            interval.tick().await;
+
+            match _anonymise(&pool, blob_store.as_ref(), grace_days).await {
+                Err(e) => log::error!("Unable to anonymise deleted users. {}", e),
+                Ok(0) => {}
+                Ok(anonymised) => log::info!("Anonymised {} deleted users", anonymised),
+            }
+        }
+    });
+}
+
+async fn _anonymise(
+    pool: &MySqlPool,
+    blob_store: &dyn BlobStore,
+    grace_days: i64,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let cutoff = Utc::now().naive_utc() - chrono::Duration::days(grace_days);
+    let mut total: u64 = 0;
+
+    loop {
+        let user_ids =
+            UserModel::get_ids_due_for_anonymising(pool, &cutoff, ANONYMISE_BATCH_SIZE).await?;
+
+        for user_id in &user_ids {
+            if AccountService::anonymise(pool, blob_store, *user_id, &cutoff).await? {
+                total += 1;
+            }
+        }
+
+        if user_ids.len() < ANONYMISE_BATCH_SIZE as usize {
+            return Ok(total);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use sqlx::MySqlPool;
+
+use crate::config::app_config::ExportConfig;
+use crate::services::export_service::ExportService;
+
+// exports deleted per query
+const PURGE_BATCH_SIZE: u32 = 100;
+
+// Deletes exports and their archives once their ttl has passed, at startup and then every interval
+pub fn spawn(pool: MySqlPool, export_config: ExportConfig, interval_minutes: u64) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(Duration::from_secs(interval_minutes * 60));
+
+        loop {
+            interval.tick().await;
+
+            match _purge(&pool, &export_config).await {
+                Err(e) => log::error!("Unable to purge expired exports. {}", e),
+                Ok(0) => {}
+                Ok(deleted) => log::info!("Purged {} expired exports", deleted),
+            }
+        }
+    });
+}
+
+async fn _purge(
+    pool: &MySqlPool,
+    export_config: &ExportConfig,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let mut total: u64 = 0;
+
+    loop {
+        let deleted = ExportService::purge_expired(pool, export_config, PURGE_BATCH_SIZE).await?;
+        total += deleted;
+
+        if deleted < PURGE_BATCH_SIZE as u64 {
+            return Ok(total);
+        }
+    }
+}
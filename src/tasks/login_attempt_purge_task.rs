@@ -0,0 +1,32 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::rt;
+use chrono::Utc;
+
+use crate::stores::login_attempt_store::LoginAttemptStore;
+
+// Forgets failed logins that can't slow anyone down anymore, once at startup and then every interval
+// failures older than the window are reset on the next one anyway, and the block has ended
+pub fn spawn(
+    store: Arc<dyn LoginAttemptStore>,
+    failure_window_minutes: i64,
+    interval_minutes: u64,
+) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(Duration::from_secs(interval_minutes * 60));
+
+        loop {
+            interval.tick().await;
+
+            let now = Utc::now().naive_utc();
+            let window_start = now - chrono::Duration::minutes(failure_window_minutes);
+
+            match store.purge_expired(now, window_start).await {
+                Err(e) => log::error!("Unable to purge login attempts. {}", e),
+                Ok(0) => {}
+                Ok(deleted) => log::info!("Purged {} expired login attempts", deleted),
+            }
+        }
+    });
+}
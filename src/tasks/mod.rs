@@ -0,0 +1,4 @@
+pub mod account_anonymise_task;
+pub mod export_purge_task;
+pub mod login_attempt_purge_task;
+pub mod revoked_token_purge_task;
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use chrono::Utc;
+use sqlx::MySqlPool;
+
+use crate::models::revoked_token_models::revoked_token_model::RevokedTokenModel;
+
+// rows deleted per statement - keeps each delete short so it doesn't hold locks for long
+const PURGE_BATCH_SIZE: u32 = 1000;
+
+// Deletes revoked tokens whose ttl has passed, once at startup and then every interval
+// a token past its ttl has long expired and can't be used anymore, so there's nothing left to block
+pub fn spawn(pool: MySqlPool, interval_minutes: u64) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(Duration::from_secs(interval_minutes * 60));
+
+        loop {
+            interval.tick().await;
+
+            match _purge(&pool).await {
+                Err(e) => log::error!("Unable to purge revoked tokens. {}", e),
+                Ok(0) => {}
+                Ok(deleted) => log::info!("Purged {} expired revoked tokens", deleted),
+            }
+        }
+    });
+}
+
+async fn _purge(pool: &MySqlPool) -> Result<u64, sqlx::error::Error> {
+    let now = Utc::now().naive_utc();
+    let mut total: u64 = 0;
+
+    loop {
+        let deleted = RevokedTokenModel::delete_expired(pool, &now, PURGE_BATCH_SIZE).await?;
+        total += deleted;
+
+        if deleted < PURGE_BATCH_SIZE as u64 {
+            return Ok(total);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+// Bounded in-process cache where every entry expires ttl after it was inserted
+// each instance of the server has its own, so entries may lag behind the database for up to ttl
+pub struct TtlCache<V: Clone> {
+    name: &'static str,
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<Entries<V>>,
+    generation: AtomicU64, // bumped by every invalidate
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+// every entry has the same ttl, so insertion order is also expiry order
+// the queue can still name keys that were replaced or removed since - those are skipped when popped
+struct Entries<V> {
+    values: HashMap<String, (V, Instant)>, // value and when it expires
+    order: VecDeque<(String, Instant)>,    // oldest first, with the expiry it was inserted with
+}
+
+impl<V> Entries<V> {
+    // true if the queued entry is still the one in values
+    fn _is_current(&self, key: &str, expires_at: Instant) -> bool {
+        matches!(self.values.get(key), Some((_, e)) if *e == expires_at)
+    }
+
+    // drop the oldest entry that is still in values
+    fn _evict_oldest(&mut self) {
+        while let Some((key, expires_at)) = self.order.pop_front() {
+            if self._is_current(&key, expires_at) {
+                self.values.remove(&key);
+                return;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheStats {
+    pub name: &'static str,
+    pub entries: usize,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl<V: Clone> TtlCache<V> {
+    pub fn new(name: &'static str, ttl: Duration, capacity: usize) -> TtlCache<V> {
+        TtlCache {
+            name,
+            ttl,
+            capacity: capacity.max(1),
+            entries: Mutex::new(Entries {
+                values: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+            generation: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    // counts as a hit or a miss
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        let value = match entries.values.get(key) {
+            Some((v, expires_at)) if *expires_at > Instant::now() => Some(v.clone()),
+            Some(_) => {
+                entries.values.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        match value {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+
+        value
+    }
+
+    pub fn insert(&self, key: &str, value: V) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        self._insert(&mut entries, key, value);
+    }
+
+    // for a value read from the database after taking generation()
+    // dropped if anything was invalidated since, the value may have been read before that change
+    pub fn insert_unless_invalidated(&self, key: &str, value: V, generation: u64) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if self.generation.load(Ordering::Relaxed) != generation {
+            return;
+        }
+        self._insert(&mut entries, key, value);
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    // drop the entry once whatever it was read from has changed
+    pub fn invalidate(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.values.remove(key);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // amortised O(1) - every queued key is popped at most once
+    fn _insert(&self, entries: &mut Entries<V>, key: &str, value: V) {
+        let now = Instant::now();
+
+        // expired entries are at the front of the queue
+        while entries.order.front().is_some_and(|(_, e)| *e <= now) {
+            if let Some((k, e)) = entries.order.pop_front()
+                && entries._is_current(&k, e)
+            {
+                entries.values.remove(&k);
+            }
+        }
+
+        if entries.values.len() >= self.capacity && !entries.values.contains_key(key) {
+            entries._evict_oldest();
+        }
+
+        // replaced and removed keys leave their old place in the queue behind
+        // compact once those outnumber the live entries so the queue stays bounded
+        if entries.order.len() >= 2 * self.capacity {
+            let Entries { values, order } = entries;
+            order.retain(|(k, e)| matches!(values.get(k), Some((_, v)) if v == e));
+        }
+
+        let expires_at = now + self.ttl;
+        entries.values.insert(key.to_string(), (value, expires_at));
+        entries.order.push_back((key.to_string(), expires_at));
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            name: self.name,
+            entries: self
+                .entries
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .values
+                .len(),
+            capacity: self.capacity,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_counts_hits_and_misses() {
+        let cache: TtlCache<i64> = TtlCache::new("test", Duration::from_secs(60), 10);
+
+        assert_eq!(cache.get("a"), None);
+        cache.insert("a", 1);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("b"), None);
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.entries, 1);
+    }
+
+    #[test]
+    fn expired_entries_miss_and_are_dropped() {
+        let cache: TtlCache<i64> = TtlCache::new("test", Duration::ZERO, 10);
+
+        cache.insert("a", 1);
+        assert_eq!(cache.stats().entries, 1);
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!(cache.stats().misses, 1);
+    }
+
+    #[test]
+    fn insert_replaces_the_value_and_its_expiry() {
+        let cache: TtlCache<i64> = TtlCache::new("test", Duration::from_secs(60), 10);
+
+        cache.insert("a", 1);
+        cache.insert("a", 2);
+
+        assert_eq!(cache.get("a"), Some(2));
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn full_cache_drops_the_oldest_entry() {
+        let cache: TtlCache<i64> = TtlCache::new("test", Duration::from_secs(60), 2);
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        // an existing key doesn't need room, and is now the newest
+        cache.insert("a", 3);
+        assert_eq!(cache.stats().entries, 2);
+
+        cache.insert("c", 4);
+        assert_eq!(cache.stats().entries, 2);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(3));
+        assert_eq!(cache.get("c"), Some(4));
+    }
+
+    #[test]
+    fn invalidated_entries_make_room() {
+        let cache: TtlCache<i64> = TtlCache::new("test", Duration::from_secs(60), 2);
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.invalidate("a");
+        cache.insert("c", 3);
+
+        assert_eq!(cache.get("b"), Some(2));
+        assert_eq!(cache.get("c"), Some(3));
+    }
+
+    #[test]
+    fn insert_drops_expired_entries_first() {
+        let cache: TtlCache<i64> = TtlCache::new("test", Duration::ZERO, 10);
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+
+        // the earlier two expired the moment they were inserted
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn queue_stays_bounded_when_keys_are_replaced() {
+        let cache: TtlCache<i64> = TtlCache::new("test", Duration::from_secs(60), 4);
+
+        for i in 0..100 {
+            cache.insert("a", i);
+            cache.insert("b", i);
+        }
+
+        assert!(cache.entries.lock().unwrap().order.len() <= 8);
+        assert_eq!(cache.get("a"), Some(99));
+        assert_eq!(cache.get("b"), Some(99));
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[test]
+    fn insert_unless_invalidated_drops_values_read_before_an_invalidate() {
+        let cache: TtlCache<Option<i64>> = TtlCache::new("test", Duration::from_secs(60), 10);
+        cache.insert("a", Some(1));
+
+        // a lookup read "a" from the database, then it was rotated away before the lookup cached it
+        let generation = cache.generation();
+        cache.invalidate("a");
+        cache.insert_unless_invalidated("a", Some(1), generation);
+        assert_eq!(cache.get("a"), None);
+
+        // read after the invalidate, kept
+        let generation = cache.generation();
+        cache.insert_unless_invalidated("a", None, generation);
+        assert_eq!(cache.get("a"), Some(None));
+    }
+}
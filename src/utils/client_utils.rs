@@ -0,0 +1,55 @@
+use std::net::{IpAddr, SocketAddr};
+
+use actix_web::HttpRequest;
+use actix_web::web;
+
+use crate::config::app_config::AppConfig;
+use crate::utils::header_utils::RequestHeader;
+
+// Where a request came from - recorded against user sessions
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn from_request(req: &HttpRequest) -> ClientInfo {
+        // column only holds 512 characters
+        let user_agent = match req.get_header_value("user-agent") {
+            Ok(Some(ua)) => Some(ua.chars().take(512).collect()),
+            _ => None,
+        };
+
+        ClientInfo {
+            user_agent,
+            ip_address: client_ip(req).map(|ip| ip.to_string()),
+        }
+    }
+}
+
+// ip address of the client
+// the peer address, unless SERVER_TRUSTED_PROXY says the forwarding headers can be believed
+// a client can put anything in those headers, so without a proxy that overwrites them they are ignored
+pub fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let trusted_proxy = req
+        .app_data::<web::Data<AppConfig>>()
+        .is_some_and(|c| c.server.trusted_proxy);
+
+    if !trusted_proxy {
+        return req.peer_addr().map(|addr| addr.ip());
+    }
+
+    req.connection_info()
+        .realip_remote_addr()
+        .and_then(_parse_ip)
+}
+
+// forwarding headers may carry a port, e.g. 203.0.113.7:4711 or [2001:db8::1]:4711
+fn _parse_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or(value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
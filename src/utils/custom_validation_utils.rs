@@ -26,3 +26,126 @@ pub fn validate_email(email: &str) -> Result<(), ValidationError> {
 
     Ok(())
 }
+
+// the part of an email after the @, e.g. example.com
+pub fn validate_email_domain(domain: &str) -> Result<(), ValidationError> {
+    if domain.contains('@') || !domain.contains('.') {
+        return Err(ValidationError::new("Invalid format"));
+    }
+
+    validate_email(&format!("user@{}", domain))
+}
+
+// handles that could pass for the site itself, its staff or one of its routes
+const RESERVED_HANDLES: [&str; 24] = [
+    "about",
+    "admin",
+    "administrator",
+    "anonymous",
+    "api",
+    "deleted",
+    "everyone",
+    "help",
+    "here",
+    "login",
+    "logout",
+    "makisama",
+    "me",
+    "mod",
+    "moderator",
+    "null",
+    "register",
+    "root",
+    "settings",
+    "staff",
+    "support",
+    "system",
+    "undefined",
+    "user",
+];
+
+pub fn validate_handle(handle: &str) -> Result<(), ValidationError> {
+    if handle.len() < 3 || handle.len() > 30 {
+        return Err(ValidationError::new("Must be between 3 and 30 characters"));
+    }
+
+    if !handle
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(ValidationError::new(
+            "Must only contain letters, numbers and underscores",
+        ));
+    }
+
+    if !handle.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(ValidationError::new("Must start with a letter"));
+    }
+
+    if RESERVED_HANDLES.contains(&handle.to_ascii_lowercase().as_str()) {
+        return Err(ValidationError::new("Is reserved"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _handle_error(handle: &str) -> String {
+        validate_handle(handle).unwrap_err().code.to_string()
+    }
+
+    #[test]
+    fn validate_handle_accepts_well_formed_handles() {
+        for handle in ["abc", "Maki_chan", "user_1", "a__", &"a".repeat(30)] {
+            assert!(validate_handle(handle).is_ok(), "{handle}");
+        }
+    }
+
+    #[test]
+    fn validate_handle_rejects_bad_lengths() {
+        for handle in ["", "ab", &"a".repeat(31)] {
+            assert_eq!(_handle_error(handle), "Must be between 3 and 30 characters");
+        }
+    }
+
+    #[test]
+    fn validate_handle_rejects_other_characters() {
+        // "ãbc" is 4 bytes, the length check alone must not let it through
+        for handle in ["maki-chan", "maki.chan", "maki chan", "ãbc", "makí"] {
+            assert_eq!(
+                _handle_error(handle),
+                "Must only contain letters, numbers and underscores"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_handle_must_start_with_a_letter() {
+        for handle in ["1maki", "_maki", "123"] {
+            assert_eq!(_handle_error(handle), "Must start with a letter");
+        }
+    }
+
+    #[test]
+    fn validate_handle_rejects_reserved_handles_in_any_casing() {
+        for handle in RESERVED_HANDLES.into_iter().filter(|h| h.len() >= 3) {
+            assert_eq!(_handle_error(handle), "Is reserved");
+            assert_eq!(_handle_error(&handle.to_ascii_uppercase()), "Is reserved");
+        }
+
+        assert_eq!(_handle_error("AdMiN"), "Is reserved");
+        assert!(validate_handle("admin_").is_ok());
+        assert!(validate_handle("administrators").is_ok());
+    }
+
+    #[test]
+    fn reserved_handles_are_lowercase() {
+        // they are compared against the lowercased handle
+        for handle in RESERVED_HANDLES {
+            assert_eq!(handle, handle.to_ascii_lowercase());
+        }
+    }
+}
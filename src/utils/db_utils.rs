@@ -5,18 +5,20 @@ use sqlx::{
     mysql::{MySqlConnectOptions, MySqlPoolOptions},
 };
 
+use crate::config::app_config::DatabaseConfig;
+
 pub struct DatabasePool {
     pub pool: MySqlPool,
 }
 
 impl DatabasePool {
-    pub async fn new(url: &str) -> sqlx::Result<DatabasePool> {
+    pub async fn new(config: &DatabaseConfig) -> sqlx::Result<DatabasePool> {
         let pool = MySqlPoolOptions::new()
-            .max_connections(5)
-            .min_connections(1)
-            .acquire_timeout(Duration::from_secs(5))
-            .idle_timeout(Duration::from_secs(60 * 5))
-            .connect_with(MySqlConnectOptions::from_str(url)?)
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+            .acquire_timeout(Duration::from_secs(config.acquire_timeout_seconds))
+            .idle_timeout(Duration::from_secs(config.idle_timeout_seconds))
+            .connect_with(MySqlConnectOptions::from_str(&config.url)?)
             .await?;
 
         Ok(DatabasePool { pool: pool })
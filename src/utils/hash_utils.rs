@@ -0,0 +1,29 @@
+use ring::hmac;
+use sha2::{Digest, Sha256};
+
+// hex encoded sha256 digest of value
+// used for tokens that are stored server side but must never be stored raw
+pub fn sha256_hex(value: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(value.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+// hex encoded hmac-sha256 of value
+// used to sign links so the server can tell later that it made them
+pub fn hmac_sha256_hex(secret: &str, value: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hex::encode(hmac::sign(&key, value.as_bytes()).as_ref())
+}
+
+// compares every byte so the time taken doesn't tell how much of a secret matched
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
@@ -0,0 +1,93 @@
+use std::fmt;
+use std::io::Cursor;
+
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+
+// Why an image could not be turned into variants
+#[derive(Debug)]
+pub enum ImageError {
+    Rejected(String), // the upload itself is the problem - safe to show to the client
+    Failed(String),   // decoded fine but could not be encoded
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Rejected(reason) => write!(f, "Image rejected: {}", reason),
+            ImageError::Failed(reason) => write!(f, "Image processing failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+// Square lossless webp of the image for each size, in the order given
+// only png, jpeg and webp are accepted, the format is sniffed from the bytes and not trusted from the client
+// the image is re-encoded from its pixels so exif and every other piece of metadata is left behind,
+// exif orientation is applied first so the image still shows the right way up
+pub fn square_webp_variants(
+    bytes: &[u8],
+    sizes: &[u32],
+    min_dimension: u32,
+    max_dimension: u32,
+) -> Result<Vec<Vec<u8>>, ImageError> {
+    let reader = _reader(bytes)?;
+
+    match reader.format() {
+        Some(ImageFormat::Png) | Some(ImageFormat::Jpeg) | Some(ImageFormat::WebP) => {}
+        _ => {
+            return Err(ImageError::Rejected(
+                "Must be a PNG, JPEG or WebP image".to_string(),
+            ));
+        }
+    }
+
+    // dimensions come from the header, checked before anything is decoded
+    let (width, height) = reader
+        .into_dimensions()
+        .map_err(|_| ImageError::Rejected("Image could not be read".to_string()))?;
+    if width < min_dimension || height < min_dimension {
+        return Err(ImageError::Rejected(format!(
+            "Must be at least {}x{} pixels",
+            min_dimension, min_dimension
+        )));
+    }
+    if width > max_dimension || height > max_dimension {
+        return Err(ImageError::Rejected(format!(
+            "Must be at most {}x{} pixels",
+            max_dimension, max_dimension
+        )));
+    }
+
+    let mut decoder = _reader(bytes)?
+        .into_decoder()
+        .map_err(|_| ImageError::Rejected("Image could not be read".to_string()))?;
+    let orientation = decoder
+        .orientation()
+        .map_err(|_| ImageError::Rejected("Image could not be read".to_string()))?;
+    let mut image = DynamicImage::from_decoder(decoder)
+        .map_err(|_| ImageError::Rejected("Image could not be read".to_string()))?;
+    image.apply_orientation(orientation);
+
+    let mut variants = Vec::with_capacity(sizes.len());
+    for size in sizes {
+        let resized = image.resize_to_fill(*size, *size, FilterType::Lanczos3);
+
+        let mut encoded: Vec<u8> = Vec::new();
+        DynamicImage::ImageRgba8(resized.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut encoded))
+            .map_err(|e| ImageError::Failed(e.to_string()))?;
+
+        variants.push(encoded);
+    }
+
+    Ok(variants)
+}
+
+fn _reader(bytes: &[u8]) -> Result<ImageReader<Cursor<&[u8]>>, ImageError> {
+    ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|_| ImageError::Rejected("Image could not be read".to_string()))
+}
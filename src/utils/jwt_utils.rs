@@ -1,35 +1,371 @@
-use std::env;
+use std::fs;
 
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
 use jsonwebtoken::{
-    Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation, decode, encode,
+    Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation, decode, decode_header,
+    encode,
 };
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair, RsaPublicKeyComponents};
 use serde::{Deserialize, Serialize};
 
+use crate::config::app_config::JwtConfig;
+use crate::utils::response_utils::ErrorCode;
+use crate::utils::string_utils::random_alphanumeric;
+
+// kid of HS512 keys - symmetric keys are never published
+const ACCESS_SECRET_KID: &str = "access-hs512";
+const REFRESH_SECRET_KID: &str = "refresh-hs512";
+
+// seconds past exp a token is still accepted, for clock skew
+const EXP_LEEWAY_SECONDS: u64 = 60;
+
 pub enum TokenType {
     Access,
     Refresh,
 }
 
+impl TokenType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenType::Access => "access",
+            TokenType::Refresh => "refresh",
+        }
+    }
+}
+
+// Why a token couldn't be issued or accepted
+#[derive(Debug)]
+pub enum JwtError {
+    Expired,
+    BadSignature,
+    Malformed,
+    WrongTokenType,
+    NotYetValid,           // nbf is still in the future
+    InvalidClaims,         // iss or aud is missing or not ours
+    ConfigMissing(String), // server side - missing or unusable key, secret or setting
+}
+
+impl JwtError {
+    // code for the response envelope, None when it's not the client's fault
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            JwtError::Expired => Some(ErrorCode::TokenExpired),
+            JwtError::BadSignature => Some(ErrorCode::TokenInvalidSignature),
+            JwtError::Malformed => Some(ErrorCode::TokenMalformed),
+            JwtError::WrongTokenType => Some(ErrorCode::TokenWrongType),
+            JwtError::NotYetValid => Some(ErrorCode::TokenNotYetValid),
+            JwtError::InvalidClaims => Some(ErrorCode::TokenInvalidClaims),
+            JwtError::ConfigMissing(_) => None,
+        }
+    }
+}
+
+// reads as the end of "Access token ..." / "Refresh token ..."
+impl std::fmt::Display for JwtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JwtError::Expired => write!(f, "has expired"),
+            JwtError::BadSignature => write!(f, "has an invalid signature"),
+            JwtError::Malformed => write!(f, "is malformed"),
+            JwtError::WrongTokenType => write!(f, "is not of the expected type"),
+            JwtError::NotYetValid => write!(f, "is not valid yet"),
+            JwtError::InvalidClaims => write!(f, "was not issued for this service"),
+            JwtError::ConfigMissing(m) => write!(f, "can't be processed, JWT config: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for JwtError {}
+
+impl From<jsonwebtoken::errors::Error> for JwtError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        use jsonwebtoken::errors::ErrorKind;
+
+        match e.kind() {
+            ErrorKind::ExpiredSignature => JwtError::Expired,
+            ErrorKind::InvalidSignature => JwtError::BadSignature,
+            ErrorKind::ImmatureSignature => JwtError::NotYetValid,
+            ErrorKind::InvalidIssuer
+            | ErrorKind::InvalidAudience
+            | ErrorKind::MissingRequiredClaim(_) => JwtError::InvalidClaims,
+            ErrorKind::InvalidEcdsaKey
+            | ErrorKind::InvalidRsaKey(_)
+            | ErrorKind::RsaFailedSigning
+            | ErrorKind::InvalidKeyFormat
+            | ErrorKind::Crypto(_) => JwtError::ConfigMissing(e.to_string()),
+            // bad encoding, unknown kid, wrong alg, claims that don't parse...
+            _ => JwtError::Malformed,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,        // Subject (the auth token)
     pub exp: i64,           // Expiration time
     pub iat: i64,           // Issued at time
     pub token_type: String, // access or refresh
+    #[serde(default)]
+    pub jti: String, // unique id of the token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fam: Option<String>, // refresh token family - only on refresh tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<i64>, // Not before - same as iat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>, // Issuer - JWT_ISSUER, if set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>, // Audience - JWT_AUDIENCE, if set
+}
+
+struct SigningKey {
+    kid: String,
+    encoding_key: EncodingKey,
+}
+
+struct VerifyingKey {
+    kid: String,
+    decoding_key: DecodingKey,
+    jwk: Option<Jwk>, // public part, None for HS512 secrets
+}
+
+// Keys and claim settings for signing and verifying tokens, built once at startup from AppConfig
+// and shared as web::Data
+//
+// Access tokens are signed with JWT_ACCESS_ALGORITHM:
+//  - HS512 (default) with JWT_ACCESS_SECRET
+//  - EdDSA or RS256 with JWT_ACCESS_KEYS, a comma separated list of kid=path to a PKCS#8 private key PEM
+//    the first key signs new tokens, the others only verify tokens signed before a rotation
+//    their public keys are published at /.well-known/jwks.json
+// Refresh tokens are only read by this service and stay HS512 with JWT_REFRESH_SECRET
+//
+// JWT_ISSUER and JWT_AUDIENCE are optional, once set every token must carry them
+// so tokens issued before they were set stop being accepted
+pub struct JwtKeys {
+    access_algorithm: Algorithm,
+    access_signing_key: SigningKey,
+    access_verifying_keys: Vec<VerifyingKey>,
+    refresh_signing_key: SigningKey,
+    refresh_decoding_key: DecodingKey,
+    access_expiration: Duration,
+    refresh_expiration: Duration,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl JwtKeys {
+    // config is already validated by AppConfig, errors left here are about the key files
+    pub fn from_config(config: &JwtConfig) -> Result<JwtKeys, Box<dyn std::error::Error>> {
+        let access_algorithm = match config.access_algorithm.as_str() {
+            "HS512" => Algorithm::HS512,
+            "EdDSA" => Algorithm::EdDSA,
+            "RS256" => Algorithm::RS256,
+            other => return Err(format!("Unsupported JWT_ACCESS_ALGORITHM: {}", other).into()),
+        };
+
+        let (access_signing_key, access_verifying_keys) = match access_algorithm {
+            Algorithm::HS512 => {
+                let secret = config
+                    .access_secret
+                    .as_deref()
+                    .ok_or("JWT_ACCESS_SECRET must be set")?;
+                let (signing, verifying) = _secret_keys(ACCESS_SECRET_KID, secret);
+                (signing, vec![verifying])
+            }
+            _ => _private_keys(
+                &access_algorithm,
+                config
+                    .access_keys
+                    .as_deref()
+                    .ok_or("JWT_ACCESS_KEYS must be set")?,
+            )?,
+        };
+
+        let (refresh_signing_key, refresh_verifying_key) =
+            _secret_keys(REFRESH_SECRET_KID, &config.refresh_secret);
+        let refresh_decoding_key = refresh_verifying_key.decoding_key;
+
+        Ok(JwtKeys {
+            access_algorithm,
+            access_signing_key,
+            access_verifying_keys,
+            refresh_signing_key,
+            refresh_decoding_key,
+            access_expiration: Duration::minutes(config.access_token_expiration_minutes),
+            refresh_expiration: Duration::days(config.refresh_token_expiration_days),
+            issuer: config.issuer.clone(),
+            audience: config.audience.clone(),
+        })
+    }
+
+    // public keys of every access token verification key
+    // empty when access tokens are HS512
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self
+                .access_verifying_keys
+                .iter()
+                .filter_map(|k| k.jwk.clone())
+                .collect(),
+        }
+    }
+
+    // key matching the kid of the token
+    // tokens without a kid were signed before kids were added - only the signing key can verify them
+    fn access_decoding_key(&self, token: &str) -> Result<&DecodingKey, JwtError> {
+        let header = decode_header(token)?;
+
+        let kid = header
+            .kid
+            .unwrap_or(self.access_signing_key.kid.to_string());
+
+        match self.access_verifying_keys.iter().find(|k| k.kid == kid) {
+            None => Err(JwtError::Malformed),
+            Some(k) => Ok(&k.decoding_key),
+        }
+    }
+}
+
+fn _secret_keys(kid: &str, secret: &str) -> (SigningKey, VerifyingKey) {
+    (
+        SigningKey {
+            kid: kid.to_string(),
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+        },
+        VerifyingKey {
+            kid: kid.to_string(),
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+        },
+    )
+}
+
+// parse "kid=path,kid=path" - first key is the signing key
+fn _private_keys(
+    algorithm: &Algorithm,
+    entries: &str,
+) -> Result<(SigningKey, Vec<VerifyingKey>), Box<dyn std::error::Error>> {
+    let mut signing_key: Option<SigningKey> = None;
+    let mut verifying_keys: Vec<VerifyingKey> = Vec::new();
+
+    for entry in entries
+        .split(',')
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+    {
+        let (kid, path) = entry.split_once('=').ok_or(format!(
+            "JWT_ACCESS_KEYS entry must be kid=path, got {}",
+            entry
+        ))?;
+        let (kid, path) = (kid.trim(), path.trim());
+
+        if verifying_keys.iter().any(|k| k.kid == kid) {
+            return Err(format!("JWT_ACCESS_KEYS has kid {} more than once", kid).into());
+        }
+
+        let pem_bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        let (encoding_key, decoding_key, params) =
+            _load_private_key(algorithm, &pem_bytes).map_err(|e| format!("{}: {}", path, e))?;
+
+        verifying_keys.push(VerifyingKey {
+            kid: kid.to_string(),
+            decoding_key,
+            jwk: Some(Jwk {
+                common: CommonParameters {
+                    public_key_use: Some(PublicKeyUse::Signature),
+                    key_algorithm: Some(match algorithm {
+                        Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+                        _ => KeyAlgorithm::RS256,
+                    }),
+                    key_id: Some(kid.to_string()),
+                    ..Default::default()
+                },
+                algorithm: params,
+            }),
+        });
+
+        if signing_key.is_none() {
+            signing_key = Some(SigningKey {
+                kid: kid.to_string(),
+                encoding_key,
+            });
+        }
+    }
+
+    let signing_key = signing_key.ok_or("JWT_ACCESS_KEYS has no keys")?;
+
+    Ok((signing_key, verifying_keys))
+}
+
+// keys for both directions and the public jwk parameters of a private key PEM
+fn _load_private_key(
+    algorithm: &Algorithm,
+    pem_bytes: &[u8],
+) -> Result<(EncodingKey, DecodingKey, AlgorithmParameters), Box<dyn std::error::Error>> {
+    let parsed = pem::parse(pem_bytes)?;
+
+    match algorithm {
+        Algorithm::EdDSA => {
+            if parsed.tag() != "PRIVATE KEY" {
+                return Err("EdDSA key must be a PKCS#8 private key".into());
+            }
+
+            let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(parsed.contents())
+                .map_err(|e| format!("Invalid Ed25519 key. {}", e))?;
+            let public_key = key_pair.public_key().as_ref();
+
+            Ok((
+                EncodingKey::from_ed_der(parsed.contents()),
+                DecodingKey::from_ed_der(public_key),
+                AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: BASE64URL_NOPAD.encode(public_key),
+                }),
+            ))
+        }
+        Algorithm::RS256 => {
+            let key_pair = match parsed.tag() {
+                "PRIVATE KEY" => RsaKeyPair::from_pkcs8(parsed.contents()),
+                "RSA PRIVATE KEY" => RsaKeyPair::from_der(parsed.contents()),
+                other => return Err(format!("Unsupported RSA key PEM: {}", other).into()),
+            }
+            .map_err(|e| format!("Invalid RSA key. {}", e))?;
+
+            let components: RsaPublicKeyComponents<Vec<u8>> = key_pair.public().into();
+
+            Ok((
+                EncodingKey::from_rsa_pem(pem_bytes)?,
+                DecodingKey::from_rsa_raw_components(&components.n, &components.e),
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: BASE64URL_NOPAD.encode(&components.n),
+                    e: BASE64URL_NOPAD.encode(&components.e),
+                }),
+            ))
+        }
+        _ => Err(format!("Unsupported algorithm {:?}", algorithm).into()),
+    }
 }
 
 fn _generate_jwt(
+    keys: &JwtKeys,
     auth_identity_value: &str,
     token_type_enum: &TokenType,
     exp_time: DateTime<Utc>,
-    secret: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
+    jti: &str,
+    family: Option<&str>,
+) -> Result<String, JwtError> {
     let utc_now = Utc::now();
 
-    let token_type = match token_type_enum {
-        TokenType::Access => "access",
-        TokenType::Refresh => "refresh",
+    // refresh tokens are only read by this service and stay HS512
+    let (algorithm, signing_key) = match token_type_enum {
+        TokenType::Access => (keys.access_algorithm, &keys.access_signing_key),
+        TokenType::Refresh => (Algorithm::HS512, &keys.refresh_signing_key),
     };
 
     // Set the claims
@@ -37,158 +373,353 @@ fn _generate_jwt(
         sub: auth_identity_value.to_string(),
         exp: exp_time.timestamp(),
         iat: utc_now.timestamp(),
-        token_type: token_type.to_string(),
+        token_type: token_type_enum.as_str().to_string(),
+        jti: jti.to_string(),
+        fam: family.map(|f| f.to_string()),
+        nbf: Some(utc_now.timestamp()),
+        iss: keys.issuer.clone(),
+        aud: keys.audience.clone(),
     };
 
+    let mut header = Header::new(algorithm);
+    header.kid = Some(signing_key.kid.to_string());
+
     // encode to get jwt token
-    let token = encode(
-        &Header::new(Algorithm::HS512),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )?;
+    let token = encode(&header, &claims, &signing_key.encoding_key)?;
 
     Ok(token)
 }
 
-fn _decode_jwt(token: &str, secret: &str) -> Result<TokenData<Claims>, Box<dyn std::error::Error>> {
-    let mut validation = Validation::new(Algorithm::HS512);
-    validation.validate_exp = true;
-
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &validation,
-    )?;
-
-    Ok(token_data)
-}
-
-fn _decode_jwt_no_exp_validation(
+// a valid token of the other type is still rejected - access and refresh tokens never stand in for each other
+// nbf is checked when present, iss and aud are required once configured
+fn _decode_jwt(
+    keys: &JwtKeys,
     token: &str,
-    secret: &str,
-) -> Result<TokenData<Claims>, Box<dyn std::error::Error>> {
-    let mut validation = Validation::new(Algorithm::HS512);
-    validation.validate_exp = false;
-
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &validation,
-    )?;
+    token_type_enum: &TokenType,
+    algorithm: Algorithm,
+    decoding_key: &DecodingKey,
+    validate_exp: bool,
+) -> Result<TokenData<Claims>, JwtError> {
+    let mut validation = Validation::new(algorithm);
+    validation.validate_exp = validate_exp;
+    validation.leeway = EXP_LEEWAY_SECONDS;
+    validation.validate_nbf = true;
+
+    let mut required_claims = vec!["exp"];
+    if let Some(issuer) = &keys.issuer {
+        validation.set_issuer(&[issuer]);
+        required_claims.push("iss");
+    }
+    if let Some(audience) = &keys.audience {
+        validation.set_audience(&[audience]);
+        required_claims.push("aud");
+    }
+    validation.set_required_spec_claims(&required_claims);
+
+    let token_data = decode::<Claims>(token, decoding_key, &validation)?;
+
+    if token_data.claims.token_type != token_type_enum.as_str() {
+        return Err(JwtError::WrongTokenType);
+    }
 
     Ok(token_data)
 }
 
 pub fn generate_access_token(
+    keys: &JwtKeys,
     auth_identity_value: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    // get secret
-    let access_secret = env::var("JWT_ACCESS_SECRET").map_err(|e| {
-        log::error!("{}", e);
-        Box::new(e)
-    })?;
-
-    // get exp time
-    let exp_minutes = env::var("ACCESS_TOKEN_EXPIRATION_MINUTES")
-        .map_err(|e| {
-            log::error!("{}", e);
-            Box::new(e)
-        })?
-        .parse::<i64>()
-        .map_err(|e| {
-            log::error!(
-                "Failed to parse ACCESS_TOKEN_EXPIRATION_MINUTES to i64: {}",
-                e
-            );
-            Box::new(e)
-        })?;
-
+) -> Result<String, JwtError> {
     // set the expiration time to be used into the token
-    let utc_now = Utc::now();
-    let expiration = utc_now + Duration::minutes(exp_minutes);
+    let expiration = Utc::now() + keys.access_expiration;
 
     // get access token
     let token = _generate_jwt(
+        keys,
         auth_identity_value,
         &TokenType::Access,
         expiration,
-        &access_secret,
+        &random_alphanumeric(32),
+        None,
     )?;
 
     Ok(token)
 }
 
+// family and jti are tracked server side - see RefreshTokenService
 pub fn generate_refresh_token(
+    keys: &JwtKeys,
     auth_identity_value: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    // get secret
-    let refresh_secret = env::var("JWT_REFRESH_SECRET").map_err(|e| {
-        log::error!("{}", e);
-        Box::new(e)
-    })?;
-
-    // get exp time
-    let exp_days = env::var("REFRESH_TOKEN_EXPIRATION_DAYS")
-        .map_err(|e| {
-            log::error!("{}", e);
-            Box::new(e)
-        })?
-        .parse::<i64>()
-        .map_err(|e| {
-            log::error!(
-                "Failed to parse REFRESH_TOKEN_EXPIRATION_DAYS to i64: {}",
-                e
-            );
-            Box::new(e)
-        })?;
-
+    family: &str,
+    jti: &str,
+) -> Result<String, JwtError> {
     // set the expiration time to be used into the token
-    let utc_now = Utc::now();
-    let expiration = utc_now + Duration::days(exp_days);
+    let expiration = Utc::now() + keys.refresh_expiration;
 
-    // get access token
+    // get refresh token
     let token = _generate_jwt(
+        keys,
         auth_identity_value,
         &TokenType::Refresh,
         expiration,
-        &refresh_secret,
+        jti,
+        Some(family),
     )?;
 
     Ok(token)
 }
 
-pub fn decode_access_token(token: &str) -> Result<TokenData<Claims>, Box<dyn std::error::Error>> {
-    let secret = env::var("JWT_ACCESS_SECRET").map_err(|e| {
-        log::error!("{}", e);
-        Box::new(e)
-    })?;
+pub fn decode_access_token(keys: &JwtKeys, token: &str) -> Result<TokenData<Claims>, JwtError> {
+    let decoding_key = keys.access_decoding_key(token)?;
 
-    let token_data = _decode_jwt(token, &secret)?;
+    let token_data = _decode_jwt(
+        keys,
+        token,
+        &TokenType::Access,
+        keys.access_algorithm,
+        decoding_key,
+        true,
+    )?;
 
     Ok(token_data)
 }
 
 // only useful for getting the claims even when token had expired
 pub fn decode_access_token_no_validation_exp(
+    keys: &JwtKeys,
     token: &str,
-) -> Result<TokenData<Claims>, Box<dyn std::error::Error>> {
-    let secret = env::var("JWT_ACCESS_SECRET").map_err(|e| {
-        log::error!("{}", e);
-        Box::new(e)
-    })?;
+) -> Result<TokenData<Claims>, JwtError> {
+    let decoding_key = keys.access_decoding_key(token)?;
 
-    let token_data = _decode_jwt_no_exp_validation(token, &secret)?;
+    let token_data = _decode_jwt(
+        keys,
+        token,
+        &TokenType::Access,
+        keys.access_algorithm,
+        decoding_key,
+        false,
+    )?;
 
     Ok(token_data)
 }
 
-pub fn decode_refresh_token(token: &str) -> Result<TokenData<Claims>, Box<dyn std::error::Error>> {
-    let secret = env::var("JWT_REFRESH_SECRET").map_err(|e| {
-        log::error!("{}", e);
-        Box::new(e)
-    })?;
-
-    let token_data = _decode_jwt(token, &secret)?;
+pub fn decode_refresh_token(keys: &JwtKeys, token: &str) -> Result<TokenData<Claims>, JwtError> {
+    let token_data = _decode_jwt(
+        keys,
+        token,
+        &TokenType::Refresh,
+        Algorithm::HS512,
+        &keys.refresh_decoding_key,
+        true,
+    )?;
 
     Ok(token_data)
 }
+
+// how long a revoked token has to stay revoked - after that decoding refuses it as expired anyway
+pub fn revoked_until(claims: &Claims) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp(claims.exp.saturating_add(EXP_LEEWAY_SECONDS as i64), 0)
+        .map(|dt| dt.naive_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _config(issuer: Option<&str>, audience: Option<&str>) -> JwtConfig {
+        JwtConfig {
+            access_algorithm: "HS512".to_string(),
+            access_secret: Some("test-access-secret".to_string()),
+            access_keys: None,
+            refresh_secret: "test-refresh-secret".to_string(),
+            access_token_expiration_minutes: 15,
+            refresh_token_expiration_days: 7,
+            refresh_reuse_rotate_authid: false,
+            issuer: issuer.map(|i| i.to_string()),
+            audience: audience.map(|a| a.to_string()),
+        }
+    }
+
+    fn _keys() -> JwtKeys {
+        JwtKeys::from_config(&_config(None, None)).unwrap()
+    }
+
+    // access token claims valid right now, for tests to change one thing of
+    fn _claims() -> Claims {
+        let now = Utc::now().timestamp();
+
+        Claims {
+            sub: "authid".to_string(),
+            exp: now + 900,
+            iat: now,
+            token_type: TokenType::Access.as_str().to_string(),
+            jti: "jti".to_string(),
+            fam: None,
+            nbf: Some(now),
+            iss: None,
+            aud: None,
+        }
+    }
+
+    // signed like an access token, with the access secret and its kid
+    fn _sign(keys: &JwtKeys, claims: &Claims) -> String {
+        let mut header = Header::new(Algorithm::HS512);
+        header.kid = Some(ACCESS_SECRET_KID.to_string());
+        encode(&header, claims, &keys.access_signing_key.encoding_key).unwrap()
+    }
+
+    #[test]
+    fn issued_tokens_decode() {
+        let keys = _keys();
+
+        let access_token = generate_access_token(&keys, "authid").unwrap();
+        let claims = decode_access_token(&keys, &access_token).unwrap().claims;
+        assert_eq!(claims.sub, "authid");
+        assert_eq!(claims.token_type, "access");
+
+        let refresh_token = generate_refresh_token(&keys, "authid", "family", "jti").unwrap();
+        let claims = decode_refresh_token(&keys, &refresh_token).unwrap().claims;
+        assert_eq!(claims.fam.as_deref(), Some("family"));
+        assert_eq!(claims.jti, "jti");
+    }
+
+    #[test]
+    fn wrong_token_type_is_rejected() {
+        let keys = _keys();
+
+        let mut claims = _claims();
+        claims.token_type = TokenType::Refresh.as_str().to_string();
+
+        assert!(matches!(
+            decode_access_token(&keys, &_sign(&keys, &claims)),
+            Err(JwtError::WrongTokenType)
+        ));
+    }
+
+    #[test]
+    fn future_nbf_is_rejected() {
+        let keys = _keys();
+
+        let mut claims = _claims();
+        claims.nbf = Some(Utc::now().timestamp() + 3600);
+
+        assert!(matches!(
+            decode_access_token(&keys, &_sign(&keys, &claims)),
+            Err(JwtError::NotYetValid)
+        ));
+    }
+
+    #[test]
+    fn missing_or_wrong_issuer_and_audience_are_rejected() {
+        let keys = JwtKeys::from_config(&_config(Some("makisama"), Some("makisama-web"))).unwrap();
+
+        let mut claims = _claims();
+        claims.iss = Some("makisama".to_string());
+        claims.aud = Some("makisama-web".to_string());
+        assert!(decode_access_token(&keys, &_sign(&keys, &claims)).is_ok());
+
+        let cases: [(Option<&str>, Option<&str>); 4] = [
+            (None, Some("makisama-web")),
+            (Some("someone-else"), Some("makisama-web")),
+            (Some("makisama"), None),
+            (Some("makisama"), Some("someone-else")),
+        ];
+
+        for (iss, aud) in cases {
+            claims.iss = iss.map(|i| i.to_string());
+            claims.aud = aud.map(|a| a.to_string());
+
+            assert!(
+                matches!(
+                    decode_access_token(&keys, &_sign(&keys, &claims)),
+                    Err(JwtError::InvalidClaims)
+                ),
+                "iss {:?} aud {:?}",
+                iss,
+                aud
+            );
+        }
+    }
+
+    #[test]
+    fn bad_signature_is_rejected() {
+        let keys = _keys();
+
+        let mut other_config = _config(None, None);
+        other_config.access_secret = Some("some-other-secret".to_string());
+        let other_keys = JwtKeys::from_config(&other_config).unwrap();
+
+        // same kid, signed with another secret
+        assert!(matches!(
+            decode_access_token(&keys, &_sign(&other_keys, &_claims())),
+            Err(JwtError::BadSignature)
+        ));
+
+        // a refresh token is signed with the refresh secret
+        let refresh_token = generate_refresh_token(&keys, "authid", "family", "jti").unwrap();
+        let access_token = generate_access_token(&keys, "authid").unwrap();
+        assert!(matches!(
+            decode_refresh_token(&keys, &access_token),
+            Err(JwtError::BadSignature)
+        ));
+        assert!(decode_refresh_token(&keys, &refresh_token).is_ok());
+    }
+
+    #[test]
+    fn unknown_kid_is_rejected() {
+        let keys = _keys();
+
+        let mut header = Header::new(Algorithm::HS512);
+        header.kid = Some("retired-key".to_string());
+        let token = encode(&header, &_claims(), &keys.access_signing_key.encoding_key).unwrap();
+
+        assert!(matches!(
+            decode_access_token(&keys, &token),
+            Err(JwtError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let keys = _keys();
+
+        let mut claims = _claims();
+        claims.iat -= 7200;
+        claims.nbf = Some(claims.iat);
+        claims.exp = Utc::now().timestamp() - 3600;
+        let token = _sign(&keys, &claims);
+
+        assert!(matches!(
+            decode_access_token(&keys, &token),
+            Err(JwtError::Expired)
+        ));
+
+        // claims of an expired token can still be read when asked for
+        assert!(decode_access_token_no_validation_exp(&keys, &token).is_ok());
+    }
+
+    #[test]
+    fn token_is_accepted_until_revoked_until() {
+        let keys = _keys();
+        let now = Utc::now().timestamp();
+
+        // just expired, still inside the leeway - a revocation must still cover it
+        let mut claims = _claims();
+        claims.iat -= 7200;
+        claims.nbf = Some(claims.iat);
+        claims.exp = now - 10;
+        let token = _sign(&keys, &claims);
+
+        assert!(decode_access_token(&keys, &token).is_ok());
+        assert!(revoked_until(&claims).unwrap().and_utc().timestamp() > now);
+
+        // past revoked_until nothing is left to revoke
+        claims.exp = now - EXP_LEEWAY_SECONDS as i64 - 10;
+        let token = _sign(&keys, &claims);
+
+        assert!(revoked_until(&claims).unwrap().and_utc().timestamp() < now);
+        assert!(matches!(
+            decode_access_token(&keys, &token),
+            Err(JwtError::Expired)
+        ));
+    }
+}
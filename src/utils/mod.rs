@@ -1,9 +1,14 @@
 pub mod bcrypt_utils;
+pub mod cache_utils;
+pub mod client_utils;
 pub mod custom_validation_utils;
 pub mod db_utils;
 // pub mod handler_utils;
+pub mod hash_utils;
 pub mod header_utils;
+pub mod image_utils;
 // pub mod json_response_utils;
 pub mod jwt_utils;
 pub mod response_utils;
 pub mod string_utils;
+pub mod totp_utils;
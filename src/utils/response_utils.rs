@@ -1,13 +1,18 @@
 use actix_web::HttpRequest;
 use actix_web::HttpResponse;
+use actix_web::cookie::Cookie;
 use actix_web::cookie::CookieBuilder;
 use actix_web::cookie::time::Duration;
 use actix_web::http::StatusCode;
+use actix_web::http::header::RETRY_AFTER;
 
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::config::app_config::CookieConfig;
 use crate::constants;
+use crate::middlewares::csrf_middleware::CSRF_HEADER;
+use crate::utils::string_utils::random_alphanumeric;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RequestDetails {
@@ -21,10 +26,32 @@ pub struct StatusDetails {
     pub status: String,
 }
 
+// Machine readable reason of an error response - clients branch on these instead of the message
+// token_expired on an access token means refresh now, the others mean log in again
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    TokenMissing,
+    TokenExpired,
+    TokenInvalidSignature,
+    TokenMalformed,
+    TokenWrongType,
+    TokenNotYetValid,
+    TokenInvalidClaims,
+    TokenRevoked,
+    TokenMismatch,
+    CsrfTokenInvalid,
+    OriginNotAllowed,
+    AccountInactive,
+    EmailNotConfirmed,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ResponseDetails<T> {
     request_details: RequestDetails,
     status_details: StatusDetails,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error_code: Option<ErrorCode>,
     payload: T,
 }
 
@@ -41,6 +68,24 @@ impl ResponseMaker {
         return resp_builder.json(ResponseDetails {
             request_details: _get_request_details(&req),
             status_details: _get_status_details(&code),
+            error_code: None,
+            payload,
+        });
+    }
+
+    // same as general_response with an error code clients can act on
+    pub fn error_response<T: Serialize>(
+        req: &HttpRequest,
+        code: &StatusCode,
+        error_code: ErrorCode,
+        payload: T,
+    ) -> HttpResponse {
+        let mut resp_builder = HttpResponse::build(*code);
+        resp_builder.content_type("application/json");
+        return resp_builder.json(ResponseDetails {
+            request_details: _get_request_details(&req),
+            status_details: _get_status_details(&code),
+            error_code: Some(error_code),
             payload,
         });
     }
@@ -54,34 +99,84 @@ impl ResponseMaker {
         );
     }
 
+    // 429 telling the client how many seconds to wait before trying again
+    pub fn too_many_requests_response<T: Serialize>(
+        req: &HttpRequest,
+        retry_after_seconds: i64,
+        payload: T,
+    ) -> HttpResponse {
+        let code = StatusCode::TOO_MANY_REQUESTS;
+        let mut resp_builder = HttpResponse::build(code);
+        resp_builder.content_type("application/json");
+        resp_builder.insert_header((RETRY_AFTER, retry_after_seconds.max(1).to_string()));
+
+        return resp_builder.json(ResponseDetails {
+            request_details: _get_request_details(&req),
+            status_details: _get_status_details(&code),
+            error_code: None,
+            payload,
+        });
+    }
+
     pub fn jwt_response(
         req: &HttpRequest,
         code: &StatusCode,
+        cookie_config: &CookieConfig,
         access_token: &str,
         refresh_token: &str,
     ) -> HttpResponse {
         let mut resp_builder = HttpResponse::build(*code);
         resp_builder.content_type("application/json");
 
-        let refresh_cookie = CookieBuilder::new("refresh_token", refresh_token)
-            .http_only(true)
-            .secure(false) // TODO true on prod
-            // TODO this on prod    .same_site(actix_web::cookie::SameSite::Strict)
-            .same_site(actix_web::cookie::SameSite::Lax)
-            .max_age(Duration::days(7))
-            .path("/api/auth")
-            .finish();
+        resp_builder.cookie(_cookie(
+            cookie_config,
+            cookie_config.refresh_cookie_name(),
+            refresh_token,
+            true,
+        ));
 
-        resp_builder.cookie(refresh_cookie);
+        // double submit csrf token - the client sends it back in the X-CSRF-Token header
+        // a frontend on another origin can't read the cookie, so it also comes in a header
+        let csrf_token = random_alphanumeric(32);
+        resp_builder.cookie(_cookie(
+            cookie_config,
+            cookie_config.csrf_cookie_name(),
+            &csrf_token,
+            false,
+        ));
+        resp_builder.insert_header((CSRF_HEADER, csrf_token));
 
         return resp_builder.json(ResponseDetails {
             request_details: _get_request_details(&req),
             status_details: _get_status_details(&code),
+            error_code: None,
             payload: access_token,
         });
     }
 }
 
+// cookie with the attributes from config, sent only where the refresh cookie is
+fn _cookie(
+    cookie_config: &CookieConfig,
+    name: &'static str,
+    value: &str,
+    http_only: bool,
+) -> Cookie<'static> {
+    let mut cookie = CookieBuilder::new(name, value.to_string())
+        .http_only(http_only)
+        .secure(cookie_config.secure)
+        .same_site(cookie_config.same_site)
+        .max_age(Duration::days(cookie_config.max_age_days))
+        .path(cookie_config.path())
+        .finish();
+
+    if let Some(domain) = &cookie_config.domain {
+        cookie.set_domain(domain.to_string());
+    }
+
+    cookie
+}
+
 fn _get_request_details(req: &HttpRequest) -> RequestDetails {
     RequestDetails {
         path: req.path().to_string(),
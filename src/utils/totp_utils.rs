@@ -0,0 +1,174 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha1::Sha1;
+
+use crate::utils::hash_utils::constant_time_eq;
+
+// RFC 6238 defaults - what every authenticator app expects
+const TOTP_PERIOD_SECONDS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+const TOTP_SECRET_BYTES: usize = 20;
+
+// how many periods before/after the current one are still accepted (clock drift)
+const TOTP_ALLOWED_SKEW: i64 = 1;
+
+// new random secret, base32 encoded without padding as authenticator apps expect
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; TOTP_SECRET_BYTES];
+    rand::rng().fill(&mut bytes);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+// otpauth uri that authenticator apps read from a QR code
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        _percent_encode(issuer),
+        _percent_encode(account),
+        secret,
+        _percent_encode(issuer),
+        TOTP_DIGITS,
+        TOTP_PERIOD_SECONDS
+    )
+}
+
+// time step a unix timestamp falls into
+pub fn time_step(unix_seconds: i64) -> i64 {
+    unix_seconds / TOTP_PERIOD_SECONDS
+}
+
+// check a code against the secret around the given time
+// returns the time step the code matched so callers can refuse to accept it twice
+pub fn verify_code(
+    secret: &str,
+    code: &str,
+    unix_seconds: i64,
+) -> Result<Option<i64>, Box<dyn std::error::Error>> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let key = BASE32_NOPAD.decode(secret.as_bytes())?;
+    let current_step = time_step(unix_seconds);
+
+    for step in (current_step - TOTP_ALLOWED_SKEW)..=(current_step + TOTP_ALLOWED_SKEW) {
+        if step < 0 {
+            continue;
+        }
+
+        let expected = format!(
+            "{:0width$}",
+            _hotp(&key, step as u64)?,
+            width = TOTP_DIGITS as usize
+        );
+
+        if constant_time_eq(expected.as_bytes(), code.as_bytes()) {
+            return Ok(Some(step));
+        }
+    }
+
+    Ok(None)
+}
+
+// RFC 4226 HOTP value for a counter
+fn _hotp(key: &[u8], counter: u64) -> Result<u32, Box<dyn std::error::Error>> {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key)?;
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // dynamic truncation
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = ((digest[offset] as u32 & 0x7f) << 24)
+        | ((digest[offset + 1] as u32) << 16)
+        | ((digest[offset + 2] as u32) << 8)
+        | (digest[offset + 3] as u32);
+
+    Ok(binary % 10u32.pow(TOTP_DIGITS))
+}
+
+// encode everything except unreserved characters (RFC 3986)
+fn _percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // shared secret of the RFC 4226 and RFC 6238 test vectors
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    fn _rfc_secret_base32() -> String {
+        BASE32_NOPAD.encode(RFC_SECRET)
+    }
+
+    // RFC 4226 Appendix D
+    #[test]
+    fn hotp_matches_rfc4226_vectors() {
+        let expected = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(_hotp(RFC_SECRET, counter as u64).unwrap(), *code);
+        }
+    }
+
+    // RFC 6238 Appendix B, SHA1 - the RFC uses 8 digits, its last 6 are the 6 digit code
+    #[test]
+    fn totp_matches_rfc6238_sha1_vectors() {
+        let expected: [(i64, u32); 6] = [
+            (59, 94287082),
+            (1111111109, 7081804),
+            (1111111111, 14050471),
+            (1234567890, 89005924),
+            (2000000000, 69279037),
+            (20000000000, 65353130),
+        ];
+
+        for (unix_seconds, code) in expected {
+            let step = time_step(unix_seconds);
+            assert_eq!(_hotp(RFC_SECRET, step as u64).unwrap(), code % 1_000_000);
+
+            let code = format!("{:06}", code % 1_000_000);
+            assert_eq!(
+                verify_code(&_rfc_secret_base32(), &code, unix_seconds).unwrap(),
+                Some(step)
+            );
+        }
+    }
+
+    #[test]
+    fn verify_code_accepts_one_step_either_side() {
+        let secret = _rfc_secret_base32();
+        let code = format!("{:06}", _hotp(RFC_SECRET, 10).unwrap());
+
+        // step 10 is 300..=329, steps 9 and 11 are still accepted
+        assert_eq!(verify_code(&secret, &code, 269).unwrap(), None);
+        assert_eq!(verify_code(&secret, &code, 270).unwrap(), Some(10));
+        assert_eq!(verify_code(&secret, &code, 315).unwrap(), Some(10));
+        assert_eq!(verify_code(&secret, &code, 359).unwrap(), Some(10));
+        assert_eq!(verify_code(&secret, &code, 360).unwrap(), None);
+    }
+
+    #[test]
+    fn verify_code_refuses_malformed_codes() {
+        let secret = _rfc_secret_base32();
+
+        assert_eq!(verify_code(&secret, "", 59).unwrap(), None);
+        assert_eq!(verify_code(&secret, "28708", 59).unwrap(), None);
+        assert_eq!(verify_code(&secret, "2870822", 59).unwrap(), None);
+        assert_eq!(verify_code(&secret, "28708a", 59).unwrap(), None);
+        assert_eq!(verify_code(&secret, " 287082 ", 59).unwrap(), Some(1));
+    }
+}